@@ -0,0 +1,44 @@
+/* The C API of tp1_rust. Written by `cargo run --bin header`, do not edit. */
+
+#ifndef TP1_RUST_H
+#define TP1_RUST_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* A board, created by tp1_board_from_table or tp1_board_from_fen and freed with
+ * tp1_board_free. */
+typedef struct Tp1Board Tp1Board;
+
+/* Creates a board from the text of a table file, with its lines separated by newlines.
+ * Returns NULL on error, whose message is given by tp1_last_error. */
+Tp1Board *tp1_board_from_table(const char *text);
+
+/* Creates a board from a position in Forsyth-Edwards Notation.
+ * Returns NULL on error, whose message is given by tp1_last_error. */
+Tp1Board *tp1_board_from_fen(const char *fen);
+
+/* Returns 1 if white can capture a black piece, 0 if not, and -1 if the board is NULL. */
+int tp1_board_white_can_capture(const Tp1Board *board);
+
+/* Returns 1 if black can capture a white piece, 0 if not, and -1 if the board is NULL. */
+int tp1_board_black_can_capture(const Tp1Board *board);
+
+/* Returns the verdict of the game, as printed by the command line: "B" if white wins,
+ * "N" if black wins, "E" for a draw, "T" for a draw by a rule and "P" if both
+ * lose. The string is static. Returns NULL if the board is NULL. */
+const char *tp1_board_verdict(const Tp1Board *board);
+
+/* Frees a board. Does nothing if the board is NULL. */
+void tp1_board_free(Tp1Board *board);
+
+/* Returns the message of the last error of the calling thread, or NULL if there was none.
+ * The string belongs to the library and is valid until the next failing call. */
+const char *tp1_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* TP1_RUST_H */
@@ -0,0 +1,18 @@
+use std::env;
+use std::fs;
+use tp1_rust::ffi::c_header;
+
+/// The file the header is written to when none is given.
+const DEFAULT_HEADER: &str = "include/tp1_rust.h";
+
+/// Writes the C header of the library to the file given as argument, or to
+/// `include/tp1_rust.h`.
+fn main() {
+    let file = env::args()
+        .nth(1)
+        .unwrap_or_else(|| DEFAULT_HEADER.to_string());
+    match fs::write(&file, c_header()) {
+        Ok(()) => println!("Header written to {}", file),
+        Err(e) => println!("ERROR: {}", e),
+    }
+}
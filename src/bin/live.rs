@@ -0,0 +1,21 @@
+use std::env;
+use std::net::TcpListener;
+use tp1_rust::protocol::websocket::serve;
+
+/// The address the server listens on when none is given.
+const DEFAULT_ADDRESS: &str = "127.0.0.1:8081";
+
+/// Runs the WebSocket server of live games on the address given as argument, or on
+/// `127.0.0.1:8081`.
+fn main() {
+    let address = env::args()
+        .nth(1)
+        .unwrap_or_else(|| DEFAULT_ADDRESS.to_string());
+    match TcpListener::bind(&address) {
+        Ok(listener) => {
+            println!("Listening on ws://{}", address);
+            serve(listener);
+        }
+        Err(e) => println!("ERROR: Cannot listen on {}: {}", address, e),
+    }
+}
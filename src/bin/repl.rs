@@ -0,0 +1,36 @@
+use std::env;
+use std::io::{self, BufRead, Write};
+use std::sync::Arc;
+use tp1_rust::game::load_position;
+use tp1_rust::protocol::repl::Repl;
+
+/// Runs an interactive shell to edit and query a board, starting from the table file or FEN
+/// given as argument, or from an empty board.
+fn main() {
+    let output = Arc::new(|line: &str| println!("{}", line));
+    let mut repl = match env::args().nth(1) {
+        Some(source) => match load_position(&source) {
+            Ok(table) => Repl::with_table(table, output),
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
+        },
+        None => Repl::new(output),
+    };
+    let prompt = || {
+        print!("> ");
+        let _ = io::stdout().flush();
+    };
+    prompt();
+    for line in io::stdin().lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        if !repl.handle_command(&line) {
+            return;
+        }
+        prompt();
+    }
+}
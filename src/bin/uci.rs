@@ -0,0 +1,19 @@
+use std::io::{self, BufRead};
+use std::sync::Arc;
+use tp1_rust::protocol::uci::Uci;
+
+/// Runs the engine with the Universal Chess Interface protocol, reading commands from the
+/// standard input and answering on the standard output.
+fn main() {
+    let mut uci = Uci::new(Arc::new(|line: &str| println!("{}", line)));
+    for line in io::stdin().lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        if !uci.handle_command(&line) {
+            return;
+        }
+    }
+    uci.wait();
+}
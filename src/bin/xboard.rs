@@ -0,0 +1,18 @@
+use std::io::{self, BufRead};
+use std::sync::Arc;
+use tp1_rust::protocol::xboard::XBoard;
+
+/// Runs the engine with the XBoard protocol, reading commands from the standard input and
+/// answering on the standard output.
+fn main() {
+    let mut xboard = XBoard::new(Arc::new(|line: &str| println!("{}", line)));
+    for line in io::stdin().lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        if !xboard.handle_command(&line) {
+            return;
+        }
+    }
+}
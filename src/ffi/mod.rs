@@ -0,0 +1,325 @@
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::ptr;
+
+use crate::game::game_result;
+use crate::game::table::fen::parse_fen;
+use crate::game::table::{check_moves, parse_table, Table};
+
+/// A Rust type passed to or returned by an exported function, with its name in C.
+trait CType {
+    /// The name of the type in the C header, such as `const char *`.
+    const C: &'static str;
+}
+
+impl CType for *const c_char {
+    const C: &'static str = "const char *";
+}
+
+impl CType for c_int {
+    const C: &'static str = "int";
+}
+
+impl CType for *const Tp1Board {
+    const C: &'static str = "const Tp1Board *";
+}
+
+impl CType for *mut Tp1Board {
+    const C: &'static str = "Tp1Board *";
+}
+
+/// The declaration of an exported function in the C header.
+struct Declaration {
+    /// The lines of the documentation of the function, whose first paragraph is its comment
+    /// in the header.
+    doc: &'static [&'static str],
+    /// The C type returned by the function.
+    ret: &'static str,
+    /// The name of the function.
+    name: &'static str,
+    /// The C type and name of each argument.
+    args: &'static [(&'static str, &'static str)],
+}
+
+impl Declaration {
+    /// Returns the comment of the function in the header, without the comment delimiters.
+    fn comment(&self) -> Vec<&'static str> {
+        self.doc
+            .iter()
+            .map(|line| line.strip_prefix(' ').unwrap_or(line))
+            .take_while(|line| !line.is_empty())
+            .collect()
+    }
+
+    /// Returns the C prototype of the function, such as
+    /// `Tp1Board *tp1_board_from_fen(const char *fen);`.
+    fn prototype(&self) -> String {
+        let join = |c_type: &str, name: &str| {
+            if c_type.ends_with('*') {
+                format!("{}{}", c_type, name)
+            } else {
+                format!("{} {}", c_type, name)
+            }
+        };
+        let args: Vec<String> = self
+            .args
+            .iter()
+            .map(|(c_type, name)| join(c_type, name))
+            .collect();
+        let args = if args.is_empty() {
+            "void".to_string()
+        } else {
+            args.join(", ")
+        };
+        format!("{}({});", join(self.ret, self.name), args)
+    }
+}
+
+/// Defines the exported functions, each as `#[no_mangle] extern "C"`, and their
+/// declarations in `DECLARATIONS`, in the same order, so the C header is written from the
+/// functions themselves.
+///
+/// The first paragraph of the documentation of each function is its comment in the header,
+/// and the types of its arguments and result must implement `CType`.
+macro_rules! c_api {
+    (@ret) => {
+        "void"
+    };
+    (@ret $ret:ty) => {
+        <$ret as CType>::C
+    };
+    (@declare [$($declaration:expr,)*]) => {
+        /// The declarations of the exported functions, in the order of the C header.
+        const DECLARATIONS: &[Declaration] = &[$($declaration,)*];
+    };
+    (
+        @declare [$($declaration:expr,)*]
+        $(#[doc = $doc:literal])*
+        pub unsafe fn $name:ident($($arg:ident: $ty:ty),*) $(-> $ret:ty)? $body:block
+        $($rest:tt)*
+    ) => {
+        $(#[doc = $doc])*
+        #[no_mangle]
+        pub unsafe extern "C" fn $name($($arg: $ty),*) $(-> $ret)? $body
+
+        c_api!(@declare [$($declaration,)* Declaration {
+            doc: &[$($doc),*],
+            ret: c_api!(@ret $($ret)?),
+            name: stringify!($name),
+            args: &[$((<$ty as CType>::C, stringify!($arg))),*],
+        },] $($rest)*);
+    };
+    (
+        @declare [$($declaration:expr,)*]
+        $(#[doc = $doc:literal])*
+        pub fn $name:ident($($arg:ident: $ty:ty),*) $(-> $ret:ty)? $body:block
+        $($rest:tt)*
+    ) => {
+        $(#[doc = $doc])*
+        #[no_mangle]
+        pub extern "C" fn $name($($arg: $ty),*) $(-> $ret)? $body
+
+        c_api!(@declare [$($declaration,)* Declaration {
+            doc: &[$($doc),*],
+            ret: c_api!(@ret $($ret)?),
+            name: stringify!($name),
+            args: &[$((<$ty as CType>::C, stringify!($arg))),*],
+        },] $($rest)*);
+    };
+    ($($functions:tt)*) => {
+        c_api!(@declare [] $($functions)*);
+    };
+}
+
+thread_local! {
+    /// The message of the last error of the thread, as given by `tp1_last_error`.
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// A struct representing a board handed to C, which only knows it through pointers.
+pub struct Tp1Board {
+    /// The table of the board.
+    table: Table,
+}
+
+/// Returns the text of the C header of the functions exported by the library, as found in
+/// `include/tp1_rust.h`.
+///
+/// The header is written by the `header` binary, and a test checks that the file is up to date.
+pub fn c_header() -> String {
+    let mut header = String::from(
+        "/* The C API of tp1_rust. Written by `cargo run --bin header`, do not edit. */\n\n\
+         #ifndef TP1_RUST_H\n#define TP1_RUST_H\n\n\
+         #ifdef __cplusplus\nextern \"C\" {\n#endif\n\n\
+         /* A board, created by tp1_board_from_table or tp1_board_from_fen and freed with\n\
+         \x20* tp1_board_free. */\n\
+         typedef struct Tp1Board Tp1Board;\n",
+    );
+    for declaration in DECLARATIONS {
+        let lines = declaration.comment();
+        header.push_str(&format!("\n/* {}", lines[0]));
+        for line in &lines[1..] {
+            header.push_str(&format!("\n * {}", line));
+        }
+        header.push_str(&format!(" */\n{}\n", declaration.prototype()));
+    }
+    header.push_str("\n#ifdef __cplusplus\n}\n#endif\n\n#endif /* TP1_RUST_H */\n");
+    header
+}
+
+/// Records the message of an error for `tp1_last_error`.
+fn set_error(message: String) {
+    let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|error| *error.borrow_mut() = Some(message));
+}
+
+/// Creates a board with a parser, from a C string.
+///
+/// # Safety
+///
+/// `text` must be NULL or point to a string ending with a NUL byte.
+unsafe fn new_board(
+    text: *const c_char,
+    parse: fn(&str) -> Result<Table, String>,
+) -> *mut Tp1Board {
+    if text.is_null() {
+        set_error("ERROR: The string is NULL".to_string());
+        return ptr::null_mut();
+    }
+    let board = CStr::from_ptr(text)
+        .to_str()
+        .map_err(|_| "ERROR: The string is not valid UTF-8".to_string())
+        .and_then(parse);
+    match board {
+        Ok(table) => Box::into_raw(Box::new(Tp1Board { table })),
+        Err(e) => {
+            set_error(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+c_api! {
+    /// Creates a board from the text of a table file, with its lines separated by newlines.
+    /// Returns NULL on error, whose message is given by tp1_last_error.
+    ///
+    /// # Safety
+    ///
+    /// `text` must be NULL or point to a string ending with a NUL byte.
+    pub unsafe fn tp1_board_from_table(text: *const c_char) -> *mut Tp1Board {
+        new_board(text, |text| {
+            let lines: Vec<String> = text.lines().map(|line| line.to_string()).collect();
+            parse_table(&lines)
+        })
+    }
+
+    /// Creates a board from a position in Forsyth-Edwards Notation.
+    /// Returns NULL on error, whose message is given by tp1_last_error.
+    ///
+    /// # Safety
+    ///
+    /// `fen` must be NULL or point to a string ending with a NUL byte.
+    pub unsafe fn tp1_board_from_fen(fen: *const c_char) -> *mut Tp1Board {
+        new_board(fen, parse_fen)
+    }
+
+    /// Returns 1 if white can capture a black piece, 0 if not, and -1 if the board is NULL.
+    ///
+    /// # Safety
+    ///
+    /// `board` must be NULL or a board that was not freed.
+    pub unsafe fn tp1_board_white_can_capture(board: *const Tp1Board) -> c_int {
+        match board.as_ref() {
+            Some(board) => check_moves(&board.table).0 as c_int,
+            None => -1,
+        }
+    }
+
+    /// Returns 1 if black can capture a white piece, 0 if not, and -1 if the board is NULL.
+    ///
+    /// # Safety
+    ///
+    /// `board` must be NULL or a board that was not freed.
+    pub unsafe fn tp1_board_black_can_capture(board: *const Tp1Board) -> c_int {
+        match board.as_ref() {
+            Some(board) => check_moves(&board.table).1 as c_int,
+            None => -1,
+        }
+    }
+
+    /// Returns the verdict of the game, as printed by the command line: "B" if white wins,
+    /// "N" if black wins, "E" for a draw, "T" for a draw by a rule and "P" if both
+    /// lose. The string is static. Returns NULL if the board is NULL.
+    ///
+    /// # Safety
+    ///
+    /// `board` must be NULL or a board that was not freed.
+    pub unsafe fn tp1_board_verdict(board: *const Tp1Board) -> *const c_char {
+        let board = match board.as_ref() {
+            Some(board) => board,
+            None => return ptr::null(),
+        };
+        let verdict: &'static CStr = match game_result(&board.table).code() {
+            "B" => c"B",
+            "N" => c"N",
+            "E" => c"E",
+            "T" => c"T",
+            _ => c"P",
+        };
+        verdict.as_ptr()
+    }
+
+    /// Frees a board. Does nothing if the board is NULL.
+    ///
+    /// # Safety
+    ///
+    /// `board` must be NULL or a board that was not freed yet.
+    pub unsafe fn tp1_board_free(board: *mut Tp1Board) {
+        if !board.is_null() {
+            drop(Box::from_raw(board));
+        }
+    }
+
+    /// Returns the message of the last error of the calling thread, or NULL if there was none.
+    /// The string belongs to the library and is valid until the next failing call.
+    pub fn tp1_last_error() -> *const c_char {
+        LAST_ERROR.with(|error| {
+            error
+                .borrow()
+                .as_ref()
+                .map_or(ptr::null(), |message| message.as_ptr())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ffi() {
+        unsafe {
+            let board = tp1_board_from_fen(c"3r4/8/8/8/8/8/8/3B4 w - - 0 1".as_ptr());
+            assert_eq!(tp1_board_white_can_capture(board), 0);
+            assert_eq!(tp1_board_black_can_capture(board), 1);
+            assert_eq!(CStr::from_ptr(tp1_board_verdict(board)), c"N");
+            tp1_board_free(board);
+
+            assert!(tp1_board_from_table(c"_ _".as_ptr()).is_null());
+            assert_eq!(
+                CStr::from_ptr(tp1_last_error()).to_str().unwrap(),
+                "ERROR: Table formated incorrectly. Table has 1 rows, expected 8"
+            );
+            assert!(tp1_board_from_fen(ptr::null()).is_null());
+            assert_eq!(tp1_board_white_can_capture(ptr::null()), -1);
+            assert!(tp1_board_verdict(ptr::null()).is_null());
+        }
+    }
+
+    #[test]
+    fn test_c_header() {
+        // The header in the repository must be the one the `header` binary writes
+        let header = std::fs::read_to_string("include/tp1_rust.h").unwrap();
+        assert_eq!(header, c_header());
+    }
+}
@@ -1,11 +1,70 @@
-mod table;
+pub mod table;
 
-use crate::game::table::{check_moves, parse_table};
+use crate::game::table::draw::{draw_reason, DrawReason};
+use crate::game::table::fen::parse_fen;
+use crate::game::table::symmetry::group_equivalent;
+use crate::game::table::variant::Variant;
+use crate::game::table::{check_moves, parse_position, parse_table, Table};
+use crate::utils::read_file;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// The verdict of a game played on a table, as returned by `game_result`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Verdict {
+    /// White wins.
+    White,
+    /// Black wins.
+    Black,
+    /// Both players can capture a piece of the other.
+    Draw,
+    /// The game is drawn by a rule, whatever the pieces can capture.
+    DrawBy(DrawReason),
+    /// Both players lose.
+    BothLose,
+}
+
+impl Verdict {
+    /// Returns the code of the verdict, as described in `play_game`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Verdict::White => "B",
+            Verdict::Black => "N",
+            Verdict::Draw => "E",
+            Verdict::DrawBy(_) => "T",
+            Verdict::BothLose => "P",
+        }
+    }
+
+    /// Returns the rule the game is drawn by, if any.
+    pub fn draw_reason(&self) -> Option<DrawReason> {
+        match self {
+            Verdict::DrawBy(reason) => Some(*reason),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Verdict {
+    /// Writes the code of the verdict, followed by the rule the game is drawn by, such as
+    /// `T (Insufficient material)`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.draw_reason() {
+            Some(reason) => write!(f, "{} ({})", self.code(), reason),
+            None => write!(f, "{}", self.code()),
+        }
+    }
+}
 
 /// Plays a game of chess as described by the input `lines`, and returns the result of the game.
 ///
 /// The input `lines` should be a vector of strings, where each string represents a row of the chess board.
 ///
+/// The variant declared in the table file changes the outcome of a capture: at Atomic the
+/// capturing piece explodes with the captured one, so both lose, and at Antichess the player
+/// whose last piece is captured wins.
+///
 /// # Arguments
 ///
 /// * `lines` - A vector of strings representing the chess board.
@@ -13,31 +72,138 @@ use crate::game::table::{check_moves, parse_table};
 /// # Returns
 ///
 /// * `Ok(String)` - The result of the game, which can be one of the following values:
-///   * "E" - Draw
+///   * "E" - Draw, both players can capture
 ///   * "B" - White wins
 ///   * "N" - Black wins
 ///   * "P" - Both lose
+///   * "T" - Draw by a rule, such as two bare kings, whatever the pieces can capture. The
+///     rule is given by `play_game_with_variant`.
 ///
 /// * `Err(String)` - An error message.
 ///
 pub fn play_game(lines: Vec<String>) -> Result<String, String> {
-    let table = match parse_table(&lines) {
-        Ok(t) => t,
-        Err(e) => return Err(e),
-    };
+    play_game_with_variant(lines, None).map(|verdict| verdict.code().to_string())
+}
 
-    let (w, b) = check_moves(&table);
+/// Plays a game of chess as described by the input `lines` like `play_game`, but with the
+/// rules of `variant` instead of the variant declared in the table file, if it is given.
+///
+/// # Arguments
+///
+/// * `lines` - A vector of strings representing the chess board.
+/// * `variant` - The variant to play, if it is chosen by the caller.
+///
+/// # Returns
+///
+/// * `Ok(Verdict)` - The verdict of the game, with the rule it is drawn by.
+///
+/// * `Err(String)` - An error message.
+///
+pub fn play_game_with_variant(
+    lines: Vec<String>,
+    variant: Option<Variant>,
+) -> Result<Verdict, String> {
+    let mut table = parse_table(&lines)?;
+    if let Some(variant) = variant {
+        table.set_variant(variant);
+    }
+    Ok(game_result(&table))
+}
+
+/// Returns the verdict of the game played on a table, as described in `play_game`.
+///
+/// The table may hold any number of pieces, in which case a color wins if any of its pieces
+/// can capture a piece of the other color, following `check_moves`. The draw rules of
+/// `draw_reason` come first, and a table read from a file has no moves to be repeated.
+///
+/// # Arguments
+///
+/// * `table` - A reference to the `Table` of the game.
+///
+pub fn game_result(table: &Table) -> Verdict {
+    if let Some(reason) = draw_reason(table) {
+        return Verdict::DrawBy(reason);
+    }
+
+    let (mut w, mut b) = check_moves(table);
+    match table.state().variant {
+        Variant::Atomic if w || b => return Verdict::BothLose, // Both pieces explode
+        Variant::Antichess => (w, b) = (b, w),                 // Losing the last piece wins
+        _ => {}
+    }
     if w && b {
-        Ok("E".to_string()) // Draw
+        Verdict::Draw
     } else if w {
-        Ok("B".to_string()) // White wins
+        Verdict::White
     } else if b {
-        Ok("N".to_string()) // Black wins
+        Verdict::Black
+    } else {
+        Verdict::BothLose
+    }
+}
+
+/// Loads a full chess position from a table file or from a FEN string.
+///
+/// # Arguments
+///
+/// * `source` - The path of a `.txt` table file, or a position in Forsyth-Edwards Notation.
+///
+/// # Returns
+///
+/// * `Ok(Table)` - The position described by `source`.
+///
+/// * `Err(String)` - An error message if the file could not be read or the position is invalid.
+///
+pub fn load_position(source: &str) -> Result<Table, String> {
+    if source.ends_with(".txt") {
+        parse_position(&read_file(&source)?)
     } else {
-        Ok("P".to_string()) // Both lose
+        parse_fen(source)
     }
 }
 
+/// Groups the table files of a directory that describe equivalent games.
+///
+/// Two files are equivalent if one table can be turned into the other by mirroring the board
+/// or by swapping colors and flipping the board, as described in the `symmetry` module.
+/// Only files with the `.txt` extension are considered, and files that cannot be parsed into
+/// a valid table are skipped.
+///
+/// # Arguments
+///
+/// * `dir` - The path of the directory containing the table files.
+///
+/// # Returns
+///
+/// * `Ok(Vec<Vec<String>>)` - The file names grouped by equivalence, sorted by name.
+///
+/// * `Err(String)` - An error message if the directory could not be read.
+///
+pub fn dedup_tables(dir: &impl AsRef<Path>) -> Result<Vec<Vec<String>>, String> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => return Err(format!("ERROR: {}", e)),
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "txt"))
+        .collect();
+    paths.sort();
+
+    let mut tables = Vec::new();
+    for path in paths {
+        let lines = read_file(&path)?;
+        if let Ok(table) = parse_table(&lines) {
+            let name = match path.file_name() {
+                Some(name) => name.to_string_lossy().to_string(),
+                None => continue,
+            };
+            tables.push((name, table));
+        }
+    }
+    Ok(group_equivalent(&tables))
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -63,5 +229,103 @@ mod tests {
             play_game(read_file(&"tables/game_P.txt").unwrap()).unwrap(),
             "P"
         );
+
+        // The kings cannot reach each other, but that is a draw rather than a loss for both
+        assert_eq!(
+            play_game(read_file(&"tables/game_kings.txt").unwrap()).unwrap(),
+            "T"
+        );
+        assert_eq!(
+            play_game_with_variant(read_file(&"tables/game_kings.txt").unwrap(), None),
+            Ok(Verdict::DrawBy(DrawReason::InsufficientMaterial))
+        );
+
+        // Only white can capture, which explodes both pieces at Atomic and makes black win at
+        // Antichess, whether the variant is chosen by the caller or in the file
+        let lines = read_file(&"tables/game_B.txt").unwrap();
+        assert_eq!(
+            play_game_with_variant(lines.clone(), Some(Variant::Atomic)),
+            Ok(Verdict::BothLose)
+        );
+        assert_eq!(
+            play_game_with_variant(lines, Some(Variant::Antichess)),
+            Ok(Verdict::Black)
+        );
+        assert_eq!(
+            play_game(read_file(&"tables/game_antichess.txt").unwrap()).unwrap(),
+            "N"
+        );
+    }
+
+    #[test]
+    fn test_game_result_draws() {
+        use super::*;
+        use crate::game::table::fen::{parse_fen, START_FEN};
+        use crate::game::table::moves::make_move;
+        use crate::game::table::notation::parse_uci;
+        use crate::utils::read_file;
+
+        // White can capture the pawn, but not after fifty moves without a capture
+        let mut lines = read_file(&"tables/game_B.txt").unwrap();
+        assert_eq!(play_game(lines.clone()).unwrap(), "B");
+        lines.insert(0, "halfmove: 100".to_string());
+        assert_eq!(play_game(lines.clone()).unwrap(), "T");
+        let verdict = play_game_with_variant(lines, None).unwrap();
+        assert_eq!(verdict, Verdict::DrawBy(DrawReason::FiftyMoveRule));
+        assert_eq!(verdict.to_string(), "T (Fifty-move rule)");
+
+        // Adjacent bare kings can capture each other, and are still drawn by the material
+        let mut lines = read_file(&"tables/game_kings.txt").unwrap();
+        lines[2] = "_ _ _ _ _ r _ _".to_string();
+        lines[7] = "_ _ _ _ _ _ _ _".to_string();
+        assert_eq!(play_game(lines.clone()).unwrap(), "T");
+        assert_eq!(
+            play_game_with_variant(lines, None).unwrap().to_string(),
+            "T (Insufficient material)"
+        );
+
+        // A table read from a file has no moves to repeat, unlike a game played on it
+        let mut table = parse_fen(START_FEN).unwrap();
+        assert_eq!(game_result(&table), Verdict::BothLose);
+        for mv in ["g1f3", "g8f6", "f3g1", "f6g8"].iter().cycle().take(8) {
+            make_move(&mut table, &parse_uci(mv).unwrap()).unwrap();
+        }
+        assert_eq!(
+            game_result(&table),
+            Verdict::DrawBy(DrawReason::ThreefoldRepetition)
+        );
+        assert_eq!(Verdict::Draw.to_string(), "E");
+        assert_eq!(Verdict::Draw.draw_reason(), None);
+    }
+
+    #[test]
+    fn test_dedup_tables() {
+        use super::*;
+        assert_eq!(
+            dedup_tables(&"tables/dedup").unwrap(),
+            vec![
+                vec!["f_high.txt".to_string()],
+                vec!["f_low.txt".to_string(), "f_mirrored.txt".to_string()],
+                vec!["p_left.txt".to_string(), "p_right.txt".to_string()],
+                vec![
+                    "t_left.txt".to_string(),
+                    "t_right.txt".to_string(),
+                    "t_swapped.txt".to_string()
+                ],
+            ]
+        );
+        assert!(dedup_tables(&"tables/missing").is_err());
+    }
+
+    #[test]
+    fn test_load_position() {
+        use super::*;
+        use crate::game::table::fen::START_FEN;
+        assert_eq!(
+            load_position("tables/start.txt").unwrap(),
+            load_position(START_FEN).unwrap()
+        );
+        assert!(load_position("tables/missing.txt").is_err());
+        assert!(load_position("not a fen").is_err());
     }
 }
@@ -0,0 +1,243 @@
+use crate::game::table::piece::position::Position;
+use crate::game::table::piece::{Color, Piece, PieceType};
+use crate::game::table::state::{CastlingFiles, CastlingRights, State};
+use crate::game::table::{Table, BOARD_SIZE};
+
+/// The number of start positions of Chess960.
+pub const CHESS960_POSITIONS: usize = 960;
+
+/// The index of the start position of standard chess.
+pub const STANDARD_INDEX: usize = 518;
+
+/// The placements of the two knights on the five squares left once the bishops and the queen
+/// are placed, by the knight digit of the index.
+const KNIGHTS: [(usize, usize); 10] = [
+    (0, 1),
+    (0, 2),
+    (0, 3),
+    (0, 4),
+    (1, 2),
+    (1, 3),
+    (1, 4),
+    (2, 3),
+    (2, 4),
+    (3, 4),
+];
+
+/// Returns the pieces of the first row of a Chess960 start position, from the a file to the
+/// h file.
+///
+/// Positions are numbered from 0 to 959 as proposed by Reinhard Scharnagl: the index gives,
+/// digit by digit, the file of the bishop on the light squares, the file of the bishop on
+/// the dark squares, the square of the queen among the six left, and the squares of the
+/// knights among the five left. The king stands between the rooks on the last three squares.
+///
+/// # Arguments
+///
+/// * `index` - The index of the position, where `STANDARD_INDEX` is standard chess.
+///
+/// # Errors
+///
+/// Returns an error if `index` is not lower than `CHESS960_POSITIONS`.
+///
+pub fn back_rank(index: usize) -> Result<[PieceType; BOARD_SIZE], String> {
+    if index >= CHESS960_POSITIONS {
+        return Err(format!("ERROR: Invalid Chess960 index: {}", index));
+    }
+    let mut rank = [PieceType::Empty; BOARD_SIZE];
+    let mut n = index;
+    rank[2 * (n % 4) + 1] = PieceType::A;
+    n /= 4;
+    rank[2 * (n % 4)] = PieceType::A;
+    n /= 4;
+
+    let empty = |rank: &[PieceType; BOARD_SIZE]| -> Vec<usize> {
+        (0..BOARD_SIZE)
+            .filter(|x| rank[*x] == PieceType::Empty)
+            .collect()
+    };
+    rank[empty(&rank)[n % 6]] = PieceType::D;
+    n /= 6;
+    let (first, second) = KNIGHTS[n];
+    let squares = empty(&rank);
+    rank[squares[first]] = PieceType::C;
+    rank[squares[second]] = PieceType::C;
+
+    let squares = empty(&rank);
+    rank[squares[0]] = PieceType::T;
+    rank[squares[1]] = PieceType::R;
+    rank[squares[2]] = PieceType::T;
+    Ok(rank)
+}
+
+/// Creates the table of a Chess960 start position, with every castling right and the
+/// Chess960 castling rules.
+///
+/// # Arguments
+///
+/// * `index` - The index of the position, as described in `back_rank`.
+///
+/// # Errors
+///
+/// Returns an error if `index` is not lower than `CHESS960_POSITIONS`.
+///
+pub fn chess960_position(index: usize) -> Result<Table, String> {
+    let rank = back_rank(index)?;
+    let mut pieces = Vec::new();
+    for (x, piece_type) in rank.iter().enumerate() {
+        for (color, row, pawn_row) in [
+            (Color::Black, 0, 1),
+            (Color::White, BOARD_SIZE - 1, BOARD_SIZE - 2),
+        ] {
+            pieces.push(Piece {
+                piece_type: *piece_type,
+                position: Position { x, y: row },
+                color,
+            });
+            pieces.push(Piece {
+                piece_type: PieceType::P,
+                position: Position { x, y: pawn_row },
+                color,
+            });
+        }
+    }
+
+    let rooks: Vec<usize> = (0..BOARD_SIZE)
+        .filter(|x| rank[*x] == PieceType::T)
+        .collect();
+    let state = State {
+        castling: CastlingRights {
+            white_king_side: true,
+            white_queen_side: true,
+            black_king_side: true,
+            black_queen_side: true,
+        },
+        chess960: Some(CastlingFiles {
+            king_side: rooks[1],
+            queen_side: rooks[0],
+        }),
+        ..State::default()
+    };
+    Ok(Table::new(pieces, state))
+}
+
+/// Finds the index of the Chess960 start position with the pieces of a table.
+///
+/// Only the pieces are compared, so the index of a position is found whatever its state.
+///
+/// # Returns
+///
+/// The index of the position, or `None` if the pieces of the table are not those of a
+/// Chess960 start position.
+///
+pub fn chess960_index(table: &Table) -> Option<usize> {
+    if table.pieces().len() != 4 * BOARD_SIZE {
+        return None;
+    }
+    let row = BOARD_SIZE - 1;
+    let rank: Vec<PieceType> = (0..BOARD_SIZE)
+        .map(|x| {
+            table
+                .piece_at(&Position { x, y: row })
+                .filter(|piece| piece.color == Color::White)
+                .map_or(PieceType::Empty, |piece| piece.piece_type)
+        })
+        .collect();
+    let index =
+        (0..CHESS960_POSITIONS).find(|index| back_rank(*index).is_ok_and(|r| r[..] == rank[..]))?;
+    let start = chess960_position(index).ok()?;
+    start
+        .pieces()
+        .iter()
+        .all(|piece| table.piece_at(&piece.position) == Some(piece))
+        .then_some(index)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_chess960_position() {
+        use super::*;
+        use crate::game::table::fen::{format_fen, parse_fen, START_FEN};
+        use crate::game::table::perft::perft;
+
+        let standard = parse_fen(START_FEN).unwrap();
+        assert_eq!(chess960_index(&standard), Some(STANDARD_INDEX));
+        assert_eq!(
+            format_fen(&chess960_position(STANDARD_INDEX).unwrap()),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1"
+        );
+        let table = chess960_position(0).unwrap();
+        assert_eq!(
+            format_fen(&table),
+            "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1"
+        );
+        assert_eq!(chess960_index(&table), Some(0));
+        assert!(chess960_position(CHESS960_POSITIONS).is_err());
+
+        // Every index gives a different position, with the bishops on opposite colors and the
+        // king between the rooks
+        let mut ranks: Vec<[PieceType; BOARD_SIZE]> = (0..CHESS960_POSITIONS)
+            .map(|index| back_rank(index).unwrap())
+            .collect();
+        for rank in &ranks {
+            let find = |piece_type: PieceType| {
+                (0..BOARD_SIZE)
+                    .filter(|x| rank[*x] == piece_type)
+                    .collect::<Vec<usize>>()
+            };
+            let (bishops, rooks, king) =
+                (find(PieceType::A), find(PieceType::T), find(PieceType::R));
+            assert_ne!(bishops[0] % 2, bishops[1] % 2);
+            assert!(rooks[0] < king[0] && king[0] < rooks[1]);
+        }
+        ranks.sort_by_key(|rank| format!("{:?}", rank));
+        ranks.dedup();
+        assert_eq!(ranks.len(), CHESS960_POSITIONS);
+
+        // Known move counts, with castlings where the king or the rook stays in place
+        let mut table =
+            parse_fen("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9").unwrap();
+        assert_eq!(perft(&mut table, 1), 21);
+        assert_eq!(perft(&mut table, 2), 528);
+        assert_eq!(perft(&mut table, 3), 12189);
+        let mut table =
+            parse_fen("2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9").unwrap();
+        assert_eq!(perft(&mut table, 1), 21);
+        assert_eq!(perft(&mut table, 2), 807);
+        assert_eq!(perft(&mut table, 3), 18002);
+    }
+
+    #[test]
+    fn test_chess960_castling() {
+        use crate::game::table::fen::{format_fen, parse_fen};
+        use crate::game::table::moves::{make_move, unmake_move};
+        use crate::game::table::notation::{format_san, parse_san, parse_uci, Language};
+
+        // The king on g1 castles onto the rook on h1 and stays in place
+        let mut table = parse_fen("4k3/8/8/8/8/8/8/1R4KR w HB - 0 1").unwrap();
+        let castle = parse_san(&table, "O-O", Language::English).unwrap();
+        assert_eq!(castle, parse_uci("g1h1").unwrap());
+        let before = format_fen(&table);
+        make_move(&mut table, &castle).unwrap();
+        assert_eq!(format_fen(&table), "4k3/8/8/8/8/8/8/1R3RK1 b - - 1 1");
+        unmake_move(&mut table);
+        assert_eq!(format_fen(&table), before);
+
+        // The rook on b1 and the king swap their squares towards the queen's side
+        let castle = parse_uci("g1b1").unwrap();
+        assert_eq!(
+            format_san(&table, &castle, Language::English).unwrap(),
+            "O-O-O"
+        );
+        make_move(&mut table, &castle).unwrap();
+        assert_eq!(format_fen(&table), "4k3/8/8/8/8/8/8/2KR3R b - - 1 1");
+
+        // X-FEN letters name the outermost rook, and standard squares keep standard rules
+        let table = parse_fen("4k3/8/8/8/8/8/8/RR2K2R w KQ - 0 1").unwrap();
+        assert_eq!(table.state().chess960, None);
+        let table = parse_fen("4k3/8/8/8/8/8/8/1R3K1R w KQ - 0 1").unwrap();
+        assert_eq!(format_fen(&table), "4k3/8/8/8/8/8/8/1R3K1R w HB - 0 1");
+        assert!(parse_fen("4k3/8/8/8/8/8/8/1R3K1R w HA - 0 1").is_err());
+    }
+}
@@ -0,0 +1,180 @@
+use std::fmt;
+
+use crate::game::table::generator::is_checkmate;
+use crate::game::table::moves::unmake_move;
+use crate::game::table::piece::{Color, PieceType};
+use crate::game::table::variant::Variant;
+use crate::game::table::zobrist::zobrist_hash;
+use crate::game::table::Table;
+
+/// The number of times a position must occur for the game to be drawn by repetition.
+pub const REPETITION_LIMIT: usize = 3;
+
+/// The number of half moves without a capture or pawn move after which the game is drawn.
+pub const FIFTY_MOVE_LIMIT: u32 = 100;
+
+/// An enum representing the rule by which a game is drawn.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DrawReason {
+    /// The same position, with the same player to move and the same castling and en passant
+    /// rights, occurred three times.
+    ThreefoldRepetition,
+    /// Fifty moves of each player were played without a capture or a pawn move.
+    FiftyMoveRule,
+    /// Neither player has the pieces needed to checkmate the other.
+    InsufficientMaterial,
+}
+
+impl fmt::Display for DrawReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            DrawReason::ThreefoldRepetition => "Threefold repetition",
+            DrawReason::FiftyMoveRule => "Fifty-move rule",
+            DrawReason::InsufficientMaterial => "Insufficient material",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// Returns the rule by which the game on a table is drawn, if any.
+///
+/// Stalemate is not a draw rule but the lack of legal moves, and is checked with
+/// `is_stalemate`.
+///
+/// # Arguments
+///
+/// * `table` - A reference to the `Table` of the game, whose move history is used to find
+///   repetitions.
+///
+/// # Returns
+///
+/// * `Some(DrawReason)` - The first rule that draws the game, checked in the order
+///   insufficient material, fifty-move rule and threefold repetition.
+///
+/// * `None` - If the game is not drawn by any rule.
+///
+pub fn draw_reason(table: &Table) -> Option<DrawReason> {
+    if is_insufficient_material(table) {
+        Some(DrawReason::InsufficientMaterial)
+    } else if is_fifty_move_rule(table) {
+        Some(DrawReason::FiftyMoveRule)
+    } else if is_threefold_repetition(table) {
+        Some(DrawReason::ThreefoldRepetition)
+    } else {
+        None
+    }
+}
+
+/// Checks if the position of a table occurred at least `REPETITION_LIMIT` times.
+///
+/// Only the positions reached by the moves in the history of the table are known, so
+/// positions before a table was loaded are not counted. The search stops at the last
+/// capture or pawn move, since no earlier position can occur again.
+pub fn is_threefold_repetition(table: &Table) -> bool {
+    let hash = zobrist_hash(table);
+    let mut previous = table.clone();
+    let mut count = 1;
+    for _ in 0..table.state().halfmove_clock {
+        if unmake_move(&mut previous).is_none() {
+            break;
+        }
+        if zobrist_hash(&previous) == hash {
+            count += 1;
+            if count >= REPETITION_LIMIT {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Checks if `FIFTY_MOVE_LIMIT` half moves were played without a capture or pawn move.
+///
+/// A checkmate given on the last of those moves still wins the game.
+pub fn is_fifty_move_rule(table: &Table) -> bool {
+    table.state().halfmove_clock >= FIFTY_MOVE_LIMIT && !is_checkmate(table)
+}
+
+/// Checks if neither player can checkmate the other, whatever moves are played.
+///
+/// That is the case with both kings on the table and no other pieces but either a single
+/// bishop or knight, or any number of bishops all standing on squares of the same color.
+/// Tables missing a king are never drawn by this rule, since their pieces are played for
+/// captures rather than for checkmate, and neither are games of other variants than
+/// standard chess, which can be won without checkmate.
+pub fn is_insufficient_material(table: &Table) -> bool {
+    if table.state().variant != Variant::Standard {
+        return false;
+    }
+    let has_king = |color: Color| {
+        table
+            .pieces()
+            .iter()
+            .any(|piece| piece.piece_type == PieceType::R && piece.color == color)
+    };
+    if !has_king(Color::White) || !has_king(Color::Black) {
+        return false;
+    }
+
+    let others: Vec<_> = table
+        .pieces()
+        .iter()
+        .filter(|piece| piece.piece_type != PieceType::R)
+        .collect();
+    if others
+        .iter()
+        .any(|piece| !matches!(piece.piece_type, PieceType::A | PieceType::C))
+    {
+        return false;
+    }
+    if others.len() <= 1 {
+        return true;
+    }
+    let square_color = |x: usize, y: usize| (x + y) % 2;
+    others.iter().all(|piece| {
+        piece.piece_type == PieceType::A
+            && square_color(piece.position.x, piece.position.y)
+                == square_color(others[0].position.x, others[0].position.y)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_draw_reason() {
+        use super::*;
+        use crate::game::table::fen::{parse_fen, START_FEN};
+        use crate::game::table::moves::make_move;
+        use crate::game::table::notation::parse_uci;
+
+        // The knights go out and back twice, repeating the start position three times
+        let mut table = parse_fen(START_FEN).unwrap();
+        assert_eq!(draw_reason(&table), None);
+        let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        for (i, mv) in shuffle.iter().chain(shuffle.iter()).enumerate() {
+            assert!(!is_threefold_repetition(&table), "after {} moves", i);
+            make_move(&mut table, &parse_uci(mv).unwrap()).unwrap();
+        }
+        assert_eq!(draw_reason(&table), Some(DrawReason::ThreefoldRepetition));
+
+        // The clock counts half moves, and a mate on the last one still wins
+        let table = parse_fen("4k3/8/8/8/8/8/8/R3K3 w - - 100 80").unwrap();
+        assert_eq!(draw_reason(&table), Some(DrawReason::FiftyMoveRule));
+        let table = parse_fen("4k3/8/8/8/8/8/8/R3K3 w - - 99 80").unwrap();
+        assert_eq!(draw_reason(&table), None);
+        let table = parse_fen("R3k3/8/4K3/8/8/8/8/8 b - - 100 80").unwrap();
+        assert!(!is_fifty_move_rule(&table));
+
+        let insufficient = |fen: &str| is_insufficient_material(&parse_fen(fen).unwrap());
+        assert!(insufficient("4k3/8/8/8/8/8/8/4K3 w - - 0 1"));
+        assert!(insufficient("4k3/8/8/8/8/8/8/2N1K3 w - - 0 1"));
+        assert!(insufficient("2b1k3/8/8/8/8/8/8/4KB2 w - - 0 1"));
+        assert!(!insufficient("2b1k3/8/8/8/8/8/8/2B1K3 w - - 0 1"));
+        assert!(!insufficient("4k3/8/8/8/8/8/8/1NN1K3 w - - 0 1"));
+        assert!(!insufficient("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"));
+        assert_eq!(
+            DrawReason::InsufficientMaterial.to_string(),
+            "Insufficient material"
+        );
+    }
+}
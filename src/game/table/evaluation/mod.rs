@@ -0,0 +1,220 @@
+use crate::game::table::generator::push_piece_moves;
+use crate::game::table::piece::{Color, Piece, PieceType};
+use crate::game::table::state::Pocket;
+use crate::game::table::{Table, BOARD_SIZE};
+
+/// The score, in centipawns, of each move a piece can make.
+const MOBILITY_WEIGHT: i32 = 4;
+
+/// The material of each side, without kings and pawns, below which the game is considered
+/// an endgame and kings are better off in the center.
+const ENDGAME_MATERIAL: i32 = 1300;
+
+/// A table with a bonus, in centipawns, for a piece standing on each square.
+///
+/// Rows go from the top row (rank 8) to the bottom row (rank 1), as seen by white. The rows
+/// are flipped for black pieces.
+type PieceSquareTable = [[i32; BOARD_SIZE]; BOARD_SIZE];
+
+const PAWN_TABLE: PieceSquareTable = [
+    [0, 0, 0, 0, 0, 0, 0, 0],
+    [50, 50, 50, 50, 50, 50, 50, 50],
+    [10, 10, 20, 30, 30, 20, 10, 10],
+    [5, 5, 10, 25, 25, 10, 5, 5],
+    [0, 0, 0, 20, 20, 0, 0, 0],
+    [5, -5, -10, 0, 0, -10, -5, 5],
+    [5, 10, 10, -20, -20, 10, 10, 5],
+    [0, 0, 0, 0, 0, 0, 0, 0],
+];
+
+const KNIGHT_TABLE: PieceSquareTable = [
+    [-50, -40, -30, -30, -30, -30, -40, -50],
+    [-40, -20, 0, 0, 0, 0, -20, -40],
+    [-30, 0, 10, 15, 15, 10, 0, -30],
+    [-30, 5, 15, 20, 20, 15, 5, -30],
+    [-30, 0, 15, 20, 20, 15, 0, -30],
+    [-30, 5, 10, 15, 15, 10, 5, -30],
+    [-40, -20, 0, 5, 5, 0, -20, -40],
+    [-50, -40, -30, -30, -30, -30, -40, -50],
+];
+
+const BISHOP_TABLE: PieceSquareTable = [
+    [-20, -10, -10, -10, -10, -10, -10, -20],
+    [-10, 0, 0, 0, 0, 0, 0, -10],
+    [-10, 0, 5, 10, 10, 5, 0, -10],
+    [-10, 5, 5, 10, 10, 5, 5, -10],
+    [-10, 0, 10, 10, 10, 10, 0, -10],
+    [-10, 10, 10, 10, 10, 10, 10, -10],
+    [-10, 5, 0, 0, 0, 0, 5, -10],
+    [-20, -10, -10, -10, -10, -10, -10, -20],
+];
+
+const ROOK_TABLE: PieceSquareTable = [
+    [0, 0, 0, 0, 0, 0, 0, 0],
+    [5, 10, 10, 10, 10, 10, 10, 5],
+    [-5, 0, 0, 0, 0, 0, 0, -5],
+    [-5, 0, 0, 0, 0, 0, 0, -5],
+    [-5, 0, 0, 0, 0, 0, 0, -5],
+    [-5, 0, 0, 0, 0, 0, 0, -5],
+    [-5, 0, 0, 0, 0, 0, 0, -5],
+    [0, 0, 0, 5, 5, 0, 0, 0],
+];
+
+const QUEEN_TABLE: PieceSquareTable = [
+    [-20, -10, -10, -5, -5, -10, -10, -20],
+    [-10, 0, 0, 0, 0, 0, 0, -10],
+    [-10, 0, 5, 5, 5, 5, 0, -10],
+    [-5, 0, 5, 5, 5, 5, 0, -5],
+    [0, 0, 5, 5, 5, 5, 0, -5],
+    [-10, 5, 5, 5, 5, 5, 0, -10],
+    [-10, 0, 5, 0, 0, 0, 0, -10],
+    [-20, -10, -10, -5, -5, -10, -10, -20],
+];
+
+const KING_TABLE: PieceSquareTable = [
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+    [-20, -30, -30, -40, -40, -30, -30, -20],
+    [-10, -20, -20, -20, -20, -20, -20, -10],
+    [20, 20, 0, 0, 0, 0, 20, 20],
+    [20, 30, 10, 0, 0, 10, 30, 20],
+];
+
+const KING_ENDGAME_TABLE: PieceSquareTable = [
+    [-50, -40, -30, -20, -20, -30, -40, -50],
+    [-30, -20, -10, 0, 0, -10, -20, -30],
+    [-30, -10, 20, 30, 30, 20, -10, -30],
+    [-30, -10, 30, 40, 40, 30, -10, -30],
+    [-30, -10, 30, 40, 40, 30, -10, -30],
+    [-30, -10, 20, 30, 30, 20, -10, -30],
+    [-30, -30, 0, 0, 0, 0, -30, -30],
+    [-50, -30, -30, -30, -30, -30, -30, -50],
+];
+
+/// Returns the material value of a piece type, in centipawns.
+///
+/// Kings have no material value, since they cannot be captured, and neither have pieces of
+/// registered kinds, whose strength is unknown.
+pub fn piece_value(piece_type: PieceType) -> i32 {
+    match piece_type {
+        PieceType::P => 100,
+        PieceType::C => 320,
+        PieceType::A => 330,
+        PieceType::T => 500,
+        PieceType::D => 900,
+        PieceType::R | PieceType::Custom(_) | PieceType::Empty => 0,
+    }
+}
+
+/// Evaluates a table from the point of view of the player to move.
+///
+/// The score adds up the material of the pieces, a bonus or penalty for the square each
+/// piece stands on, and the number of moves the knights, bishops, rooks and queens can make.
+/// The pieces held in the pockets at Crazyhouse count for their material.
+///
+/// # Arguments
+///
+/// * `table` - A reference to the `Table` struct containing the pieces.
+///
+/// # Returns
+///
+/// The score in centipawns: positive if the player to move is better, negative if their
+/// opponent is.
+///
+pub fn evaluate(table: &Table) -> i32 {
+    let endgame = is_endgame(table);
+    let mut moves = Vec::new();
+    let mut score = 0;
+    for piece in table.pieces() {
+        moves.clear();
+        if !matches!(piece.piece_type, PieceType::R | PieceType::P) {
+            push_piece_moves(table, piece, &mut moves);
+        }
+        let value = piece_value(piece.piece_type)
+            + square_bonus(piece, endgame)
+            + MOBILITY_WEIGHT * moves.len() as i32;
+        if piece.color == table.state().side_to_move {
+            score += value;
+        } else {
+            score -= value;
+        }
+    }
+    let state = table.state();
+    for piece_type in Pocket::PIECE_TYPES {
+        let held = state.pocket(state.side_to_move).count(piece_type) as i32
+            - state
+                .pocket(state.side_to_move.opposite())
+                .count(piece_type) as i32;
+        score += held * piece_value(piece_type);
+    }
+    score
+}
+
+/// Checks if neither side has more material than `ENDGAME_MATERIAL`, without counting kings
+/// and pawns.
+fn is_endgame(table: &Table) -> bool {
+    [Color::White, Color::Black].iter().all(|color| {
+        table
+            .pieces()
+            .iter()
+            .filter(|piece| piece.color == *color && piece.piece_type != PieceType::P)
+            .map(|piece| piece_value(piece.piece_type))
+            .sum::<i32>()
+            <= ENDGAME_MATERIAL
+    })
+}
+
+/// Returns the bonus of a piece for the square it stands on.
+fn square_bonus(piece: &Piece, endgame: bool) -> i32 {
+    let table = match piece.piece_type {
+        PieceType::P => &PAWN_TABLE,
+        PieceType::C => &KNIGHT_TABLE,
+        PieceType::A => &BISHOP_TABLE,
+        PieceType::T => &ROOK_TABLE,
+        PieceType::D => &QUEEN_TABLE,
+        PieceType::R if endgame => &KING_ENDGAME_TABLE,
+        PieceType::R => &KING_TABLE,
+        PieceType::Custom(_) | PieceType::Empty => return 0,
+    };
+    let row = match piece.color {
+        Color::Black => BOARD_SIZE - 1 - piece.position.y,
+        _ => piece.position.y,
+    };
+    table[row][piece.position.x]
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_evaluate() {
+        use super::*;
+        use crate::game::table::fen::parse_fen;
+        use crate::game::table::fen::START_FEN;
+        use crate::game::table::symmetry::Symmetry;
+        assert_eq!(evaluate(&parse_fen(START_FEN).unwrap()), 0);
+
+        // White is a knight up, so the score depends on the player to move
+        let table = parse_fen("rnbqkb1r/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        assert!(evaluate(&table) > 250);
+        let table = parse_fen("rnbqkb1r/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        assert!(evaluate(&table) < -250);
+
+        // Swapping the colors and the rows gives the same position for the other player
+        let table =
+            parse_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        let swapped = Symmetry {
+            flip_ranks: true,
+            swap_colors: true,
+            ..Symmetry::IDENTITY
+        };
+        assert_eq!(evaluate(&table), evaluate(&swapped.apply(&table)));
+
+        // A king belongs in the center once the queens are gone
+        let center = parse_fen("8/8/8/3k4/8/8/8/K6R b - - 0 1").unwrap();
+        let corner = parse_fen("k7/8/8/8/8/8/8/K6R b - - 0 1").unwrap();
+        assert!(evaluate(&center) > evaluate(&corner));
+    }
+}
@@ -0,0 +1,399 @@
+use crate::game::table::piece::kind::piece_type_from_symbol;
+use crate::game::table::piece::position::Position;
+use crate::game::table::piece::{Color, Piece, PieceType};
+use crate::game::table::state::{CastlingFiles, CastlingRights, Pocket, State};
+use crate::game::table::variant::Variant;
+use crate::game::table::{check_state, Table, BOARD_SIZE};
+
+/// The position at the start of a game, in Forsyth-Edwards Notation.
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// Parses a position in Forsyth-Edwards Notation into a `Table` struct.
+///
+/// FEN uses the English piece letters, uppercase for white and lowercase for black, which is
+/// the opposite of the case used by table files. The halfmove clock and fullmove number are
+/// optional and default to `0` and `1`.
+///
+/// The castling field can also be written as in X-FEN or Shredder-FEN for Chess960, where a
+/// right is the file letter of its rook, such as `HAha`, and `K` or `Q` stand for the
+/// outermost rook on that side of the king. The game follows the Chess960 rules unless
+/// every castling is from the standard squares and written with `KQkq`.
+///
+/// A placement followed by the pockets in brackets, such as `[QNp]`, is a Crazyhouse
+/// position, where a `~` after a piece marks it as promoted from a pawn.
+///
+/// # Arguments
+///
+/// * `fen` - The position in Forsyth-Edwards Notation.
+///
+/// # Errors
+///
+/// Returns an error if a field is missing or invalid, or if the state of the game does not
+/// match the pieces on the table.
+///
+pub fn parse_fen(fen: &str) -> Result<Table, String> {
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+    if fields.len() < 4 || fields.len() > 6 {
+        return Err(format!(
+            "ERROR: FEN has {} fields, expected 4 to 6",
+            fields.len()
+        ));
+    }
+
+    let (placement, pockets) = match fields[0].strip_suffix(']').and_then(|f| f.split_once('[')) {
+        Some((placement, pockets)) => (placement, Some(pockets)),
+        None => (fields[0], None),
+    };
+    let (pieces, promoted) = parse_placement(placement)?;
+    let (castling, chess960) = parse_castling(fields[2], &pieces)?;
+    let mut state = State {
+        side_to_move: match fields[1] {
+            "w" => Color::White,
+            "b" => Color::Black,
+            _ => return Err(format!("ERROR: Invalid FEN side to move: {}", fields[1])),
+        },
+        castling,
+        chess960,
+        en_passant: match fields[3] {
+            "-" => None,
+            square => Some(square.parse::<Position>()?),
+        },
+        ..State::default()
+    };
+    if let Some(pockets) = pockets {
+        state.variant = Variant::Crazyhouse;
+        for c in pockets.chars() {
+            let piece =
+                parse_piece(c).filter(|piece| Pocket::PIECE_TYPES.contains(&piece.piece_type));
+            match piece.and_then(|piece| state.pocket_mut(piece.color).map(|p| (p, piece))) {
+                Some((pocket, piece)) => pocket.add(piece.piece_type),
+                None => return Err(format!("ERROR: Invalid FEN pocket: {}", pockets)),
+            }
+        }
+        for position in &promoted {
+            state.set_promoted(position, true);
+        }
+    }
+    if let Some(halfmove) = fields.get(4) {
+        state.halfmove_clock = match halfmove.parse() {
+            Ok(n) => n,
+            Err(_) => return Err(format!("ERROR: Invalid FEN halfmove clock: {}", halfmove)),
+        };
+    }
+    if let Some(fullmove) = fields.get(5) {
+        state.fullmove_number = match fullmove.parse() {
+            Ok(n) if n > 0 => n,
+            _ => return Err(format!("ERROR: Invalid FEN fullmove number: {}", fullmove)),
+        };
+    }
+
+    let table = Table::new(pieces, state);
+    check_state(&table)?;
+    Ok(table)
+}
+
+/// Formats a `Table` in Forsyth-Edwards Notation.
+///
+/// The castling rights of a Chess960 game are written as in Shredder-FEN, with the file
+/// letters of the rooks, and the pockets of a Crazyhouse game in brackets after the
+/// placement. Custom pieces are written with their letter, which `parse_fen` only reads
+/// back for registered piece kinds, not for the fairy pieces of a table.
+pub fn format_fen(table: &Table) -> String {
+    let state = table.state();
+    let crazyhouse = state.variant == Variant::Crazyhouse;
+    let mut rows = Vec::new();
+    for y in 0..BOARD_SIZE {
+        let mut row = String::new();
+        let mut empty = 0;
+        for x in 0..BOARD_SIZE {
+            match table.piece_at(&Position { x, y }) {
+                Some(piece) => {
+                    if empty > 0 {
+                        row.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    row.push(fen_letter(piece));
+                    if crazyhouse && state.is_promoted(&piece.position) {
+                        row.push('~');
+                    }
+                }
+                None => empty += 1,
+            }
+        }
+        if empty > 0 {
+            row.push_str(&empty.to_string());
+        }
+        rows.push(row);
+    }
+    let mut placement = rows.join("/");
+    if crazyhouse {
+        placement.push('[');
+        for color in [Color::White, Color::Black] {
+            let pocket = state.pocket(color);
+            for piece_type in Pocket::PIECE_TYPES {
+                for _ in 0..pocket.count(piece_type) {
+                    placement.push(fen_letter(&Piece {
+                        piece_type,
+                        position: Position { x: 0, y: 0 },
+                        color,
+                    }));
+                }
+            }
+        }
+        placement.push(']');
+    }
+
+    let side = match state.side_to_move {
+        Color::Black => "b",
+        _ => "w",
+    };
+    let castling = format_castling(&state.castling, state.chess960);
+    let en_passant = match state.en_passant {
+        Some(position) => position.to_string(),
+        None => "-".to_string(),
+    };
+    format!(
+        "{} {} {} {} {} {}",
+        placement, side, castling, en_passant, state.halfmove_clock, state.fullmove_number
+    )
+}
+
+/// Parses the piece placement field of a FEN, returning the pieces and the squares of the
+/// pieces marked as promoted with a `~`.
+fn parse_placement(placement: &str) -> Result<(Vec<Piece>, Vec<Position>), String> {
+    let rows: Vec<&str> = placement.split('/').collect();
+    if rows.len() != BOARD_SIZE {
+        return Err(format!(
+            "ERROR: FEN has {} rows, expected {}",
+            rows.len(),
+            BOARD_SIZE
+        ));
+    }
+    let mut pieces = Vec::new();
+    let mut promoted = Vec::new();
+    for (y, row) in rows.iter().enumerate() {
+        let mut x = 0;
+        for c in row.chars() {
+            if let Some(empty) = c.to_digit(10) {
+                x += empty as usize;
+                continue;
+            }
+            if c == '~' && x > 0 && x <= BOARD_SIZE {
+                promoted.push(Position { x: x - 1, y });
+                continue;
+            }
+            let mut piece = parse_piece(c).ok_or(format!("ERROR: Invalid piece: {}", c))?;
+            if x < BOARD_SIZE {
+                piece.position = Position { x, y };
+                pieces.push(piece);
+            }
+            x += 1;
+        }
+        if x != BOARD_SIZE {
+            return Err(format!(
+                "ERROR: FEN row of rank {} has {} squares, expected {}",
+                BOARD_SIZE - y,
+                x,
+                BOARD_SIZE
+            ));
+        }
+    }
+    Ok((pieces, promoted))
+}
+
+/// Parses the FEN letter of a piece, placed on a8 until it is moved to its square.
+fn parse_piece(c: char) -> Option<Piece> {
+    let piece_type = match c.to_ascii_lowercase() {
+        'k' => PieceType::R,
+        'q' => PieceType::D,
+        'b' => PieceType::A,
+        'n' => PieceType::C,
+        'r' => PieceType::T,
+        'p' => PieceType::P,
+        symbol => match piece_type_from_symbol(symbol) {
+            Some(piece_type @ PieceType::Custom(_)) => piece_type,
+            _ => return None,
+        },
+    };
+    let color = if c.is_ascii_uppercase() {
+        Color::White
+    } else {
+        Color::Black
+    };
+    Some(Piece {
+        piece_type,
+        position: Position { x: 0, y: 0 },
+        color,
+    })
+}
+
+/// Returns the FEN letter of a piece.
+fn fen_letter(piece: &Piece) -> char {
+    let c = match piece.piece_type {
+        PieceType::R => 'k',
+        PieceType::D => 'q',
+        PieceType::A => 'b',
+        PieceType::C => 'n',
+        PieceType::T => 'r',
+        PieceType::P => 'p',
+        PieceType::Custom(symbol) => symbol,
+        PieceType::Empty => '?',
+    };
+    match piece.color {
+        Color::White => c.to_ascii_uppercase(),
+        _ => c,
+    }
+}
+
+/// Parses the castling field of a FEN, such as `KQkq`, `HAha` or `-`, into the castling
+/// rights and the files of the rooks if the game follows the Chess960 rules.
+fn parse_castling(
+    value: &str,
+    pieces: &[Piece],
+) -> Result<(CastlingRights, Option<CastlingFiles>), String> {
+    let invalid = || format!("ERROR: Invalid FEN castling: {}", value);
+    let mut castling = CastlingRights::default();
+    if value == "-" {
+        return Ok((castling, None));
+    }
+    // The file of the rook on each side, king's side first, and whether a right needs the
+    // Chess960 rules
+    let mut files: [Option<usize>; 2] = [None, None];
+    let mut chess960 = false;
+    for c in value.chars() {
+        let (color, y) = if c.is_ascii_uppercase() {
+            (Color::White, BOARD_SIZE - 1)
+        } else {
+            (Color::Black, 0)
+        };
+        let on_row = |piece_type: PieceType| {
+            pieces
+                .iter()
+                .filter(move |p| {
+                    p.piece_type == piece_type && p.color == color && p.position.y == y
+                })
+                .map(|p| p.position.x)
+        };
+        let king_x = on_row(PieceType::R).next().unwrap_or(4);
+        let (king_side, file) = match c.to_ascii_lowercase() {
+            'k' => (
+                true,
+                on_row(PieceType::T)
+                    .filter(|x| *x > king_x)
+                    .max()
+                    .unwrap_or(BOARD_SIZE - 1),
+            ),
+            'q' => (
+                false,
+                on_row(PieceType::T)
+                    .filter(|x| *x < king_x)
+                    .min()
+                    .unwrap_or(0),
+            ),
+            letter @ 'a'..='h' => {
+                chess960 = true;
+                let file = letter as usize - 'a' as usize;
+                (file > king_x, file)
+            }
+            _ => return Err(invalid()),
+        };
+        if king_x != 4 {
+            chess960 = true;
+        }
+        let side = if king_side { 0 } else { 1 };
+        if files[side].is_some_and(|other| other != file) {
+            return Err(invalid());
+        }
+        files[side] = Some(file);
+        let right = match (color, king_side) {
+            (Color::White, true) => &mut castling.white_king_side,
+            (Color::White, false) => &mut castling.white_queen_side,
+            (_, true) => &mut castling.black_king_side,
+            (_, false) => &mut castling.black_queen_side,
+        };
+        if *right {
+            return Err(invalid());
+        }
+        *right = true;
+    }
+    let files = CastlingFiles {
+        king_side: files[0].unwrap_or(BOARD_SIZE - 1),
+        queen_side: files[1].unwrap_or(0),
+    };
+    if chess960 || files != CastlingFiles::STANDARD {
+        Ok((castling, Some(files)))
+    } else {
+        Ok((castling, None))
+    }
+}
+
+/// Formats the castling field of a FEN, with the file letters of the rooks in Chess960.
+fn format_castling(castling: &CastlingRights, chess960: Option<CastlingFiles>) -> String {
+    let letter = |king_side: bool, white: bool| {
+        let c = match chess960 {
+            Some(files) => (b'a' + files.file(king_side) as u8) as char,
+            None if king_side => 'k',
+            None => 'q',
+        };
+        if white {
+            c.to_ascii_uppercase()
+        } else {
+            c
+        }
+    };
+    let rights = [
+        (castling.white_king_side, letter(true, true)),
+        (castling.white_queen_side, letter(false, true)),
+        (castling.black_king_side, letter(true, false)),
+        (castling.black_queen_side, letter(false, false)),
+    ];
+    let value: String = rights
+        .iter()
+        .filter(|(available, _)| *available)
+        .map(|(_, c)| c)
+        .collect();
+    if value.is_empty() {
+        "-".to_string()
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_fen() {
+        use super::*;
+        use crate::game::table::parse_position;
+        use crate::utils::read_file;
+        let table = parse_fen(START_FEN).unwrap();
+        assert_eq!(
+            table,
+            parse_position(&read_file(&"tables/start.txt").unwrap()).unwrap()
+        );
+        assert_eq!(format_fen(&table), START_FEN);
+
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b Kq a3 3 12";
+        let table = parse_fen(fen);
+        assert!(table.is_err());
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/Pp2P3/2N2Q1p/1PPBBPPP/R3K2R b Kq a3 3 12";
+        assert_eq!(format_fen(&parse_fen(fen).unwrap()), fen);
+
+        assert_eq!(
+            parse_fen("8/8/8/8/8/8/8/8 w -").err(),
+            Some("ERROR: FEN has 3 fields, expected 4 to 6".to_string())
+        );
+        assert_eq!(
+            parse_fen("8/8/8/8/8/8/8/9 w - -").err(),
+            Some("ERROR: FEN row of rank 1 has 9 squares, expected 8".to_string())
+        );
+        assert_eq!(
+            parse_fen("8/8/8/8/8/8/8/8 x - -").err(),
+            Some("ERROR: Invalid FEN side to move: x".to_string())
+        );
+        assert_eq!(
+            parse_fen("8/8/8/8/8/8/8/7x w - -").err(),
+            Some("ERROR: Invalid piece: x".to_string())
+        );
+    }
+}
@@ -0,0 +1,363 @@
+use crate::game::table::moves::{castling_squares, is_capture, make_move, unmake_move, Move};
+use crate::game::table::piece::position::Position;
+use crate::game::table::piece::{Color, Piece, PieceType};
+use crate::game::table::state::Pocket;
+use crate::game::table::variant::{variant_winner, Variant};
+use crate::game::table::{check_move_piece, Table, BOARD_SIZE};
+
+/// The steps of a rook, along rows and columns.
+pub const ROOK_STEPS: [(isize, isize); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+/// The steps of a bishop, along diagonals.
+pub const BISHOP_STEPS: [(isize, isize); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+/// The jumps of a knight.
+pub const KNIGHT_JUMPS: [(isize, isize); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+
+/// Checks if a square is attacked by any piece of the given color.
+///
+/// A square is attacked if a piece of that color could capture a piece standing on it,
+/// following the same rules as `check_moves`.
+///
+/// # Arguments
+///
+/// * `table` - A reference to the `Table` struct containing the pieces.
+/// * `position` - The position of the square.
+/// * `by` - The color of the attacking pieces.
+///
+pub fn is_attacked(table: &Table, position: &Position, by: Color) -> bool {
+    table
+        .pieces
+        .iter()
+        .filter(|attacker| attacker.color == by)
+        .any(|attacker| check_move_piece(table, attacker, position))
+}
+
+/// Checks if a king of the given color is attacked. Tables without a king of that color are
+/// never in check.
+///
+/// Kings are never in check at Antichess, where they are ordinary pieces, nor at Atomic when
+/// they stand next to the other king, since capturing it would explode both.
+pub fn is_in_check(table: &Table, color: Color) -> bool {
+    if !table.state.variant.has_royal_king() {
+        return false;
+    }
+    let kings = || {
+        table
+            .pieces
+            .iter()
+            .filter(|piece| piece.piece_type == PieceType::R)
+    };
+    kings().filter(|king| king.color == color).any(|king| {
+        let touching = table.state.variant == Variant::Atomic
+            && kings().any(|other| {
+                other.color == color.opposite()
+                    && other.position.x.abs_diff(king.position.x) <= 1
+                    && other.position.y.abs_diff(king.position.y) <= 1
+            });
+        !touching && is_attacked(table, &king.position, color.opposite())
+    })
+}
+
+/// Checks if the player of a color can leave a table as it is after their move, that is, if
+/// their king is not left in check.
+///
+/// At Atomic, a move that explodes the king of the player is never allowed, and a move that
+/// explodes the other king always is, since it ends the game.
+pub(crate) fn is_legal_after(table: &Table, color: Color) -> bool {
+    if table.state.variant == Variant::Atomic {
+        let has_king = |color: Color| {
+            table
+                .pieces
+                .iter()
+                .any(|piece| piece.piece_type == PieceType::R && piece.color == color)
+        };
+        if !has_king(color) {
+            return false;
+        }
+        if !has_king(color.opposite()) {
+            return true;
+        }
+    }
+    !is_in_check(table, color)
+}
+
+/// Generates the moves of the player to move that follow the movement rules of each piece,
+/// without checking whether they leave the king of the player in check.
+///
+/// # Arguments
+///
+/// * `table` - A reference to the `Table` struct containing the pieces.
+///
+/// # Returns
+///
+/// The moves, including one move per promotion for pawns reaching the last row, castlings
+/// and captures en passant. At Antichess, only the captures are returned if there are any,
+/// since capturing is compulsory. At Crazyhouse, the drops of the pieces in the pocket of the
+/// player come after the moves of the pieces on the table.
+///
+pub fn pseudo_legal_moves(table: &Table) -> Vec<Move> {
+    let color = table.state.side_to_move;
+    let mut moves = Vec::new();
+    for piece in table.pieces.iter().filter(|piece| piece.color == color) {
+        push_piece_moves(table, piece, &mut moves);
+    }
+    if table.state.variant == Variant::Antichess && moves.iter().any(|mv| is_capture(table, mv)) {
+        moves.retain(|mv| is_capture(table, mv));
+    }
+    if table.state.variant == Variant::Crazyhouse {
+        push_drops(table, color, &mut moves);
+    }
+    moves
+}
+
+/// Pushes the drops of every piece in the pocket of a player onto every empty square, but
+/// the first and last rows for pawns.
+fn push_drops(table: &Table, color: Color, moves: &mut Vec<Move>) {
+    let pocket = table.state.pocket(color);
+    for piece_type in Pocket::PIECE_TYPES {
+        if pocket.count(piece_type) == 0 {
+            continue;
+        }
+        for y in 0..BOARD_SIZE {
+            if piece_type == PieceType::P && (y == 0 || y == BOARD_SIZE - 1) {
+                continue;
+            }
+            for x in 0..BOARD_SIZE {
+                let to = Position { x, y };
+                if table.piece_at(&to).is_none() {
+                    moves.push(Move::new_drop(piece_type, to));
+                }
+            }
+        }
+    }
+}
+
+/// Pushes the moves of a single piece that follow its movement rules, whatever the player to
+/// move is.
+///
+/// # Arguments
+///
+/// * `table` - A reference to the `Table` struct containing the pieces.
+/// * `piece` - The piece to move.
+/// * `moves` - The vector where the moves are pushed.
+///
+pub(crate) fn push_piece_moves(table: &Table, piece: &Piece, moves: &mut Vec<Move>) {
+    if let Some(kind) = table.piece_kind(piece.piece_type) {
+        kind.moves(table, piece, moves);
+    }
+}
+
+/// Generates the moves of the player to move that do not leave their king in check.
+///
+/// There are none once the game is won by the rules of its variant, as told by
+/// `variant_winner`.
+///
+/// # Arguments
+///
+/// * `table` - A reference to the `Table` struct containing the pieces.
+///
+pub fn legal_moves(table: &Table) -> Vec<Move> {
+    if variant_winner(table).is_some() {
+        return Vec::new();
+    }
+    let color = table.state.side_to_move;
+    let mut scratch = table.clone();
+    pseudo_legal_moves(table)
+        .into_iter()
+        .filter(|mv| {
+            if make_move(&mut scratch, mv).is_err() {
+                return false;
+            }
+            let legal = is_legal_after(&scratch, color);
+            unmake_move(&mut scratch);
+            legal
+        })
+        .collect()
+}
+
+/// Checks if the player to move is in check and has no legal moves.
+pub fn is_checkmate(table: &Table) -> bool {
+    is_in_check(table, table.state.side_to_move) && legal_moves(table).is_empty()
+}
+
+/// Checks if the player to move is not in check but has no legal moves, while the game is
+/// not won by the rules of its variant.
+pub fn is_stalemate(table: &Table) -> bool {
+    !is_in_check(table, table.state.side_to_move)
+        && legal_moves(table).is_empty()
+        && variant_winner(table).is_none()
+}
+
+/// Returns the position `(dx, dy)` steps away from `position`, if it is on the board.
+pub(crate) fn offset(position: &Position, dx: isize, dy: isize) -> Option<Position> {
+    let x = position.x.checked_add_signed(dx)?;
+    let y = position.y.checked_add_signed(dy)?;
+    Position::new(x, y).ok()
+}
+
+/// Pushes the moves of a piece in the given directions, stopping at the first piece found.
+///
+/// This is how every built-in piece but the pawn moves, and it can be used by registered
+/// piece kinds as well.
+///
+/// # Arguments
+///
+/// * `table` - A reference to the `Table` struct containing the pieces.
+/// * `piece` - The piece to move.
+/// * `steps` - The directions in which the piece moves.
+/// * `slide` - Whether the piece can keep moving in a direction, or only takes one step.
+/// * `moves` - The vector where the moves are pushed.
+///
+pub fn push_steps(
+    table: &Table,
+    piece: &Piece,
+    steps: &[(isize, isize)],
+    slide: bool,
+    moves: &mut Vec<Move>,
+) {
+    for (dx, dy) in steps {
+        let mut current = piece.position;
+        while let Some(to) = offset(&current, *dx, *dy) {
+            match table.piece_at(&to) {
+                Some(other) => {
+                    if other.color != piece.color {
+                        moves.push(Move::new(piece.position, to));
+                    }
+                    break;
+                }
+                None => moves.push(Move::new(piece.position, to)),
+            }
+            if !slide {
+                break;
+            }
+            current = to;
+        }
+    }
+}
+
+/// Pushes the pushes and captures of a pawn, with one move per promotion when the pawn
+/// reaches the last row.
+pub(crate) fn push_pawn_moves(table: &Table, pawn: &Piece, moves: &mut Vec<Move>) {
+    let (dy, start_row) = match pawn.color {
+        Color::White => (-1, BOARD_SIZE - 2),
+        Color::Black => (1, 1),
+        Color::Empty => return,
+    };
+    let mut targets = Vec::new();
+    if let Some(one) = offset(&pawn.position, 0, dy) {
+        if table.piece_at(&one).is_none() {
+            targets.push(one);
+            if pawn.position.y == start_row {
+                if let Some(two) = offset(&one, 0, dy) {
+                    if table.piece_at(&two).is_none() {
+                        targets.push(two);
+                    }
+                }
+            }
+        }
+    }
+    for dx in [-1, 1] {
+        if let Some(to) = offset(&pawn.position, dx, dy) {
+            let enemy = table
+                .piece_at(&to)
+                .is_some_and(|other| other.color != pawn.color);
+            if enemy || table.state.en_passant == Some(to) {
+                targets.push(to);
+            }
+        }
+    }
+    for to in targets {
+        if pawn.promotes_at(&to) {
+            for promotion in table.state.variant.promotions() {
+                moves.push(Move {
+                    from: pawn.position,
+                    to,
+                    promotion: Some(*promotion),
+                    drop: None,
+                });
+            }
+        } else {
+            moves.push(Move::new(pawn.position, to));
+        }
+    }
+}
+
+/// Pushes the castlings available to a king.
+///
+/// A king can castle if the player has the right to, the squares the king and the rook move
+/// over are empty, and the king is not in check nor passes over or lands on an attacked
+/// square. With the Chess960 rules, the castling is written as the king moving onto its
+/// rook. There is no castling at Antichess.
+pub(crate) fn push_castlings(table: &Table, king: &Piece, moves: &mut Vec<Move>) {
+    if table.state.variant == Variant::Antichess {
+        return;
+    }
+    let castling = &table.state.castling;
+    let (king_side, queen_side) = match king.color {
+        Color::White => (castling.white_king_side, castling.white_queen_side),
+        Color::Black => (castling.black_king_side, castling.black_queen_side),
+        Color::Empty => return,
+    };
+    if !(king_side || queen_side) || (table.state.chess960.is_none() && king.position.x != 4) {
+        return;
+    }
+    let row = king.position.y;
+    let enemy = king.color.opposite();
+    let safe = |x: usize| !is_attacked(table, &Position { x, y: row }, enemy);
+    if !safe(king.position.x) {
+        return;
+    }
+    for (available, side) in [(king_side, true), (queen_side, false)] {
+        if !available {
+            continue;
+        }
+        let (king_to, (rook_from, _)) = match castling_squares(table, king, side) {
+            Ok(squares) => squares,
+            Err(_) => continue,
+        };
+        let (low, high) = (
+            king.position.x.min(king_to.x),
+            king.position.x.max(king_to.x),
+        );
+        if (low..=high).all(safe) {
+            let to = match table.state.chess960 {
+                Some(_) => rook_from,
+                None => king_to,
+            };
+            moves.push(Move::new(king.position, to));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_legal_moves() {
+        use super::*;
+        use crate::game::table::parse_position;
+        use crate::utils::read_file;
+        let table = parse_position(&read_file(&"tables/start.txt").unwrap()).unwrap();
+        assert_eq!(legal_moves(&table).len(), 20);
+        assert!(!is_in_check(&table, Color::White));
+
+        let table = parse_position(&read_file(&"tables/castling.txt").unwrap()).unwrap();
+        let moves = legal_moves(&table);
+        // Both castlings, and the promotions of the pawn on b7 to any of the four pieces
+        assert!(moves.contains(&Move::new("e1".parse().unwrap(), "g1".parse().unwrap())));
+        assert!(moves.contains(&Move::new("e1".parse().unwrap(), "c1".parse().unwrap())));
+        assert_eq!(moves.iter().filter(|mv| mv.promotion.is_some()).count(), 8);
+
+        let table = parse_position(&read_file(&"tables/mate.txt").unwrap()).unwrap();
+        assert!(is_in_check(&table, Color::Black));
+        assert!(is_checkmate(&table));
+        assert!(!is_stalemate(&table));
+    }
+}
@@ -1,21 +1,201 @@
+pub mod chess960;
+pub mod draw;
+pub mod evaluation;
+pub mod fen;
+pub mod generator;
+pub mod moves;
+pub mod notation;
+pub mod perft;
+pub mod pgn;
 pub mod piece;
+pub mod problem;
+pub mod search;
+pub mod state;
+pub mod symmetry;
+pub mod tablebase;
+pub mod transposition;
+pub mod variant;
+pub mod zobrist;
 
+use crate::game::table::moves::{Move, Undo};
+use crate::game::table::piece::betza::{format_fairy_pieces, parse_fairy_pieces, Betza};
+use crate::game::table::piece::kind::{piece_kind, piece_type_from_symbol, PieceKind};
 use crate::game::table::piece::position::Position;
 use crate::game::table::piece::{Color, Piece, PieceType};
+use crate::game::table::state::{
+    format_header, is_header_line, parse_header, CastlingFiles, State,
+};
+use crate::game::table::variant::Variant;
 use std::cmp::{max, min};
-const BOARD_SIZE: usize = 8;
+use std::sync::Arc;
+/// The number of rows and columns of the board.
+pub const BOARD_SIZE: usize = 8;
 
-/// A struct representing a table with a white and a black piece.
-#[derive(PartialEq, Debug)]
+/// A struct representing a table: the pieces on it and the state of the game.
+#[derive(Clone, Debug)]
 pub struct Table {
-    /// The white piece on the table.
-    white_piece: Piece,
-    /// The black piece on the table.
-    black_piece: Piece,
+    /// The pieces on the table, at most one per square.
+    pieces: Vec<Piece>,
+    /// The castling rights, en passant square and move counters of the game.
+    state: State,
+    /// The moves applied with `moves::make_move` that can be undone, the last one on top.
+    history: Vec<Undo>,
+    /// The moves undone with `moves::unmake_move` that can be redone, the last one on top.
+    undone: Vec<Move>,
+    /// The fairy pieces defined by the `fairy` header of the table file.
+    fairy: Arc<[Betza]>,
+}
+
+impl PartialEq for Table {
+    /// Two tables are equal if they have the same pieces on the same squares and the same
+    /// state, no matter the order in which the pieces were inserted or the moves played.
+    fn eq(&self, other: &Self) -> bool {
+        self.state == other.state
+            && self.fairy == other.fairy
+            && self.pieces.len() == other.pieces.len()
+            && self
+                .pieces
+                .iter()
+                .all(|piece| other.piece_at(&piece.position) == Some(piece))
+    }
+}
+
+impl Table {
+    /// Creates a new `Table` with the given pieces and state, and no move history.
+    pub fn new(pieces: Vec<Piece>, state: State) -> Table {
+        Table {
+            pieces,
+            state,
+            history: Vec::new(),
+            undone: Vec::new(),
+            fairy: Arc::new([]),
+        }
+    }
+
+    /// Returns the pieces on the table.
+    pub fn pieces(&self) -> &[Piece] {
+        &self.pieces
+    }
+
+    /// Returns the state of the game.
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    /// Returns the fairy pieces defined by the `fairy` header of the table file. They only
+    /// exist on this table and on the tables derived from it, unlike the piece kinds
+    /// registered with `register_piece_kind`.
+    pub fn fairy_pieces(&self) -> &[Betza] {
+        &self.fairy
+    }
+
+    /// Returns the movement rules of a piece type on the table, which are those of its fairy
+    /// pieces or, for the other types, those given by `piece_kind`.
+    pub fn piece_kind(&self, piece_type: PieceType) -> Option<&dyn PieceKind> {
+        let fairy = self
+            .fairy
+            .iter()
+            .find(|kind| PieceType::Custom(kind.symbol()) == piece_type);
+        match fairy {
+            Some(kind) => Some(kind),
+            None => piece_kind(piece_type),
+        }
+    }
+
+    /// Returns the piece type written with a lowercase letter in table files, built-in,
+    /// registered or one of the fairy pieces of the table.
+    pub fn piece_type_from_symbol(&self, symbol: char) -> Option<PieceType> {
+        if self.fairy.iter().any(|kind| kind.symbol() == symbol) {
+            return Some(PieceType::Custom(symbol));
+        }
+        piece_type_from_symbol(symbol)
+    }
+
+    /// Returns the piece on `position`, if there is one.
+    pub fn piece_at(&self, position: &Position) -> Option<&Piece> {
+        self.pieces.iter().find(|piece| piece.position == *position)
+    }
+
+    /// Changes the variant whose rules the game is played with, such as when it is chosen on
+    /// the command line rather than in the table file.
+    pub fn set_variant(&mut self, variant: Variant) {
+        self.state.variant = variant;
+    }
+
+    /// Puts a piece on the square given by its position, replacing the piece standing there.
+    ///
+    /// Editing the pieces clears the moves that can be undone or redone, and the castling
+    /// rights and en passant square that no longer match the pieces.
+    ///
+    /// # Returns
+    ///
+    /// The piece that was replaced, if any.
+    ///
+    pub fn place(&mut self, piece: Piece) -> Option<Piece> {
+        let replaced = self.take(&piece.position);
+        self.pieces.push(piece);
+        self.after_edit();
+        replaced
+    }
+
+    /// Removes the piece on `position` from the table, like `place` edits the pieces.
+    ///
+    /// # Returns
+    ///
+    /// The piece that was removed, or `None` if the square was empty.
+    ///
+    pub fn remove(&mut self, position: &Position) -> Option<Piece> {
+        let removed = self.take(position);
+        self.after_edit();
+        removed
+    }
+
+    /// Takes the piece on `position` out of the table, forgetting that it was promoted.
+    fn take(&mut self, position: &Position) -> Option<Piece> {
+        self.state.set_promoted(position, false);
+        let index = self
+            .pieces
+            .iter()
+            .position(|piece| piece.position == *position)?;
+        Some(self.pieces.swap_remove(index))
+    }
+
+    /// Clears the history and the state that no longer match the pieces after an edit.
+    fn after_edit(&mut self) {
+        self.history.clear();
+        self.undone.clear();
+        let files = self.state.chess960.unwrap_or(CastlingFiles::STANDARD);
+        for (color, king_side) in [
+            (Color::White, true),
+            (Color::White, false),
+            (Color::Black, true),
+            (Color::Black, false),
+        ] {
+            if !can_castle_with(self, color, files.file(king_side)) {
+                let castling = &mut self.state.castling;
+                let right = match (color, king_side) {
+                    (Color::White, true) => &mut castling.white_king_side,
+                    (Color::White, false) => &mut castling.white_queen_side,
+                    (_, true) => &mut castling.black_king_side,
+                    (_, false) => &mut castling.black_queen_side,
+                };
+                *right = false;
+            }
+        }
+        if let Some(en_passant) = self.state.en_passant {
+            if !check_en_passant_square(self, &en_passant) {
+                self.state.en_passant = None;
+            }
+        }
+    }
 }
 
 /// Parses a vector of strings representing a chessboard into a `Table` struct.
 ///
+/// The table must have exactly one white and one black piece. The rows of the chessboard may
+/// be preceded by header lines of the form `key: value` describing the state of the game, as
+/// documented in `state::parse_header`.
+///
 /// # Arguments
 ///
 /// * `lines` - A vector of strings representing a chessboard.
@@ -24,15 +204,68 @@ pub struct Table {
 ///
 /// Returns an error if:
 ///
+/// * A header line is invalid.
 /// * The number of rows in the input `lines` vector is not equal to `BOARD_SIZE`.
 /// * One or both of the white and black pieces are not inserted into the table.
+/// * The state of the game does not match the pieces on the table.
 ///
 ///
-pub fn parse_table(lines: &Vec<String>) -> Result<Table, String> {
-    let mut table = Table {
-        white_piece: Piece::new(),
-        black_piece: Piece::new(),
-    };
+pub fn parse_table(lines: &[String]) -> Result<Table, String> {
+    let table = parse_lines(lines, true)?;
+
+    if !table.pieces.iter().any(|piece| piece.color == Color::White) {
+        return Err("ERROR: No white piece inserted".to_string());
+    }
+    if !table.pieces.iter().any(|piece| piece.color == Color::Black) {
+        return Err("ERROR: No black piece inserted".to_string());
+    }
+    check_state(&table)?;
+
+    Ok(table)
+}
+
+/// Parses a vector of strings representing a chessboard with any number of pieces into a
+/// `Table` struct.
+///
+/// The format is the same as the one read by `parse_table`, but each color may have any
+/// number of pieces, so full chess positions can be described.
+///
+/// # Arguments
+///
+/// * `lines` - A vector of strings representing a chessboard.
+///
+/// # Errors
+///
+/// Returns an error if:
+///
+/// * A header line is invalid.
+/// * The number of rows in the input `lines` vector is not equal to `BOARD_SIZE`.
+/// * The state of the game does not match the pieces on the table.
+///
+pub fn parse_position(lines: &[String]) -> Result<Table, String> {
+    let table = parse_lines(lines, false)?;
+    check_state(&table)?;
+    Ok(table)
+}
+
+/// Parses the header lines and rows of a table file.
+///
+/// # Arguments
+///
+/// * `lines` - A vector of strings representing a chessboard.
+/// * `single` - Whether each color is limited to a single piece.
+///
+fn parse_lines(lines: &[String], single: bool) -> Result<Table, String> {
+    let header_len = lines.iter().take_while(|l| is_header_line(l)).count();
+    let (header, lines) = lines.split_at(header_len);
+    let mut table = Table::new(Vec::new(), parse_header(header)?);
+    if let Some((_, value)) = header
+        .iter()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.trim() == "fairy")
+    {
+        table.fairy = parse_fairy_pieces(value.trim())?.into();
+    }
     if lines.len() != BOARD_SIZE {
         return Err(format!(
             "ERROR: Table formated incorrectly. Table has {} rows, expected {}",
@@ -41,17 +274,142 @@ pub fn parse_table(lines: &Vec<String>) -> Result<Table, String> {
         ));
     }
     for (line_number, line) in lines.iter().enumerate() {
-        parse_line(line.to_string(), &mut table, line_number)?;
+        parse_line(line.to_string(), &mut table, line_number, single)?;
     }
+    Ok(table)
+}
 
-    if let PieceType::Empty = table.white_piece.piece_type {
-        return Err("ERROR: No white piece inserted".to_string());
+/// Checks that the state of the game of a table matches the pieces on it.
+///
+/// # Errors
+///
+/// Returns an error if:
+///
+/// * A castling right is set but the king or the rook are not on their initial squares.
+/// * The en passant square is not right behind a pawn that could have just moved over it.
+///
+fn check_state(table: &Table) -> Result<(), String> {
+    let castling = &table.state.castling;
+    let files = table.state.chess960.unwrap_or(CastlingFiles::STANDARD);
+    let rights = [
+        (castling.white_king_side, Color::White, files.king_side),
+        (castling.white_queen_side, Color::White, files.queen_side),
+        (castling.black_king_side, Color::Black, files.king_side),
+        (castling.black_queen_side, Color::Black, files.queen_side),
+    ];
+    for (available, color, rook_x) in rights {
+        if !available || can_castle_with(table, color, rook_x) {
+            continue;
+        }
+        let y = match color {
+            Color::White => BOARD_SIZE - 1,
+            _ => 0,
+        };
+        return Err(match table.state.chess960 {
+            Some(files) => {
+                format!(
+                    "ERROR: Castling is not possible without king between {} and {} and rook on {}",
+                    Position {
+                        x: files.queen_side,
+                        y
+                    },
+                    Position {
+                        x: files.king_side,
+                        y
+                    },
+                    Position { x: rook_x, y }
+                )
+            }
+            None => format!(
+                "ERROR: Castling is not possible without king on {} and rook on {}",
+                Position { x: 4, y },
+                Position { x: rook_x, y }
+            ),
+        });
     }
-    if let PieceType::Empty = table.black_piece.piece_type {
-        return Err("ERROR: No black piece inserted".to_string());
+    if let Some(en_passant) = table.state.en_passant {
+        if !check_en_passant_square(table, &en_passant) {
+            return Err(format!(
+                "ERROR: No pawn can be captured en passant on {}",
+                en_passant
+            ));
+        }
     }
+    Ok(())
+}
 
-    Ok(table)
+/// Checks that the king of a color and its rook on the file `rook_x` stand on the squares
+/// they castle from. In Chess960 the king starts anywhere between the rooks.
+fn can_castle_with(table: &Table, color: Color, rook_x: usize) -> bool {
+    let y = match color {
+        Color::White => BOARD_SIZE - 1,
+        _ => 0,
+    };
+    let has = |piece_type: PieceType, x: usize| {
+        table.piece_at(&Position { x, y })
+            == Some(&Piece {
+                piece_type,
+                position: Position { x, y },
+                color,
+            })
+    };
+    let has_king = match table.state.chess960 {
+        Some(files) => (files.queen_side + 1..files.king_side).any(|x| has(PieceType::R, x)),
+        None => has(PieceType::R, 4),
+    };
+    has_king && has(PieceType::T, rook_x)
+}
+
+/// Checks that the en passant square of a table is empty and right behind a pawn of the
+/// player who just moved, that is, the opponent of the player to move.
+fn check_en_passant_square(table: &Table, en_passant: &Position) -> bool {
+    let (color, row, pawn_row) = match table.state.side_to_move {
+        Color::White => (Color::Black, 2, 3),
+        Color::Black => (Color::White, BOARD_SIZE - 3, BOARD_SIZE - 4),
+        Color::Empty => return false,
+    };
+    let pawn = Piece {
+        piece_type: PieceType::P,
+        position: Position {
+            x: en_passant.x,
+            y: pawn_row,
+        },
+        color,
+    };
+    en_passant.y == row
+        && table.piece_at(en_passant).is_none()
+        && table.piece_at(&pawn.position) == Some(&pawn)
+}
+
+/// Formats a `Table` back into the rows of a table file.
+///
+/// This is the inverse of `parse_position`: parsing the returned lines yields the same table.
+///
+/// # Arguments
+///
+/// * `table` - A reference to the `Table` to format.
+///
+/// # Returns
+///
+/// The header lines for the state of the game that differs from the default, followed by
+/// `BOARD_SIZE` strings, one per row, with squares separated by a single space.
+///
+pub fn format_table(table: &Table) -> Vec<String> {
+    let mut rows = vec![vec!['_'; BOARD_SIZE]; BOARD_SIZE];
+    for piece in &table.pieces {
+        rows[piece.position.y][piece.position.x] = piece.symbol();
+    }
+    let mut lines = format_header(&table.state);
+    if let Some(fairy) = format_fairy_pieces(table) {
+        lines.push(format!("fairy: {}", fairy));
+    }
+    lines.extend(rows.iter().map(|row| {
+        row.iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<String>>()
+            .join(" ")
+    }));
+    lines
 }
 
 /// Parses a single line of the game board and updates the `table` with any pieces found on the line.
@@ -61,6 +419,7 @@ pub fn parse_table(lines: &Vec<String>) -> Result<Table, String> {
 /// * `line` - The line to parse.
 /// * `table` - A mutable reference to the game board `Table`.
 /// * `line_number` - The line number of the `line` argument.
+/// * `single` - Whether each color is limited to a single piece.
 ///
 /// # Returns
 ///
@@ -74,13 +433,18 @@ pub fn parse_table(lines: &Vec<String>) -> Result<Table, String> {
 /// * The length of the `line` argument is not equal to `BOARD_SIZE * 2 - 1`.
 /// * A piece found on the line is not a single character.
 /// * A character found on the line is not valid.
-/// * More than one white piece or black piece is inserted.
-///
-fn parse_line(line: String, table: &mut Table, line_number: usize) -> Result<(), String> {
+/// * More than one white piece or black piece is inserted when `single` is set.
+///
+fn parse_line(
+    line: String,
+    table: &mut Table,
+    line_number: usize,
+    single: bool,
+) -> Result<(), String> {
     if line.len() != BOARD_SIZE * 2 - 1 {
         return Err(format!(
-            "ERROR: Table formated incorrectly. Row number {} has {} characters, expected {}",
-            line_number,
+            "ERROR: Table formated incorrectly. Row of rank {} has {} characters, expected {}",
+            BOARD_SIZE - line_number,
             line.len(),
             BOARD_SIZE * 2 - 1
         ));
@@ -95,51 +459,40 @@ fn parse_line(line: String, table: &mut Table, line_number: usize) -> Result<(),
                 return Err(format!("ERROR: couldn't extract piece from word: {}", word));
             }
         };
+        if c == '_' {
+            continue;
+        }
 
-        if c.is_ascii_lowercase() {
-            match table.white_piece.piece_type {
-                PieceType::Empty => {
-                    table.white_piece.piece_type = match c {
-                        'r' => PieceType::R,
-                        'd' => PieceType::D,
-                        'a' => PieceType::A,
-                        'c' => PieceType::C,
-                        't' => PieceType::T,
-                        'p' => PieceType::P,
-                        _ => return Err(format!("ERROR: Invalid piece: {}", c)),
-                    };
-                    table.white_piece.position.y = line_number;
-                    table.white_piece.position.x = char_number;
-                    table.white_piece.color = Color::White;
-                }
-                _ => return Err("ERROR: More than one white piece inserted".to_string()),
-            }
+        let color = if c.is_ascii_lowercase() {
+            Color::White
         } else if c.is_ascii_uppercase() {
-            match table.black_piece.piece_type {
-                PieceType::Empty => {
-                    table.black_piece.piece_type = match c {
-                        'R' => PieceType::R,
-                        'D' => PieceType::D,
-                        'A' => PieceType::A,
-                        'C' => PieceType::C,
-                        'T' => PieceType::T,
-                        'P' => PieceType::P,
-                        _ => return Err(format!("ERROR: Invalid piece: {}", c)),
-                    };
-                    table.black_piece.position.y = line_number;
-                    table.black_piece.position.x = char_number;
-                    table.black_piece.color = Color::Black;
-                }
-                _ => return Err("ERROR: More than one black piece inserted".to_string()),
-            }
-        } else if c != '_' {
+            Color::Black
+        } else {
             return Err(format!("ERROR: Invalid piece: {}", c));
+        };
+        if single && table.pieces.iter().any(|piece| piece.color == color) {
+            return match color {
+                Color::White => Err("ERROR: More than one white piece inserted".to_string()),
+                _ => Err("ERROR: More than one black piece inserted".to_string()),
+            };
         }
+        let piece_type = match table.piece_type_from_symbol(c.to_ascii_lowercase()) {
+            Some(piece_type) => piece_type,
+            None => return Err(format!("ERROR: Invalid piece: {}", c)),
+        };
+        table.pieces.push(Piece {
+            piece_type,
+            position: Position {
+                x: char_number,
+                y: line_number,
+            },
+            color,
+        });
     }
     Ok(())
 }
 
-/// Checks if both of the pieces in the table can capture each other.
+/// Checks if the pieces of each color in the table can capture a piece of the other color.
 ///
 /// # Arguments
 ///
@@ -147,41 +500,112 @@ fn parse_line(line: String, table: &mut Table, line_number: usize) -> Result<(),
 ///
 /// # Returns
 ///
-/// A tuple containing two `bool` values. The first element indicates whether the white piece
-/// can attack the black piece, and the second element indicates whether the black piece can
-/// attack the white piece.
+/// A tuple containing two `bool` values. The first element indicates whether a white piece
+/// can attack a black piece, and the second element indicates whether a black piece can
+/// attack a white piece. On a table with a single piece of each color, these are whether
+/// the white piece can attack the black piece and whether the black piece can attack the
+/// white piece.
+///
+/// A pawn that can capture the other pawn en passant is also considered able to attack it.
+/// Queens, bishops and rooks cannot attack through other pieces.
 ///
 pub fn check_moves(table: &Table) -> (bool, bool) {
-    let white_piece = &table.white_piece;
-    let black_piece = &table.black_piece;
+    let can_capture = |color: Color| {
+        table
+            .pieces
+            .iter()
+            .filter(|attacker| attacker.color == color)
+            .any(|attacker| {
+                table
+                    .pieces
+                    .iter()
+                    .filter(|other| other.color != color)
+                    .any(|other| {
+                        check_move_piece(table, attacker, &other.position)
+                            || check_en_passant(attacker, other, &table.state)
+                    })
+            })
+    };
 
-    (
-        check_move_piece(white_piece, black_piece),
-        check_move_piece(black_piece, white_piece),
-    )
+    (can_capture(Color::White), can_capture(Color::Black))
 }
 
-/// Checks if a given attacking piece can capture another piece.
+/// Checks that there are no pieces strictly between two squares on the same row, column or
+/// diagonal. Squares that are not aligned are always considered to have a clear path, since
+/// only knights can move between them.
 ///
-/// The function takes two arguments, `attacker` and `other`, which are references to the `Piece`
-/// struct representing the attacking piece and the other piece respectively. The function returns
-/// `true` if the attacking piece can capture the other piece, and `false` otherwise.
+/// # Arguments
+///
+/// * `table` - A reference to the `Table` struct containing the pieces.
+/// * `from` - The position where the path starts.
+/// * `to` - The position where the path ends.
+///
+fn is_path_clear(table: &Table, from: &Position, to: &Position) -> bool {
+    let dx = to.x as isize - from.x as isize;
+    let dy = to.y as isize - from.y as isize;
+    if !(dx == 0 || dy == 0 || dx.abs() == dy.abs()) {
+        return true;
+    }
+    let steps = max(dx.abs(), dy.abs());
+    (1..steps).all(|i| {
+        let position = Position {
+            x: (from.x as isize + dx.signum() * i) as usize,
+            y: (from.y as isize + dy.signum() * i) as usize,
+        };
+        table.piece_at(&position).is_none()
+    })
+}
+
+/// Checks if a pawn can capture another pawn en passant.
 ///
 /// # Arguments
 ///
 /// * `attacker` - A reference to the `Piece` struct representing the attacking piece.
 /// * `other` - A reference to the `Piece` struct representing the other piece.
+/// * `state` - A reference to the `State` of the game, holding the en passant square.
+///
+/// # Returns
 ///
-fn check_move_piece(attacker: &Piece, other: &Piece) -> bool {
-    match attacker.piece_type {
-        PieceType::R => check_move_r(&attacker.position, &other.position),
-        PieceType::D => check_move_d(&attacker.position, &other.position),
-        PieceType::A => check_move_a(&attacker.position, &other.position),
-        PieceType::C => check_move_c(&attacker.position, &other.position),
-        PieceType::T => check_move_t(&attacker.position, &other.position),
-        PieceType::P => check_move_p(attacker, other),
-        PieceType::Empty => false,
+/// `true` if the attacker is a pawn of the player to move, the other piece is a pawn that
+/// just skipped over the en passant square, and the attacker can move diagonally onto it.
+///
+fn check_en_passant(attacker: &Piece, other: &Piece, state: &State) -> bool {
+    let en_passant = match state.en_passant {
+        Some(en_passant) => en_passant,
+        None => return false,
+    };
+    if attacker.piece_type != PieceType::P
+        || other.piece_type != PieceType::P
+        || attacker.color != state.side_to_move
+    {
+        return false;
     }
+    let forward = match attacker.color {
+        Color::White => attacker.position.y.checked_sub(1),
+        Color::Black => Some(attacker.position.y + 1),
+        Color::Empty => None,
+    };
+    forward == Some(en_passant.y)
+        && attacker.position.x.abs_diff(en_passant.x) == 1
+        && other.position.x == en_passant.x
+        && other.position.y == attacker.position.y
+}
+
+/// Checks if a given attacking piece can capture a piece standing on a square.
+///
+/// The movement rules of the attacker are those of its `PieceKind`, so pieces of registered
+/// kinds are handled like the built-in ones.
+///
+/// # Arguments
+///
+/// * `table` - A reference to the `Table` struct containing the pieces.
+/// * `attacker` - A reference to the `Piece` struct representing the attacking piece.
+/// * `target` - The position of the square attacked.
+///
+fn check_move_piece(table: &Table, attacker: &Piece, target: &Position) -> bool {
+    table
+        .piece_kind(attacker.piece_type)
+        .is_some_and(|kind| kind.attacks(table, attacker, target))
 }
 
 /// Checks if a king can capture another piece.
@@ -203,14 +627,8 @@ fn check_move_piece(attacker: &Piece, other: &Piece) -> bool {
 /// * `false` - If the king cannot capture the other piece.
 ///
 fn check_move_r(attacker_position: &Position, other_position: &Position) -> bool {
-    for i in attacker_position.x - 1..=attacker_position.x + 1 {
-        for j in attacker_position.y - 1..=attacker_position.y + 1 {
-            if i == other_position.x && j == other_position.y {
-                return true;
-            }
-        }
-    }
-    false
+    attacker_position.x.abs_diff(other_position.x) <= 1
+        && attacker_position.y.abs_diff(other_position.y) <= 1
 }
 
 /// Checks if a queen can capture another piece.
@@ -369,9 +787,6 @@ fn check_move_c(attacker_position: &Position, other_position: &Position) -> bool
     }
 
     // Bottom left
-    if attacker_position.x > 1 && attacker_position.y < BOARD_SIZE - 2 {
-        println!("{} {}", attacker_position.x - 2, attacker_position.y + 1)
-    }
     if (attacker_position.x > 0
         && attacker_position.y < BOARD_SIZE - 2
         && attacker_position.x - 1 == other_position.x
@@ -403,15 +818,15 @@ fn check_move_c(attacker_position: &Position, other_position: &Position) -> bool
 
 /// Checks if a given pawn piece can capture another piece.
 ///
-/// The input attacker is the pawn piece which wants to capture the other piece, and the input other
-/// is the other piece on the board. The function returns true if the pawn piece can capture
-/// the other piece.
+/// The input attacker is the pawn piece which wants to capture the other piece, and the input
+/// other_position is the position of the other piece on the board. The function returns true
+/// if the pawn piece can capture the other piece.
 ///
 /// # Arguments
 ///
 /// * attacker - The pawn piece which wants to capture the other piece.
 ///
-/// * other - The other piece on the board.
+/// * other_position - The position of the other piece.
 ///
 /// # Returns
 ///
@@ -419,11 +834,11 @@ fn check_move_c(attacker_position: &Position, other_position: &Position) -> bool
 ///
 /// * false - If the pawn piece cannot capture the other piece.
 ///
-fn check_move_p(attacker: &Piece, other: &Piece) -> bool {
+fn check_move_p(attacker: &Piece, other_position: &Position) -> bool {
     // Pawn moves
     match attacker.color {
-        Color::White => check_move_p_white(&attacker.position, &other.position),
-        Color::Black => check_move_p_black(&attacker.position, &other.position),
+        Color::White => check_move_p_white(&attacker.position, other_position),
+        Color::Black => check_move_p_black(&attacker.position, other_position),
         Color::Empty => false, // Should not happen
     }
 }
@@ -445,21 +860,10 @@ fn check_move_p(attacker: &Piece, other: &Piece) -> bool {
 /// Returns `true` if the white pawn can capture the other piece, and `false` otherwise.
 ///
 fn check_move_p_white(attacker_position: &Position, other_position: &Position) -> bool {
-    // White pawn moves
-
-    if (attacker_position.x > 0
-        && attacker_position.x < BOARD_SIZE - 1
-        && attacker_position.y > 0
+    // White pawns capture one square diagonally towards the top row
+    attacker_position.y > 0
         && attacker_position.y - 1 == other_position.y
-        && ((attacker_position.x - 1 == other_position.x)
-            || (attacker_position.x + 1 == other_position.x)))
-        || (attacker_position.x == 0 && (attacker_position.x + 1 == other_position.x))
-        || (attacker_position.x == BOARD_SIZE - 1 && (attacker_position.x - 1 == other_position.x))
-    {
-        return true;
-    }
-
-    false
+        && attacker_position.x.abs_diff(other_position.x) == 1
 }
 
 /// Checks if a black pawn can capture another piece.
@@ -479,19 +883,9 @@ fn check_move_p_white(attacker_position: &Position, other_position: &Position) -
 /// Returns `true` if the black pawn can capture the other piece, and `false` otherwise.
 ///
 fn check_move_p_black(attacker_position: &Position, other_position: &Position) -> bool {
-    if (attacker_position.x > 0
-        && attacker_position.x < BOARD_SIZE - 1
-        && attacker_position.y < BOARD_SIZE - 1
-        && attacker_position.y + 1 == other_position.y
-        && ((attacker_position.x - 1 == other_position.x)
-            || (attacker_position.x + 1 == other_position.x)))
-        || ((attacker_position.x == 0 && (attacker_position.x + 1 == other_position.x))
-            || (attacker_position.x == BOARD_SIZE - 1
-                && (attacker_position.x - 1 == other_position.x)))
-    {
-        return true;
-    }
-    false
+    // Black pawns capture one square diagonally towards the bottom row
+    attacker_position.y + 1 == other_position.y
+        && attacker_position.x.abs_diff(other_position.x) == 1
 }
 
 #[cfg(test)]
@@ -539,7 +933,7 @@ mod tests {
         assert_eq!(
             parse_table(&lines).err(),
             Some(
-                "ERROR: Table formated incorrectly. Row number 4 has 17 characters, expected 15"
+                "ERROR: Table formated incorrectly. Row of rank 4 has 17 characters, expected 15"
                     .to_string()
             )
         );
@@ -573,18 +967,45 @@ mod tests {
         assert!(parse_table(&lines).is_ok());
         assert_eq!(
             parse_table(&lines).unwrap(),
-            Table {
-                white_piece: Piece {
-                    color: Color::White,
-                    position: Position { x: 5, y: 7 },
-                    piece_type: PieceType::D
-                },
-                black_piece: Piece {
-                    color: Color::Black,
-                    position: Position { x: 5, y: 1 },
-                    piece_type: PieceType::T
-                }
-            }
+            Table::new(
+                vec![
+                    Piece {
+                        color: Color::White,
+                        position: Position { x: 5, y: 7 },
+                        piece_type: PieceType::D
+                    },
+                    Piece {
+                        color: Color::Black,
+                        position: Position { x: 5, y: 1 },
+                        piece_type: PieceType::T
+                    }
+                ],
+                State::default()
+            )
+        );
+
+        let lines = setup_parse_table_test("tables/en_passant.txt");
+
+        assert!(parse_table(&lines).is_ok());
+        assert_eq!(format_table(&parse_table(&lines).unwrap()), lines);
+
+        let mut lines = setup_parse_table_test("tables/castling.txt");
+        lines[0] = "castling: rdRD".to_string();
+        lines[1] = "_ _ _ _ R _ _ T".to_string();
+
+        assert!(parse_position(&lines).is_err());
+        assert_eq!(
+            parse_position(&lines).err(),
+            Some("ERROR: Castling is not possible without king on e8 and rook on a8".to_string())
+        );
+
+        let mut lines = setup_parse_table_test("tables/en_passant.txt");
+        lines.insert(0, "turn: N".to_string());
+
+        assert!(parse_table(&lines).is_err());
+        assert_eq!(
+            parse_table(&lines).err(),
+            Some("ERROR: No pawn can be captured en passant on d6".to_string())
         );
     }
 
@@ -628,5 +1049,12 @@ mod tests {
 
         table = setup_move_test("tables/p_border.txt");
         assert_eq!(check_moves(&table), (false, false));
+
+        table = setup_move_test("tables/en_passant.txt");
+        assert_eq!(check_moves(&table), (true, false));
+
+        // Pawns on the border files only capture diagonally
+        let table = parse_position(&setup_parse_table_test("tables/p_edge.txt")).unwrap();
+        assert_eq!(check_moves(&table), (false, false));
     }
 }
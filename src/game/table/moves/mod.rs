@@ -0,0 +1,646 @@
+use crate::game::table::generator::is_in_check;
+use crate::game::table::piece::position::Position;
+use crate::game::table::piece::{Color, Piece, PieceType};
+use crate::game::table::state::{CastlingFiles, State};
+use crate::game::table::variant::{explosion, Variant};
+use crate::game::table::{Table, BOARD_SIZE};
+
+/// A struct representing a move of a piece from one square to another.
+///
+/// Castling is written as the king moving two squares towards the rook, or as the king moving
+/// onto its own rook with the Chess960 rules, and capturing en passant as the pawn moving
+/// onto the en passant square. Dropping a piece from the pocket at Crazyhouse is written
+/// with the type of the piece, and starts and ends on the square it is dropped on.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Move {
+    /// The position of the piece before the move.
+    pub from: Position,
+    /// The position of the piece after the move.
+    pub to: Position,
+    /// The piece type a pawn is promoted to when it reaches the last row.
+    pub promotion: Option<PieceType>,
+    /// The piece type dropped from the pocket of the player, if the move is a drop.
+    pub drop: Option<PieceType>,
+}
+
+impl Move {
+    /// Creates a new `Move` without promotion.
+    pub fn new(from: Position, to: Position) -> Move {
+        Move {
+            from,
+            to,
+            promotion: None,
+            drop: None,
+        }
+    }
+
+    /// Creates a new `Move` dropping a piece of the pocket of the player on `to`.
+    pub fn new_drop(piece_type: PieceType, to: Position) -> Move {
+        Move {
+            from: to,
+            to,
+            promotion: None,
+            drop: Some(piece_type),
+        }
+    }
+}
+
+/// A struct holding everything needed to take back a move applied to a table.
+#[derive(Clone, Debug)]
+pub struct Undo {
+    /// The move that was applied.
+    played: Move,
+    /// The moved piece as it was before the move, that is, before any promotion.
+    moved: Piece,
+    /// The piece that was captured by the move, if any.
+    captured: Option<Piece>,
+    /// The other pieces removed by the explosion of a capture at Atomic.
+    exploded: Vec<Piece>,
+    /// The square the moved piece landed on, which is not the target of the move when a king
+    /// castles onto its own rook.
+    landed: Position,
+    /// The squares the rook moved from and to when the move was a castling.
+    rook: Option<(Position, Position)>,
+    /// The state of the game before the move.
+    state: State,
+}
+
+/// Applies a move to a table, updating the pieces and the state of the game.
+///
+/// Captured pieces are removed from the table, pawns reaching the last row change their
+/// `PieceType` to the promotion of the move, and castling also moves the rook. The variant
+/// of the game adds its own effects: a capture at Atomic explodes the capturing piece and
+/// the pieces around it, and a check at Three-check is counted. The move is
+/// pushed onto the undo history of the table, and the moves that were undone before are
+/// forgotten, so they can no longer be redone.
+///
+/// Only the basic rules of a move are checked: which pieces can move where is left to the
+/// caller.
+///
+/// # Arguments
+///
+/// * `table` - A mutable reference to the `Table` to change.
+/// * `mv` - A reference to the `Move` to apply.
+///
+/// # Errors
+///
+/// Returns an error, leaving the table untouched, if:
+///
+/// * There is no piece of the player to move on the starting square.
+/// * The piece would capture a piece of its own color.
+/// * A pawn reaching the last row has no promotion, or the promotion is not allowed in the
+///   variant of the game.
+/// * A king castles without the right to do so.
+/// * A piece is dropped that is not in the pocket of the player, on a square that is not
+///   empty, or a pawn is dropped on the first or last row.
+///
+pub fn make_move(table: &mut Table, mv: &Move) -> Result<(), String> {
+    let undo = apply_move(table, mv)?;
+    table.history.push(undo);
+    table.undone.clear();
+    Ok(())
+}
+
+/// Takes back the last move applied to a table.
+///
+/// # Arguments
+///
+/// * `table` - A mutable reference to the `Table` to change.
+///
+/// # Returns
+///
+/// The move that was taken back, which can then be applied again with `redo_move`, or
+/// `None` if no move was applied to the table.
+///
+pub fn unmake_move(table: &mut Table) -> Option<Move> {
+    let undo = table.history.pop()?;
+    let rook_to = undo.rook.map(|(_, to)| to);
+    table
+        .pieces
+        .retain(|piece| piece.position != undo.landed && Some(piece.position) != rook_to);
+    if undo.played.drop.is_none() {
+        table.pieces.push(undo.moved.clone());
+    }
+    if let Some(captured) = undo.captured {
+        table.pieces.push(captured);
+    }
+    table.pieces.extend(undo.exploded);
+    if let Some((from, _)) = undo.rook {
+        table.pieces.push(Piece {
+            piece_type: PieceType::T,
+            position: from,
+            color: undo.moved.color,
+        });
+    }
+    table.state = undo.state;
+    table.undone.push(undo.played);
+    Some(undo.played)
+}
+
+/// Applies again the last move taken back with `unmake_move`.
+///
+/// # Arguments
+///
+/// * `table` - A mutable reference to the `Table` to change.
+///
+/// # Returns
+///
+/// The move that was applied again, or `None` if there is no move to redo.
+///
+pub fn redo_move(table: &mut Table) -> Option<Move> {
+    let mv = table.undone.pop()?;
+    match apply_move(table, &mv) {
+        Ok(undo) => {
+            table.history.push(undo);
+            Some(mv)
+        }
+        Err(_) => None,
+    }
+}
+
+/// Checks if a move captures a piece, including captures en passant.
+pub(crate) fn is_capture(table: &Table, mv: &Move) -> bool {
+    table.piece_at(&mv.to).is_some()
+        || (table.state().en_passant == Some(mv.to)
+            && table
+                .piece_at(&mv.from)
+                .is_some_and(|piece| piece.piece_type == PieceType::P))
+}
+
+/// Returns the moves applied to a table that can be undone, from the first to the last.
+pub fn played_moves(table: &Table) -> Vec<Move> {
+    table.history.iter().map(|undo| undo.played).collect()
+}
+
+/// Applies a move to a table, returning what is needed to take it back.
+fn apply_move(table: &mut Table, mv: &Move) -> Result<Undo, String> {
+    if let Some(piece_type) = mv.drop {
+        return apply_drop(table, mv, piece_type);
+    }
+    let moved = match table.piece_at(&mv.from) {
+        Some(piece) => piece.clone(),
+        None => return Err(format!("ERROR: No piece on {}", mv.from)),
+    };
+    if moved.color != table.state.side_to_move {
+        return Err("ERROR: The piece does not belong to the player to move".to_string());
+    }
+    if mv.from == mv.to {
+        return Err("ERROR: The piece must move to another square".to_string());
+    }
+    let castling = match castling_side(table, mv) {
+        Some(king_side) => Some(castling_squares(table, &moved, king_side)?),
+        None => None,
+    };
+    let mut captured = match castling {
+        Some(_) => None,
+        None => table.piece_at(&mv.to).cloned(),
+    };
+    if captured
+        .as_ref()
+        .is_some_and(|piece| piece.color == moved.color)
+    {
+        return Err("ERROR: A piece cannot capture a piece of its own color".to_string());
+    }
+    check_promotion(&moved, mv, table.state.variant)?;
+
+    if moved.piece_type == PieceType::P
+        && mv.from.x != mv.to.x
+        && captured.is_none()
+        && table.state.en_passant == Some(mv.to)
+    {
+        let position = Position {
+            x: mv.to.x,
+            y: mv.from.y,
+        };
+        captured = table.piece_at(&position).cloned();
+    }
+
+    let exploded = match (&captured, table.state.variant) {
+        (Some(_), Variant::Atomic) => explosion(table, &mv.to, &mv.from),
+        _ => Vec::new(),
+    };
+
+    let landed = castling.map_or(mv.to, |(king_to, _)| king_to);
+    let rook = castling.map(|(_, rook)| rook);
+    let undo = Undo {
+        played: *mv,
+        moved: moved.clone(),
+        captured: captured.clone(),
+        exploded: exploded.clone(),
+        landed,
+        rook,
+        state: table.state,
+    };
+
+    if let Some(captured) = &captured {
+        table
+            .pieces
+            .retain(|piece| piece.position != captured.position);
+    }
+    // The king and the rook are moved together, since in Chess960 each of them can land on
+    // the square the other one leaves
+    let rook_from = rook.map(|(from, _)| from);
+    for piece in table.pieces.iter_mut() {
+        if piece.position == mv.from {
+            piece.position = landed;
+            if let Some(promotion) = mv.promotion {
+                piece.piece_type = promotion;
+            }
+        } else if Some(piece.position) == rook_from {
+            if let Some((_, to)) = rook {
+                piece.position = to;
+            }
+        }
+    }
+    // The capturing piece explodes too, and nothing is left on the square of the capture
+    if captured.is_some() && table.state.variant == Variant::Atomic {
+        table.pieces.retain(|piece| {
+            piece.position != landed && !exploded.iter().any(|e| e.position == piece.position)
+        });
+    }
+    if table.state.variant == Variant::Crazyhouse {
+        update_pockets(&mut table.state, &moved, mv, captured.as_ref());
+    }
+    update_state(&mut table.state, &moved, mv, captured.is_some(), &exploded);
+    if table.state.variant == Variant::ThreeCheck && is_in_check(table, moved.color.opposite()) {
+        match moved.color {
+            Color::White => table.state.checks.0 += 1,
+            Color::Black => table.state.checks.1 += 1,
+            Color::Empty => {}
+        }
+    }
+    Ok(undo)
+}
+
+/// Drops a piece from the pocket of the player to move, returning what is needed to take it
+/// back.
+fn apply_drop(table: &mut Table, mv: &Move, piece_type: PieceType) -> Result<Undo, String> {
+    let color = table.state.side_to_move;
+    if table.piece_at(&mv.to).is_some() {
+        return Err(format!(
+            "ERROR: Cannot drop on an occupied square: {}",
+            mv.to
+        ));
+    }
+    if piece_type == PieceType::P && (mv.to.y == 0 || mv.to.y == BOARD_SIZE - 1) {
+        return Err("ERROR: A pawn cannot be dropped on the first or last row".to_string());
+    }
+    let state = table.state;
+    if !table
+        .state
+        .pocket_mut(color)
+        .is_some_and(|pocket| pocket.remove(piece_type))
+    {
+        return Err(format!("ERROR: No piece to drop: {:?}", piece_type));
+    }
+
+    let dropped = Piece {
+        piece_type,
+        position: mv.to,
+        color,
+    };
+    table.pieces.push(dropped.clone());
+    update_state(&mut table.state, &dropped, mv, false, &[]);
+    Ok(Undo {
+        played: *mv,
+        moved: dropped,
+        captured: None,
+        exploded: Vec::new(),
+        landed: mv.to,
+        rook: None,
+        state,
+    })
+}
+
+/// Puts a piece captured at Crazyhouse into the pocket of the capturing player, as a pawn if
+/// it was promoted, and keeps track of the promoted pieces.
+///
+/// # Arguments
+///
+/// * `state` - A mutable reference to the `State` to update.
+/// * `moved` - The moved piece as it was before the move.
+/// * `mv` - The move that was applied.
+/// * `captured` - The piece captured by the move, if any.
+///
+fn update_pockets(state: &mut State, moved: &Piece, mv: &Move, captured: Option<&Piece>) {
+    if let Some(captured) = captured {
+        let piece_type = if state.is_promoted(&captured.position) {
+            PieceType::P
+        } else {
+            captured.piece_type
+        };
+        if let Some(pocket) = state.pocket_mut(moved.color) {
+            pocket.add(piece_type);
+        }
+        state.set_promoted(&captured.position, false);
+    }
+    let promoted = state.is_promoted(&mv.from) || mv.promotion.is_some();
+    state.set_promoted(&mv.from, false);
+    state.set_promoted(&mv.to, promoted);
+}
+
+/// Checks that a move has a promotion if and only if it moves a pawn to the last row, and
+/// that the pawn is promoted to one of the promotions of the variant.
+fn check_promotion(moved: &Piece, mv: &Move, variant: Variant) -> Result<(), String> {
+    match (moved.promotes_at(&mv.to), mv.promotion) {
+        (true, None) => Err("ERROR: A pawn reaching the last row must be promoted".to_string()),
+        (true, Some(promotion)) if !variant.promotions().contains(&promotion) => {
+            Err(format!("ERROR: Invalid promotion: {:?}", promotion))
+        }
+        (false, Some(_)) => {
+            Err("ERROR: Only a pawn reaching the last row can be promoted".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Returns the side a king castles to with a move, `Some(true)` for the king's side, or
+/// `None` if the move is not a castling.
+///
+/// In standard chess a king castles by moving two squares towards the rook. With the
+/// Chess960 rules it castles by moving onto its own rook, since it can land one square away
+/// or stay where it is.
+pub(crate) fn castling_side(table: &Table, mv: &Move) -> Option<bool> {
+    let king = table
+        .piece_at(&mv.from)
+        .filter(|piece| piece.piece_type == PieceType::R)?;
+    if mv.from.y != mv.to.y {
+        return None;
+    }
+    let castles = match table.state.chess960 {
+        None => mv.from.x.abs_diff(mv.to.x) == 2,
+        Some(_) => table
+            .piece_at(&mv.to)
+            .is_some_and(|piece| piece.piece_type == PieceType::T && piece.color == king.color),
+    };
+    castles.then_some(mv.to.x > mv.from.x)
+}
+
+/// Finds the squares a king lands on when it castles, and the squares its rook moves from
+/// and to.
+///
+/// Whatever the files of the king and the rook, the king lands on the g or c file and the
+/// rook next to it, on the f or d file.
+///
+/// # Errors
+///
+/// Returns an error if the player has no right to castle on that side, if the rook is
+/// missing, or if the squares the king and the rook move over are not empty.
+///
+pub(crate) fn castling_squares(
+    table: &Table,
+    king: &Piece,
+    king_side: bool,
+) -> Result<(Position, (Position, Position)), String> {
+    let castling = &table.state.castling;
+    let available = match (king.color, king_side) {
+        (Color::White, true) => castling.white_king_side,
+        (Color::White, false) => castling.white_queen_side,
+        (Color::Black, true) => castling.black_king_side,
+        (Color::Black, false) => castling.black_queen_side,
+        (Color::Empty, _) => false,
+    };
+    let files = table.state.chess960.unwrap_or(CastlingFiles::STANDARD);
+    let row = king.position.y;
+    let (king_to_x, rook_to_x) = if king_side {
+        (BOARD_SIZE - 2, BOARD_SIZE - 3)
+    } else {
+        (2, 3)
+    };
+    let rook_from = Position {
+        x: files.file(king_side),
+        y: row,
+    };
+    let has_rook = table
+        .piece_at(&rook_from)
+        .is_some_and(|piece| piece.piece_type == PieceType::T && piece.color == king.color);
+    let xs = [king.position.x, king_to_x, rook_from.x, rook_to_x];
+    let (low, high) = (*xs.iter().min().unwrap(), *xs.iter().max().unwrap());
+    let blocked = (low..=high)
+        .map(|x| Position { x, y: row })
+        .any(|position| {
+            position != king.position
+                && position != rook_from
+                && table.piece_at(&position).is_some()
+        });
+    if !available || !has_rook || blocked {
+        return Err("ERROR: Castling is not allowed".to_string());
+    }
+    Ok((
+        Position {
+            x: king_to_x,
+            y: row,
+        },
+        (
+            rook_from,
+            Position {
+                x: rook_to_x,
+                y: row,
+            },
+        ),
+    ))
+}
+
+/// Updates the state of the game after a move.
+///
+/// # Arguments
+///
+/// * `state` - A mutable reference to the `State` to update.
+/// * `moved` - The moved piece as it was before the move.
+/// * `mv` - The move that was applied.
+/// * `capture` - Whether the move captured a piece.
+/// * `exploded` - The other pieces removed by the explosion of the capture at Atomic.
+///
+fn update_state(state: &mut State, moved: &Piece, mv: &Move, capture: bool, exploded: &[Piece]) {
+    state.en_passant = None;
+    if moved.piece_type == PieceType::P && mv.from.y.abs_diff(mv.to.y) == 2 {
+        state.en_passant = Some(Position {
+            x: mv.from.x,
+            y: (mv.from.y + mv.to.y) / 2,
+        });
+    }
+
+    let kings = std::iter::once(moved)
+        .chain(exploded)
+        .filter(|piece| piece.piece_type == PieceType::R);
+    for king in kings {
+        match king.color {
+            Color::White => {
+                state.castling.white_king_side = false;
+                state.castling.white_queen_side = false;
+            }
+            Color::Black => {
+                state.castling.black_king_side = false;
+                state.castling.black_queen_side = false;
+            }
+            Color::Empty => {}
+        }
+    }
+    // A rook that moves, is captured or explodes on its initial square can no longer castle
+    let files = state.chess960.unwrap_or(CastlingFiles::STANDARD);
+    let positions = [mv.from, mv.to]
+        .into_iter()
+        .chain(exploded.iter().map(|piece| piece.position));
+    for position in positions {
+        let right = match position.y {
+            0 => (
+                &mut state.castling.black_king_side,
+                &mut state.castling.black_queen_side,
+            ),
+            y if y == BOARD_SIZE - 1 => (
+                &mut state.castling.white_king_side,
+                &mut state.castling.white_queen_side,
+            ),
+            _ => continue,
+        };
+        if position.x == files.king_side {
+            *right.0 = false;
+        } else if position.x == files.queen_side {
+            *right.1 = false;
+        }
+    }
+
+    if moved.piece_type == PieceType::P || capture {
+        state.halfmove_clock = 0;
+    } else {
+        state.halfmove_clock += 1;
+    }
+    if moved.color == Color::Black {
+        state.fullmove_number += 1;
+    }
+    state.side_to_move = moved.color.opposite();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::table::{format_table, parse_position};
+    use crate::utils::read_file;
+
+    fn setup_move_test(file_path: &str) -> Table {
+        parse_position(&read_file(&file_path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_make_move() {
+        let original = setup_move_test("tables/castling.txt");
+        let mut table = original.clone();
+
+        // White castles on the king's side
+        let castle = Move::new(Position { x: 4, y: 7 }, Position { x: 6, y: 7 });
+        assert!(make_move(&mut table, &castle).is_ok());
+        assert_eq!(
+            table.piece_at(&Position { x: 5, y: 7 }).unwrap().piece_type,
+            PieceType::T
+        );
+        assert!(!table.state().castling.white_king_side);
+        assert!(!table.state().castling.white_queen_side);
+        assert!(table.state().castling.black_queen_side);
+        assert_eq!(table.state().side_to_move, Color::Black);
+        assert_eq!(table.state().halfmove_clock, 1);
+
+        // Black rook captures the white rook on a1
+        let capture = Move::new(Position { x: 0, y: 0 }, Position { x: 0, y: 7 });
+        assert!(make_move(&mut table, &capture).is_ok());
+        assert_eq!(table.pieces().len(), original.pieces().len() - 1);
+        assert!(!table.state().castling.black_queen_side);
+        assert_eq!(table.state().halfmove_clock, 0);
+        assert_eq!(table.state().fullmove_number, 2);
+
+        assert_eq!(played_moves(&table), vec![castle, capture]);
+        assert_eq!(unmake_move(&mut table), Some(capture));
+        assert_eq!(unmake_move(&mut table), Some(castle));
+        assert_eq!(unmake_move(&mut table), None);
+        assert_eq!(table, original);
+        assert_eq!(format_table(&table), format_table(&original));
+
+        assert_eq!(redo_move(&mut table), Some(castle));
+        assert_eq!(redo_move(&mut table), Some(capture));
+        assert_eq!(redo_move(&mut table), None);
+        assert_eq!(played_moves(&table), vec![castle, capture]);
+    }
+
+    #[test]
+    fn test_make_move_errors() {
+        let mut table = setup_move_test("tables/castling.txt");
+        let original = table.clone();
+
+        assert_eq!(
+            make_move(
+                &mut table,
+                &Move::new(Position { x: 3, y: 3 }, Position { x: 3, y: 4 })
+            ),
+            Err("ERROR: No piece on d5".to_string())
+        );
+        assert_eq!(
+            make_move(
+                &mut table,
+                &Move::new(Position { x: 4, y: 0 }, Position { x: 4, y: 1 })
+            ),
+            Err("ERROR: The piece does not belong to the player to move".to_string())
+        );
+        assert_eq!(
+            make_move(
+                &mut table,
+                &Move::new(Position { x: 4, y: 7 }, Position { x: 0, y: 7 })
+            ),
+            Err("ERROR: A piece cannot capture a piece of its own color".to_string())
+        );
+        assert_eq!(
+            make_move(
+                &mut table,
+                &Move::new(Position { x: 1, y: 1 }, Position { x: 1, y: 0 })
+            ),
+            Err("ERROR: A pawn reaching the last row must be promoted".to_string())
+        );
+        assert_eq!(
+            make_move(
+                &mut table,
+                &Move {
+                    from: Position { x: 1, y: 1 },
+                    to: Position { x: 1, y: 0 },
+                    promotion: Some(PieceType::R),
+                    drop: None,
+                }
+            ),
+            Err("ERROR: Invalid promotion: R".to_string())
+        );
+        assert_eq!(table, original);
+    }
+
+    #[test]
+    fn test_promotion_and_en_passant() {
+        let mut table = setup_move_test("tables/castling.txt");
+        let promotion = Move {
+            from: Position { x: 1, y: 1 },
+            to: Position { x: 1, y: 0 },
+            promotion: Some(PieceType::C),
+            drop: None,
+        };
+        assert!(make_move(&mut table, &promotion).is_ok());
+        assert_eq!(
+            table.piece_at(&Position { x: 1, y: 0 }).unwrap().piece_type,
+            PieceType::C
+        );
+        unmake_move(&mut table);
+        assert_eq!(
+            table.piece_at(&Position { x: 1, y: 1 }).unwrap().piece_type,
+            PieceType::P
+        );
+
+        let mut table = setup_move_test("tables/en_passant.txt");
+        let original = table.clone();
+        let en_passant = Move::new(Position { x: 4, y: 3 }, Position { x: 3, y: 2 });
+        assert!(make_move(&mut table, &en_passant).is_ok());
+        assert_eq!(table.pieces().len(), 1);
+        assert_eq!(table.state().en_passant, None);
+        unmake_move(&mut table);
+        assert_eq!(table, original);
+
+        // A double step sets the en passant square
+        let mut table = setup_move_test("tables/castling.txt");
+        let double_step = Move::new(Position { x: 6, y: 6 }, Position { x: 6, y: 4 });
+        assert!(make_move(&mut table, &double_step).is_ok());
+        assert_eq!(table.state().en_passant, Some(Position { x: 6, y: 5 }));
+    }
+}
@@ -0,0 +1,443 @@
+use crate::game::table::generator::{is_checkmate, is_in_check, legal_moves};
+use crate::game::table::moves::{castling_side, make_move, Move};
+use crate::game::table::piece::kind::piece_type_from_symbol;
+use crate::game::table::piece::position::Position;
+use crate::game::table::piece::{Piece, PieceType};
+use crate::game::table::Table;
+
+/// An enum representing the languages whose piece letters can be used in Standard Algebraic
+/// Notation.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Language {
+    /// The letters of the table files: R, D, A, C and T.
+    Spanish,
+    /// The letters K, Q, B, N and R.
+    English,
+}
+
+/// Returns the letter of a piece type in Standard Algebraic Notation, or `None` for pawns.
+///
+/// # Arguments
+///
+/// * `piece_type` - The type of the piece.
+/// * `language` - The language of the letter.
+///
+pub fn piece_letter(piece_type: PieceType, language: Language) -> Option<char> {
+    let letters = match language {
+        Language::Spanish => ['R', 'D', 'A', 'C', 'T'],
+        Language::English => ['K', 'Q', 'B', 'N', 'R'],
+    };
+    match piece_type {
+        PieceType::R => Some(letters[0]),
+        PieceType::D => Some(letters[1]),
+        PieceType::A => Some(letters[2]),
+        PieceType::C => Some(letters[3]),
+        PieceType::T => Some(letters[4]),
+        PieceType::Custom(symbol) => Some(symbol.to_ascii_uppercase()),
+        PieceType::P | PieceType::Empty => None,
+    }
+}
+
+/// Returns the letter of a piece type dropped at Crazyhouse, which is `P` for pawns.
+fn drop_letter(piece_type: PieceType, language: Language) -> char {
+    piece_letter(piece_type, language).unwrap_or('P')
+}
+
+/// Parses the letter of a piece type dropped at Crazyhouse, where `P` is a pawn.
+fn drop_from_letter(letter: char, language: Language) -> Option<PieceType> {
+    match letter {
+        'P' => Some(PieceType::P),
+        _ => piece_from_letter(letter, language),
+    }
+}
+
+/// Returns the piece type written with a letter in Standard Algebraic Notation.
+///
+/// Pieces of registered kinds are written with their symbol in uppercase in every language.
+pub(crate) fn piece_from_letter(letter: char, language: Language) -> Option<PieceType> {
+    [
+        PieceType::R,
+        PieceType::D,
+        PieceType::A,
+        PieceType::C,
+        PieceType::T,
+    ]
+    .into_iter()
+    .find(|piece_type| piece_letter(*piece_type, language) == Some(letter))
+    .or_else(
+        || match piece_type_from_symbol(letter.to_ascii_lowercase()) {
+            Some(custom @ PieceType::Custom(_)) if letter.is_ascii_uppercase() => Some(custom),
+            _ => None,
+        },
+    )
+}
+
+/// Returns the piece type written with a letter in Standard Algebraic Notation on a table,
+/// which may be one of its fairy pieces.
+fn table_piece_from_letter(table: &Table, letter: char, language: Language) -> Option<PieceType> {
+    piece_from_letter(letter, language).or_else(|| {
+        match table.piece_type_from_symbol(letter.to_ascii_lowercase()) {
+            Some(custom @ PieceType::Custom(_)) if letter.is_ascii_uppercase() => Some(custom),
+            _ => None,
+        }
+    })
+}
+
+/// Formats a legal move in Standard Algebraic Notation, such as `Cf3`, `exd5`, `O-O` or
+/// `e8=D#` with Spanish letters. Drops at Crazyhouse are written as `C@f3` or `P@e4`.
+///
+/// Moves are disambiguated by file, rank or both when another piece of the same type can
+/// move to the same square, and get a `+` suffix when they give check or `#` when they
+/// give checkmate.
+///
+/// # Arguments
+///
+/// * `table` - A reference to the `Table` before the move.
+/// * `mv` - A reference to the `Move` to format.
+/// * `language` - The language of the piece letters.
+///
+/// # Errors
+///
+/// Returns an error if the move is not legal on the table.
+///
+pub fn format_san(table: &Table, mv: &Move, language: Language) -> Result<String, String> {
+    let moves = legal_moves(table);
+    if !moves.contains(mv) {
+        return Err(format!("ERROR: Illegal move: {}", format_uci(mv)));
+    }
+    let mut san = san_without_suffix(table, mv, &moves, language);
+    let mut after = table.clone();
+    make_move(&mut after, mv)?;
+    if is_checkmate(&after) {
+        san.push('#');
+    } else if is_in_check(&after, after.state.side_to_move) {
+        san.push('+');
+    }
+    Ok(san)
+}
+
+/// Formats a legal move in Standard Algebraic Notation without the check suffix.
+fn san_without_suffix(table: &Table, mv: &Move, moves: &[Move], language: Language) -> String {
+    if let Some(piece_type) = mv.drop {
+        return format!("{}@{}", drop_letter(piece_type, language), mv.to);
+    }
+    let piece = match table.piece_at(&mv.from) {
+        Some(piece) => piece,
+        None => return format_uci(mv),
+    };
+    if let Some(king_side) = castling_side(table, mv) {
+        return if king_side {
+            "O-O".to_string()
+        } else {
+            "O-O-O".to_string()
+        };
+    }
+    let capture = table.piece_at(&mv.to).is_some()
+        || (piece.piece_type == PieceType::P && mv.from.x != mv.to.x);
+
+    let mut san = String::new();
+    match piece_letter(piece.piece_type, language) {
+        Some(letter) => {
+            san.push(letter);
+            san.push_str(&disambiguation(table, piece, mv, moves));
+        }
+        None if capture => san.push(mv.from.file()),
+        None => {}
+    }
+    if capture {
+        san.push('x');
+    }
+    san.push_str(&mv.to.to_string());
+    if let Some(letter) = mv.promotion.and_then(|p| piece_letter(p, language)) {
+        san.push('=');
+        san.push(letter);
+    }
+    san
+}
+
+/// Returns the file, rank or square needed to tell a move apart from the moves of other
+/// pieces of the same type to the same square.
+fn disambiguation(table: &Table, piece: &Piece, mv: &Move, moves: &[Move]) -> String {
+    let others: Vec<&Position> = moves
+        .iter()
+        .filter(|other| other.to == mv.to && other.from != mv.from)
+        .filter(|other| {
+            table
+                .piece_at(&other.from)
+                .is_some_and(|p| p.piece_type == piece.piece_type)
+        })
+        .map(|other| &other.from)
+        .collect();
+    if others.is_empty() {
+        String::new()
+    } else if others.iter().all(|from| from.x != mv.from.x) {
+        mv.from.file().to_string()
+    } else if others.iter().all(|from| from.y != mv.from.y) {
+        mv.from.rank().to_string()
+    } else {
+        mv.from.to_string()
+    }
+}
+
+/// Parses a move in Standard Algebraic Notation into a legal move of the table.
+///
+/// Check and annotation suffixes (`+`, `#`, `!`, `?`) are ignored, castling can be written
+/// with the letter `O` or the digit `0`, and the `=` before a promotion is optional. The
+/// letter of a pawn drop can be left out, as in `@e4`.
+///
+/// # Arguments
+///
+/// * `table` - A reference to the `Table` before the move.
+/// * `san` - The move in Standard Algebraic Notation.
+/// * `language` - The language of the piece letters.
+///
+/// # Errors
+///
+/// Returns an error if the move cannot be parsed, or if it matches no legal move or more
+/// than one.
+///
+pub fn parse_san(table: &Table, san: &str, language: Language) -> Result<Move, String> {
+    let text = san.trim_end_matches(['+', '#', '!', '?']);
+    let moves = legal_moves(table);
+
+    if matches!(text, "O-O" | "0-0" | "O-O-O" | "0-0-0") {
+        let king_side = text.len() == 3;
+        return moves
+            .iter()
+            .find(|mv| castling_side(table, mv) == Some(king_side))
+            .copied()
+            .ok_or(format!("ERROR: Illegal move: {}", san));
+    }
+
+    let invalid = || format!("ERROR: Invalid move: {}", san);
+    if let Some((letter, square)) = text.split_once('@') {
+        let mut letters = letter.chars();
+        let piece_type = match (letters.next(), letters.next()) {
+            (None, _) => PieceType::P,
+            (Some(c), None) => drop_from_letter(c, language).ok_or_else(invalid)?,
+            _ => return Err(invalid()),
+        };
+        let to: Position = square.parse().map_err(|_| invalid())?;
+        return moves
+            .iter()
+            .find(|mv| mv.drop == Some(piece_type) && mv.to == to)
+            .copied()
+            .ok_or(format!("ERROR: Illegal move: {}", san));
+    }
+    let mut chars: Vec<char> = text.chars().collect();
+
+    let mut promotion = None;
+    if let Some(&last) = chars.last() {
+        if let Some(piece_type) = table_piece_from_letter(table, last, language) {
+            promotion = Some(piece_type);
+            chars.pop();
+            if chars.last() == Some(&'=') {
+                chars.pop();
+            }
+        }
+    }
+
+    let piece_type = match chars
+        .first()
+        .and_then(|c| table_piece_from_letter(table, *c, language))
+    {
+        Some(piece_type) => {
+            chars.remove(0);
+            piece_type
+        }
+        None => PieceType::P,
+    };
+
+    if chars.len() < 2 {
+        return Err(invalid());
+    }
+    let to: Position = chars[chars.len() - 2..]
+        .iter()
+        .collect::<String>()
+        .parse()
+        .map_err(|_| invalid())?;
+    chars.truncate(chars.len() - 2);
+    if chars.last() == Some(&'x') {
+        chars.pop();
+    }
+
+    let (mut file, mut rank) = (None, None);
+    for c in chars {
+        match c {
+            'a'..='h' if file.is_none() && rank.is_none() => file = Some(c),
+            '1'..='8' if rank.is_none() => rank = c.to_digit(10).map(|r| r as usize),
+            _ => return Err(invalid()),
+        }
+    }
+
+    let candidates: Vec<&Move> = moves
+        .iter()
+        .filter(|mv| mv.to == to && mv.promotion == promotion)
+        .filter(|mv| file.is_none_or(|f| mv.from.file() == f))
+        .filter(|mv| rank.is_none_or(|r| mv.from.rank() == r))
+        .filter(|mv| {
+            table
+                .piece_at(&mv.from)
+                .is_some_and(|p| p.piece_type == piece_type)
+        })
+        .collect();
+    match candidates.as_slice() {
+        [mv] => Ok(**mv),
+        [] => Err(format!("ERROR: Illegal move: {}", san)),
+        _ => Err(format!("ERROR: Ambiguous move: {}", san)),
+    }
+}
+
+/// Formats a move in the long algebraic notation of the UCI protocol, such as `e2e4` or
+/// `e7e8q`. Promotions always use the lowercase English letters, and drops at Crazyhouse
+/// are written with the uppercase English letter of the piece, as in `N@f3` or `P@e4`.
+pub fn format_uci(mv: &Move) -> String {
+    if let Some(piece_type) = mv.drop {
+        return format!("{}@{}", drop_letter(piece_type, Language::English), mv.to);
+    }
+    let mut uci = format!("{}{}", mv.from, mv.to);
+    if let Some(letter) = mv
+        .promotion
+        .and_then(|p| piece_letter(p, Language::English))
+    {
+        uci.push(letter.to_ascii_lowercase());
+    }
+    uci
+}
+
+/// Parses a move in the long algebraic notation of the UCI protocol.
+///
+/// The move is only parsed, not checked against the rules of the game.
+///
+/// # Errors
+///
+/// Returns an error if the move is not two squares followed by an optional promotion
+/// letter (`q`, `r`, `b`, `n`, or `k` at Antichess), nor a drop.
+///
+pub fn parse_uci(uci: &str) -> Result<Move, String> {
+    let invalid = || format!("ERROR: Invalid move: {}", uci);
+    if !uci.is_ascii() || (uci.len() != 4 && uci.len() != 5) {
+        return Err(invalid());
+    }
+    if &uci[1..2] == "@" {
+        let piece_type = match uci[..1]
+            .chars()
+            .next()
+            .and_then(|c| drop_from_letter(c, Language::English))
+        {
+            Some(PieceType::Custom(_) | PieceType::R) | None => return Err(invalid()),
+            Some(piece_type) => piece_type,
+        };
+        let to: Position = uci[2..].parse().map_err(|_| invalid())?;
+        return Ok(Move::new_drop(piece_type, to));
+    }
+    let from: Position = uci[0..2].parse().map_err(|_| invalid())?;
+    let to: Position = uci[2..4].parse().map_err(|_| invalid())?;
+    let promotion = match uci[4..].chars().next() {
+        Some(c) => match piece_from_letter(c.to_ascii_uppercase(), Language::English) {
+            Some(PieceType::Custom(_)) | None => return Err(invalid()),
+            Some(piece_type) => Some(piece_type),
+        },
+        None => None,
+    };
+    Ok(Move {
+        from,
+        to,
+        promotion,
+        drop: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_san() {
+        use super::*;
+        use crate::game::table::parse_position;
+        use crate::utils::read_file;
+        let table = parse_position(&read_file(&"tables/start.txt").unwrap()).unwrap();
+        let mv = parse_san(&table, "Cf3", Language::Spanish).unwrap();
+        assert_eq!(mv, parse_uci("g1f3").unwrap());
+        assert_eq!(parse_san(&table, "Nf3", Language::English), Ok(mv));
+        assert_eq!(format_san(&table, &mv, Language::Spanish).unwrap(), "Cf3");
+        assert_eq!(format_san(&table, &mv, Language::English).unwrap(), "Nf3");
+        assert_eq!(
+            parse_san(&table, "e4", Language::Spanish),
+            parse_uci("e2e4")
+        );
+        assert_eq!(
+            parse_san(&table, "Cf6", Language::Spanish).err(),
+            Some("ERROR: Illegal move: Cf6".to_string())
+        );
+        assert_eq!(
+            parse_san(&table, "Zz9", Language::Spanish).err(),
+            Some("ERROR: Invalid move: Zz9".to_string())
+        );
+
+        // Castling, promotion with capture and rook disambiguation
+        let table = parse_position(&read_file(&"tables/castling.txt").unwrap()).unwrap();
+        let castle = parse_san(&table, "O-O-O", Language::Spanish).unwrap();
+        assert_eq!(castle, parse_uci("e1c1").unwrap());
+        assert_eq!(
+            format_san(&table, &castle, Language::English).unwrap(),
+            "O-O-O"
+        );
+        let promotion = parse_san(&table, "bxa8=D+", Language::Spanish).unwrap();
+        assert_eq!(promotion, parse_uci("b7a8q").unwrap());
+        assert_eq!(
+            format_san(&table, &promotion, Language::Spanish).unwrap(),
+            "bxa8=D+"
+        );
+        assert_eq!(
+            format_san(&table, &promotion, Language::English).unwrap(),
+            "bxa8=Q+"
+        );
+
+        let table = parse_position(&read_file(&"tables/rooks.txt").unwrap()).unwrap();
+        assert_eq!(
+            parse_san(&table, "Td1", Language::Spanish).err(),
+            Some("ERROR: Ambiguous move: Td1".to_string())
+        );
+        let rook = parse_san(&table, "Tad1", Language::Spanish).unwrap();
+        assert_eq!(rook, parse_uci("a1d1").unwrap());
+        assert_eq!(
+            format_san(&table, &rook, Language::Spanish).unwrap(),
+            "Tad1"
+        );
+        let rook = parse_san(&table, "R5a3", Language::English).unwrap();
+        assert_eq!(rook, parse_uci("a5a3").unwrap());
+        assert_eq!(
+            format_san(&table, &rook, Language::Spanish).unwrap(),
+            "T5a3"
+        );
+
+        // Checkmate
+        let table = parse_position(&read_file(&"tables/back_rank.txt").unwrap()).unwrap();
+        let mate = parse_san(&table, "Te8", Language::Spanish).unwrap();
+        assert_eq!(
+            format_san(&table, &mate, Language::Spanish).unwrap(),
+            "Te8#"
+        );
+        assert_eq!(
+            format_san(&table, &mate, Language::English).unwrap(),
+            "Re8#"
+        );
+    }
+
+    #[test]
+    fn test_uci() {
+        use super::*;
+        let mv = parse_uci("e7e8q").unwrap();
+        assert_eq!(mv.promotion, Some(PieceType::D));
+        assert_eq!(format_uci(&mv), "e7e8q");
+        assert_eq!(format_uci(&parse_uci("g1f3").unwrap()), "g1f3");
+        // Kings are only promoted at Antichess, which `make_move` checks
+        assert_eq!(parse_uci("e7e8k").unwrap().promotion, Some(PieceType::R));
+        for uci in ["", "e2", "e2e9", "e7e8p", "e7e8x", "e2e4e5"] {
+            assert_eq!(
+                parse_uci(uci).err(),
+                Some(format!("ERROR: Invalid move: {}", uci))
+            );
+        }
+    }
+}
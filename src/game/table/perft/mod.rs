@@ -0,0 +1,134 @@
+use crate::game::table::generator::legal_moves;
+use crate::game::table::moves::{make_move, unmake_move, Move};
+use crate::game::table::Table;
+
+/// Counts the leaf nodes of the tree of legal moves of a table, down to a given depth.
+///
+/// Comparing these counts with published results is the standard way to check that move
+/// generation follows the rules of chess, including castling, en passant and promotions.
+///
+/// # Arguments
+///
+/// * `table` - A mutable reference to the `Table` to explore. Every move applied while
+///   exploring is taken back, so the table is left as it was.
+/// * `depth` - The number of moves to play from the table.
+///
+/// # Returns
+///
+/// The number of sequences of `depth` legal moves that can be played from the table.
+///
+pub fn perft(table: &mut Table, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let moves = legal_moves(table);
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+    let mut nodes = 0;
+    for mv in moves {
+        if make_move(table, &mv).is_ok() {
+            nodes += perft(table, depth - 1);
+            unmake_move(table);
+        }
+    }
+    nodes
+}
+
+/// Counts the leaf nodes of the tree of legal moves of a table, split by the first move.
+///
+/// # Arguments
+///
+/// * `table` - A mutable reference to the `Table` to explore, which is left as it was.
+/// * `depth` - The number of moves to play from the table, at least 1.
+///
+/// # Returns
+///
+/// Each legal move of the table with the number of leaf nodes that follow it. The counts add
+/// up to `perft(table, depth)`.
+///
+pub fn divide(table: &mut Table, depth: usize) -> Vec<(Move, u64)> {
+    let mut result = Vec::new();
+    for mv in legal_moves(table) {
+        if make_move(table, &mv).is_ok() {
+            result.push((mv, perft(table, depth.saturating_sub(1))));
+            unmake_move(table);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::table::fen::{parse_fen, START_FEN};
+
+    /// Checks the published perft results of a position, given in Forsyth-Edwards Notation.
+    fn check_perft(fen: &str, expected: &[u64]) {
+        use super::*;
+        let mut table = parse_fen(fen).unwrap();
+        let original = table.clone();
+        for (depth, nodes) in expected.iter().enumerate() {
+            assert_eq!(
+                perft(&mut table, depth + 1),
+                *nodes,
+                "{} at depth {}",
+                fen,
+                depth + 1
+            );
+        }
+        assert_eq!(table, original);
+    }
+
+    #[test]
+    fn test_perft_start() {
+        check_perft(START_FEN, &[20, 400, 8902, 197281]);
+    }
+
+    #[test]
+    fn test_perft_kiwipete() {
+        check_perft(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            &[48, 2039, 97862],
+        );
+    }
+
+    #[test]
+    fn test_perft_endgame() {
+        check_perft(
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            &[14, 191, 2812, 43238],
+        );
+    }
+
+    #[test]
+    fn test_perft_promotions() {
+        check_perft(
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            &[6, 264, 9467],
+        );
+        check_perft(
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            &[44, 1486, 62379],
+        );
+    }
+
+    #[test]
+    fn test_perft_middlegame() {
+        check_perft(
+            "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+            &[46, 2079, 89890],
+        );
+    }
+
+    #[test]
+    fn test_divide() {
+        use super::*;
+        use crate::game::table::notation::parse_uci;
+        let mut table = parse_fen(START_FEN).unwrap();
+        let result = divide(&mut table, 3);
+        assert_eq!(result.len(), 20);
+        assert_eq!(result.iter().map(|(_, n)| n).sum::<u64>(), 8902);
+        let e4 = parse_uci("e2e4").unwrap();
+        assert!(result.contains(&(e4, 600)));
+    }
+}
@@ -0,0 +1,183 @@
+use crate::game::table::draw::draw_reason;
+use crate::game::table::fen::{format_fen, START_FEN};
+use crate::game::table::generator::{is_checkmate, is_stalemate};
+use crate::game::table::moves::{make_move, played_moves, unmake_move};
+use crate::game::table::notation::{format_san, Language};
+use crate::game::table::piece::Color;
+use crate::game::table::variant::{variant_winner, Variant};
+use crate::game::table::Table;
+
+/// The tags every game in Portable Game Notation starts with, in their order.
+pub const SEVEN_TAG_ROSTER: [&str; 7] =
+    ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+/// The maximum length of the lines of the moves.
+const LINE_LENGTH: usize = 80;
+
+/// Returns the result of the game on a table as written in Portable Game Notation.
+///
+/// # Returns
+///
+/// * `Some(&str)` - `1-0` or `0-1` if a player won by checkmate or by the rules of the
+///   variant, or `1/2-1/2` if the game is drawn by stalemate or a draw rule.
+///
+/// * `None` - If the game goes on.
+///
+pub fn pgn_result(table: &Table) -> Option<&'static str> {
+    let winner = if let Some(winner) = variant_winner(table) {
+        winner
+    } else if is_checkmate(table) {
+        table.state().side_to_move.opposite()
+    } else if is_stalemate(table) || draw_reason(table).is_some() {
+        return Some("1/2-1/2");
+    } else {
+        return None;
+    };
+    Some(match winner {
+        Color::White => "1-0",
+        _ => "0-1",
+    })
+}
+
+/// Formats the moves played on a table as a game in Portable Game Notation.
+///
+/// The moves are the ones that can be undone with `moves::unmake_move`, written in Standard
+/// Algebraic Notation with English letters. The tags of the Seven Tag Roster that are not
+/// given are written as unknown, and a game that does not start from the standard position
+/// gets the `SetUp` and `FEN` tags. Games of other variants than standard chess get the
+/// `Variant` tag with the name given by `Variant::pgn_name`, and standard games with the
+/// Chess960 castling rules get `Chess960`.
+///
+/// # Arguments
+///
+/// * `table` - A reference to the `Table` of the game, after its last move.
+/// * `tags` - The names and values of the tags, such as `("White", "Ana")`.
+/// * `result` - The result of the game: `1-0`, `0-1`, `1/2-1/2`, or `*` if it goes on.
+///
+/// # Errors
+///
+/// Returns an error if a move of the history cannot be replayed from the start position.
+///
+pub fn format_pgn(table: &Table, tags: &[(&str, &str)], result: &str) -> Result<String, String> {
+    let moves = played_moves(table);
+    let mut start = table.clone();
+    while unmake_move(&mut start).is_some() {}
+
+    let mut lines = Vec::new();
+    let tag = |name: &str| tags.iter().find(|(n, _)| *n == name).map(|(_, v)| *v);
+    for name in SEVEN_TAG_ROSTER {
+        let value = match name {
+            "Result" => result,
+            "Date" => tag(name).unwrap_or("????.??.??"),
+            _ => tag(name).unwrap_or("?"),
+        };
+        lines.push(format_tag(name, value));
+    }
+    let fen = format_fen(&start);
+    if fen != START_FEN {
+        lines.push(format_tag("SetUp", "1"));
+        lines.push(format_tag("FEN", &fen));
+    }
+    match start.state().variant {
+        Variant::Standard if start.state().chess960.is_some() => {
+            lines.push(format_tag("Variant", "Chess960"))
+        }
+        Variant::Standard => {}
+        variant => lines.push(format_tag("Variant", variant.pgn_name())),
+    }
+    for (name, value) in tags {
+        if !SEVEN_TAG_ROSTER.contains(name) {
+            lines.push(format_tag(name, value));
+        }
+    }
+    lines.push(String::new());
+
+    let mut tokens = Vec::new();
+    for (i, mv) in moves.iter().enumerate() {
+        let state = start.state();
+        if state.side_to_move == Color::White {
+            tokens.push(format!("{}.", state.fullmove_number));
+        } else if i == 0 {
+            tokens.push(format!("{}...", state.fullmove_number));
+        }
+        tokens.push(format_san(&start, mv, Language::English)?);
+        make_move(&mut start, mv)?;
+    }
+    tokens.push(result.to_string());
+
+    let mut line = String::new();
+    for token in tokens {
+        if !line.is_empty() && line.len() + 1 + token.len() > LINE_LENGTH {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&token);
+    }
+    lines.push(line);
+    Ok(lines.join("\n") + "\n")
+}
+
+/// Formats a tag pair, escaping the quotes and backslashes of its value.
+fn format_tag(name: &str, value: &str) -> String {
+    let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("[{} \"{}\"]", name, value)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_format_pgn() {
+        use super::*;
+        use crate::game::table::chess960::chess960_position;
+        use crate::game::table::fen::parse_fen;
+        use crate::game::table::notation::parse_uci;
+
+        // Fool's mate, with the tags that were not given left unknown
+        let mut table = parse_fen(START_FEN).unwrap();
+        for mv in ["f2f3", "e7e5", "g2g4", "d8h4"] {
+            make_move(&mut table, &parse_uci(mv).unwrap()).unwrap();
+        }
+        assert_eq!(pgn_result(&table), Some("0-1"));
+        let pgn = format_pgn(&table, &[("White", "Ana"), ("Opening", "Barnes")], "0-1").unwrap();
+        assert_eq!(
+            pgn,
+            "[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n\
+             [White \"Ana\"]\n[Black \"?\"]\n[Result \"0-1\"]\n[Opening \"Barnes\"]\n\n\
+             1. f3 e5 2. g4 Qh4# 0-1\n"
+        );
+
+        // A game from another position starting with black, whose moves are wrapped
+        let mut table = parse_fen("4k3/8/8/8/8/8/8/R3K3 b Q - 0 30").unwrap();
+        for _ in 0..6 {
+            for mv in ["e8d8", "a1a2", "d8e8", "a2a1"] {
+                make_move(&mut table, &parse_uci(mv).unwrap()).unwrap();
+            }
+        }
+        assert_eq!(pgn_result(&table), Some("1/2-1/2"));
+        let pgn = format_pgn(&table, &[], "1/2-1/2").unwrap();
+        let lines: Vec<&str> = pgn.lines().collect();
+        assert_eq!(lines[7], "[SetUp \"1\"]");
+        assert_eq!(lines[8], "[FEN \"4k3/8/8/8/8/8/8/R3K3 b Q - 0 30\"]");
+        assert!(lines[10].starts_with("30... Kd8 31. Ra2 Ke8 32. Ra1 Kd8"));
+        assert!(lines[10..].iter().all(|line| line.len() <= LINE_LENGTH));
+        assert!(pgn.ends_with(" 1/2-1/2\n"));
+
+        // The variant is named as in other programs, and Chess960 is a variant of its own
+        let mut table = parse_fen(START_FEN).unwrap();
+        table.set_variant(Variant::KingOfTheHill);
+        let pgn = format_pgn(&table, &[], "*").unwrap();
+        assert_eq!(pgn.lines().nth(7), Some("[Variant \"King of the Hill\"]"));
+        table.set_variant(Variant::ThreeCheck);
+        let pgn = format_pgn(&table, &[], "*").unwrap();
+        assert_eq!(pgn.lines().nth(7), Some("[Variant \"Three-check\"]"));
+        let mut table = chess960_position(0).unwrap();
+        make_move(&mut table, &parse_uci("b2b3").unwrap()).unwrap();
+        let pgn = format_pgn(&table, &[], "*").unwrap();
+        let lines: Vec<&str> = pgn.lines().collect();
+        assert_eq!(lines[7], "[SetUp \"1\"]");
+        assert_eq!(lines[9], "[Variant \"Chess960\"]");
+        assert_eq!(lines[11], "1. b3 *");
+    }
+}
@@ -0,0 +1,527 @@
+use crate::game::table::generator::offset;
+use crate::game::table::moves::Move;
+use crate::game::table::piece::kind::{
+    check_symbol, piece_kind, piece_type_from_symbol, register_piece_kind, PieceKind,
+};
+use crate::game::table::piece::position::Position;
+use crate::game::table::piece::{Color, Piece, PieceType};
+use crate::game::table::symmetry::Symmetry;
+use crate::game::table::Table;
+
+/// The fairy pieces known by name, with their Betza notation.
+pub const FAIRY_PIECES: [(&str, &str); 5] = [
+    ("Archbishop", "BN"),
+    ("Chancellor", "RN"),
+    ("Amazon", "QN"),
+    ("Camel", "C"),
+    ("Grasshopper", "gQ"),
+];
+
+/// What a leg of a piece can do on the square it lands on.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Mode {
+    /// Move to an empty square or capture.
+    Both,
+    /// Only move to an empty square.
+    Move,
+    /// Only capture.
+    Capture,
+}
+
+/// A single atom of a Betza notation, with its modifiers.
+#[derive(Clone, Debug)]
+struct Leg {
+    /// The steps of the leg as `(file, forward)` offsets, forward being towards the opponent.
+    steps: Vec<(isize, isize)>,
+    /// The number of steps the piece can take in a direction: 1 for a leaper, 0 for a rider
+    /// without limit.
+    range: usize,
+    /// What the piece can do on the square it lands on.
+    mode: Mode,
+    /// Whether the piece must jump over a piece and land right behind it, like the
+    /// grasshopper.
+    hop: bool,
+}
+
+/// A piece kind whose movement is described by a Betza notation, such as `BN` for the
+/// archbishop.
+///
+/// The notation is a sequence of atoms, each an uppercase letter for a basic move:
+///
+/// * `W` (wazir), `F` (ferz), `D` (dabbaba), `N` (knight), `A` (alfil), `H` (threeleaper),
+///   `C` (camel), `Z` (zebra) and `G` (tripper) leap to the squares at a fixed offset.
+/// * `K` (king) is `WF`, `R` (rook) and `B` (bishop) ride along the `W` and `F` directions,
+///   and `Q` (queen) is `RB`.
+///
+/// A leaping atom written twice, such as `NN`, rides as far as the squares are empty, and a
+/// number after an atom, such as `R2` or `N0`, limits its range, 0 being no limit.
+///
+/// An atom can be preceded by lowercase modifiers:
+///
+/// * `m` to only move to empty squares, `c` to only capture.
+/// * `g` to hop over the first piece in the way and land right behind it.
+/// * `f`, `b`, `l` and `r` to keep the forward, backward, left or right steps, `v` and `s` to
+///   keep the steps that are mostly vertical or sideways. A pair like `fl` keeps the steps
+///   that are both forward and left, and several directions keep the steps of any of them.
+#[derive(Clone, Debug)]
+pub struct Betza {
+    name: String,
+    symbol: char,
+    notation: String,
+    legs: Vec<Leg>,
+}
+
+impl PartialEq for Betza {
+    /// Two piece kinds are equal if they have the same name, symbol and notation, from which
+    /// their legs are built.
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.symbol == other.symbol && self.notation == other.notation
+    }
+}
+
+impl Betza {
+    /// Creates a piece kind from its Betza notation.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the piece kind.
+    /// * `symbol` - The lowercase letter of the piece kind in table files.
+    /// * `notation` - The Betza notation of its movement.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the notation is empty, has an unknown letter or modifiers without
+    /// an atom.
+    ///
+    pub fn new(name: &str, symbol: char, notation: &str) -> Result<Betza, String> {
+        let invalid = || format!("ERROR: Invalid Betza notation: {}", notation);
+        let chars: Vec<char> = notation.chars().collect();
+        let mut legs = Vec::new();
+        let mut modifiers = String::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            i += 1;
+            if "mcgfblrvs".contains(c) {
+                modifiers.push(c);
+                continue;
+            }
+            let (atoms, mut range): (&[(isize, isize)], usize) = match c {
+                'W' => (&[(0, 1)], 1),
+                'F' => (&[(1, 1)], 1),
+                'D' => (&[(0, 2)], 1),
+                'N' => (&[(1, 2)], 1),
+                'A' => (&[(2, 2)], 1),
+                'H' => (&[(0, 3)], 1),
+                'C' => (&[(1, 3)], 1),
+                'Z' => (&[(2, 3)], 1),
+                'G' => (&[(3, 3)], 1),
+                'K' => (&[(0, 1), (1, 1)], 1),
+                'R' => (&[(0, 1)], 0),
+                'B' => (&[(1, 1)], 0),
+                'Q' => (&[(0, 1), (1, 1)], 0),
+                _ => return Err(invalid()),
+            };
+            if range == 1 && chars.get(i) == Some(&c) {
+                range = 0;
+                i += 1;
+            }
+            let digits: String = chars[i..]
+                .iter()
+                .take_while(|d| d.is_ascii_digit())
+                .collect();
+            if !digits.is_empty() {
+                range = digits.parse().map_err(|_| invalid())?;
+                i += digits.len();
+            }
+            legs.push(leg(atoms, range, &modifiers));
+            modifiers.clear();
+        }
+        if legs.is_empty() || !modifiers.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Betza {
+            name: name.to_string(),
+            symbol,
+            notation: notation.to_string(),
+            legs,
+        })
+    }
+
+    /// Returns the squares a piece can land on with a step of a leg, whether they are empty
+    /// or not. The last one may hold a piece, which blocks the way further.
+    fn landings(table: &Table, piece: &Piece, leg: &Leg, step: &(isize, isize)) -> Vec<Position> {
+        // White moves forward towards row 0, and black pieces are turned around
+        let (dx, dy) = match piece.color {
+            Color::White => (step.0, -step.1),
+            _ => (-step.0, step.1),
+        };
+        let mut landings = Vec::new();
+        let mut current = piece.position;
+        let mut distance = 0;
+        let mut hurdle = !leg.hop;
+        while let Some(to) = offset(&current, dx, dy) {
+            distance += 1;
+            let occupied = table.piece_at(&to).is_some();
+            if hurdle {
+                landings.push(to);
+                if occupied || leg.hop {
+                    break;
+                }
+            } else {
+                hurdle = occupied;
+            }
+            if leg.range != 0 && distance >= leg.range && !(leg.hop && hurdle) {
+                break;
+            }
+            current = to;
+        }
+        landings
+    }
+}
+
+/// Builds a leg from the first-quadrant offsets of an atom, turned in every direction and
+/// filtered by the direction modifiers.
+fn leg(atoms: &[(isize, isize)], range: usize, modifiers: &str) -> Leg {
+    let mut steps: Vec<(isize, isize)> = Vec::new();
+    for (a, b) in atoms {
+        for (x, y) in [(*a, *b), (*b, *a)] {
+            for (sx, sy) in [(1, 1), (1, -1), (-1, 1), (-1, -1)] {
+                let step = (x * sx, y * sy);
+                if !steps.contains(&step) {
+                    steps.push(step);
+                }
+            }
+        }
+    }
+
+    // Directions are read one at a time, but a vertical one followed by a horizontal one is
+    // a single diagonal direction
+    let directions: Vec<char> = modifiers
+        .chars()
+        .filter(|c| "fblrvs".contains(*c))
+        .collect();
+    let mut filters: Vec<(char, Option<char>)> = Vec::new();
+    let mut i = 0;
+    while i < directions.len() {
+        let pair = directions.get(i + 1).filter(|c| "lr".contains(**c));
+        match pair {
+            Some(second) if "fb".contains(directions[i]) => {
+                filters.push((directions[i], Some(*second)));
+                i += 2;
+            }
+            _ => {
+                filters.push((directions[i], None));
+                i += 1;
+            }
+        }
+    }
+    let keeps = |direction: char, (x, y): (isize, isize)| match direction {
+        'f' => y > 0,
+        'b' => y < 0,
+        'l' => x < 0,
+        'r' => x > 0,
+        'v' => x.abs() < y.abs(),
+        _ => x.abs() > y.abs(),
+    };
+    if !filters.is_empty() {
+        steps.retain(|step| {
+            filters.iter().any(|(first, second)| {
+                keeps(*first, *step) && second.is_none_or(|s| keeps(s, *step))
+            })
+        });
+    }
+
+    let mode = if modifiers.contains('m') && !modifiers.contains('c') {
+        Mode::Move
+    } else if modifiers.contains('c') && !modifiers.contains('m') {
+        Mode::Capture
+    } else {
+        Mode::Both
+    };
+    Leg {
+        steps,
+        range,
+        mode,
+        hop: modifiers.contains('g'),
+    }
+}
+
+impl PieceKind for Betza {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn symbol(&self) -> char {
+        self.symbol
+    }
+
+    fn betza(&self) -> Option<&str> {
+        Some(&self.notation)
+    }
+
+    fn attacks(&self, table: &Table, piece: &Piece, target: &Position) -> bool {
+        self.legs
+            .iter()
+            .filter(|leg| leg.mode != Mode::Move)
+            .any(|leg| {
+                leg.steps
+                    .iter()
+                    .any(|step| Betza::landings(table, piece, leg, step).contains(target))
+            })
+    }
+
+    fn moves(&self, table: &Table, piece: &Piece, moves: &mut Vec<Move>) {
+        for leg in &self.legs {
+            for step in &leg.steps {
+                for to in Betza::landings(table, piece, leg, step) {
+                    let allowed = match table.piece_at(&to) {
+                        Some(other) => other.color != piece.color && leg.mode != Mode::Move,
+                        None => leg.mode != Mode::Capture,
+                    };
+                    let mv = Move::new(piece.position, to);
+                    if allowed && !moves.contains(&mv) {
+                        moves.push(mv);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The movement is kept if the steps of every leg, turned by the transformation for
+    /// pieces of both colors, are steps of a leg with the same range and mode.
+    fn is_preserved_by(&self, symmetry: &Symmetry) -> bool {
+        // Turns a `(file, forward)` step into a `(file, row)` offset on the board and back
+        let turn = |color: Color, (x, y): (isize, isize)| match color {
+            Color::White => (x, -y),
+            _ => (-x, y),
+        };
+        [Color::White, Color::Black].into_iter().all(|color| {
+            let moved = if symmetry.swap_colors {
+                color.opposite()
+            } else {
+                color
+            };
+            self.legs.iter().all(|leg| {
+                leg.steps.iter().all(|step| {
+                    let turned = turn(moved, symmetry.apply_offset(turn(color, *step)));
+                    self.legs.iter().any(|other| {
+                        other.range == leg.range
+                            && other.mode == leg.mode
+                            && other.hop == leg.hop
+                            && other.steps.contains(&turned)
+                    })
+                })
+            })
+        })
+    }
+}
+
+/// Registers a fairy piece described by a Betza notation for the whole program, so that it
+/// can be placed on any table with its own letter. Pieces only used by a table file are
+/// defined by its `fairy` header instead, see `parse_fairy_pieces`.
+///
+/// Registering the same notation with the same letter again has no effect.
+///
+/// # Arguments
+///
+/// * `name` - The name of the piece kind.
+/// * `symbol` - The lowercase letter of the piece in table files.
+/// * `notation` - The Betza notation of its movement, as described in `Betza`.
+///
+/// # Returns
+///
+/// * `Ok(PieceType)` - The `PieceType::Custom` of the piece.
+///
+/// * `Err(String)` - An error message if the notation is invalid, or the letter is invalid or
+///   already in use.
+///
+pub fn register_fairy_piece(name: &str, symbol: char, notation: &str) -> Result<PieceType, String> {
+    let piece_type = PieceType::Custom(symbol);
+    if let Some(kind) = piece_kind(piece_type) {
+        if kind.betza() == Some(notation) {
+            return Ok(piece_type);
+        }
+    }
+    if piece_type_from_symbol(symbol).is_some() {
+        return Err(format!("ERROR: Piece symbol already in use: {}", symbol));
+    }
+    let kind = Betza::new(name, symbol, notation)?;
+    register_piece_kind(Box::leak(Box::new(kind)))
+}
+
+/// Parses the fairy pieces of the `fairy` header of a table file, which only exist on the
+/// table read from it. See `Table::fairy_pieces`.
+///
+/// The value is a list of definitions separated by spaces, each a letter and a Betza
+/// notation, such as `h=BN l=RN`. Pieces named in `FAIRY_PIECES` get their name, and the
+/// others are named after their notation.
+///
+/// # Errors
+///
+/// Returns an error if a definition is invalid, or its letter is used by a built-in or
+/// registered piece kind or by another definition.
+///
+pub fn parse_fairy_pieces(value: &str) -> Result<Vec<Betza>, String> {
+    let mut pieces: Vec<Betza> = Vec::new();
+    for definition in value.split_whitespace() {
+        let (symbol, notation) = match definition.split_once('=') {
+            Some((symbol, notation)) if symbol.chars().count() == 1 => (symbol, notation),
+            _ => return Err(format!("ERROR: Invalid fairy piece: {}", definition)),
+        };
+        let symbol = symbol.chars().next().unwrap_or_default();
+        check_symbol(symbol)?;
+        if pieces.iter().any(|piece| piece.symbol == symbol) {
+            return Err(format!("ERROR: Piece symbol already in use: {}", symbol));
+        }
+        let name = FAIRY_PIECES
+            .iter()
+            .find(|(_, known)| *known == notation)
+            .map_or(notation, |(name, _)| name);
+        pieces.push(Betza::new(name, symbol, notation)?);
+    }
+    Ok(pieces)
+}
+
+/// Formats the value of the `fairy` header for the fairy pieces of a table.
+///
+/// # Returns
+///
+/// The definitions of the pieces, or `None` if the table defines no fairy piece.
+///
+pub fn format_fairy_pieces(table: &Table) -> Option<String> {
+    let definitions: Vec<String> = table
+        .fairy_pieces()
+        .iter()
+        .map(|piece| format!("{}={}", piece.symbol, piece.notation))
+        .collect();
+    (!definitions.is_empty()).then(|| definitions.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_betza() {
+        use super::*;
+        use crate::game::table::fen::parse_fen;
+
+        assert!(Betza::new("Nothing", 'x', "").is_err());
+        assert!(Betza::new("Unknown", 'x', "BX").is_err());
+        assert!(Betza::new("Dangling", 'x', "Nm").is_err());
+
+        // Move counts from d4 on an otherwise empty table
+        let count = |notation: &str, fen: &str| {
+            let kind = Betza::new(notation, 'x', notation).unwrap();
+            let table = parse_fen(fen).unwrap();
+            let piece = Piece {
+                piece_type: PieceType::Custom('x'),
+                position: Position::from_file_rank('d', 4).unwrap(),
+                color: Color::White,
+            };
+            let mut moves = Vec::new();
+            kind.moves(&table, &piece, &mut moves);
+            moves.len()
+        };
+        let empty = "8/8/8/8/8/8/8/8 w - - 0 1";
+        assert_eq!(count("BN", empty), 21);
+        assert_eq!(count("RN", empty), 22);
+        assert_eq!(count("QN", empty), 35);
+        assert_eq!(count("C", empty), 8);
+        assert_eq!(count("K", empty), 8);
+        assert_eq!(count("R2", empty), 8);
+        assert_eq!(count("NN", empty), 12);
+        assert_eq!(count("fW", empty), 1);
+        assert_eq!(count("fN", empty), 4);
+        assert_eq!(count("fsN", empty), 6);
+        assert_eq!(count("flF", empty), 1);
+        assert_eq!(count("mfWcfF", "8/8/8/2p5/8/8/8/8 w - - 0 1"), 2);
+
+        // The grasshopper only lands right behind the pieces in its way
+        assert_eq!(count("gQ", empty), 0);
+        assert_eq!(count("gQ", "8/8/8/8/7p/8/8/8 w - - 0 1"), 0);
+        assert_eq!(count("gQ", "8/8/8/8/4p3/8/8/8 w - - 0 1"), 1);
+        assert_eq!(count("gQ", "8/3p4/8/8/8/8/1p1P4/8 w - - 0 1"), 3);
+
+        // Forward pieces are kept by mirrors, and by flips only if the colors are swapped
+        let preserved = |notation: &str, bits: usize| {
+            let kind = Betza::new(notation, 'x', notation).unwrap();
+            kind.is_preserved_by(&Symmetry::all()[bits])
+        };
+        assert!((0..16).all(|bits| preserved("RN", bits)));
+        assert!(preserved("fW", 2));
+        assert!(!preserved("fW", 4));
+        assert!(preserved("fW", 12));
+        assert!(!preserved("fN", 1));
+        assert!(!preserved("flF", 2));
+        assert!(preserved("flFfrF", 2));
+    }
+
+    #[test]
+    fn test_fairy_table() {
+        use super::*;
+        use crate::game::table::generator::is_in_check;
+        use crate::game::table::{check_moves, format_table, parse_position, parse_table};
+        use crate::utils::read_file;
+
+        // The archbishop attacks the rook with a knight jump, which cannot capture it back
+        let lines = read_file(&"tables/fairy.txt").unwrap();
+        let table = parse_table(&lines).unwrap();
+        assert_eq!(check_moves(&table), (true, false));
+        assert_eq!(format_table(&table), lines);
+        assert_eq!(
+            table.piece_kind(PieceType::Custom('h')).unwrap().name(),
+            "Archbishop"
+        );
+        assert!(piece_kind(PieceType::Custom('h')).is_none());
+
+        // Another table can give the same letter another movement: the chancellor captures
+        // the knight along the column, and the archbishop cannot
+        let mut other = lines.clone();
+        other[0] = "fairy: h=RN".to_string();
+        other[2] = "_ _ _ _ C _ _ _".to_string();
+        let table = parse_table(&other).unwrap();
+        assert_eq!(
+            table.piece_kind(PieceType::Custom('h')).unwrap().name(),
+            "Chancellor"
+        );
+        assert_eq!(check_moves(&table), (true, false));
+        other[0] = "fairy: h=BN".to_string();
+        assert_eq!(check_moves(&parse_table(&other).unwrap()), (false, false));
+
+        // Letters cannot be used twice, nor by the built-in or registered pieces
+        assert!(parse_fairy_pieces("h=BN h=RN").is_err());
+        assert!(parse_fairy_pieces("r=C").is_err());
+        assert!(parse_fairy_pieces("n=C").is_err());
+        assert!(parse_fairy_pieces("zh=BN").is_err());
+        assert_eq!(
+            register_fairy_piece("Wazir", 'e', "W"),
+            Ok(PieceType::Custom('e'))
+        );
+        assert_eq!(
+            register_fairy_piece("Wazir", 'e', "W"),
+            Ok(PieceType::Custom('e'))
+        );
+        assert!(register_fairy_piece("Ferz", 'e', "F").is_err());
+        assert_eq!(
+            parse_fairy_pieces("e=F").err(),
+            Some("ERROR: Piece symbol already in use: e".to_string())
+        );
+        let lines: Vec<String> = [
+            "fairy: s=gQ",
+            "_ _ _ _ R _ _ _",
+            "_ _ _ _ P _ _ _",
+            "_ _ _ _ s _ _ _",
+            "_ _ _ _ _ _ _ _",
+            "_ _ _ _ _ _ _ _",
+            "_ _ _ _ _ _ _ _",
+            "_ _ _ _ _ _ _ _",
+            "_ _ _ _ r _ _ _",
+        ]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+        let table = parse_position(&lines).unwrap();
+        assert!(is_in_check(&table, Color::Black));
+        assert!(!is_in_check(&table, Color::White));
+    }
+}
@@ -0,0 +1,399 @@
+use std::sync::RwLock;
+
+use crate::game::table::generator::{
+    push_castlings, push_pawn_moves, push_steps, BISHOP_STEPS, KNIGHT_JUMPS, ROOK_STEPS,
+};
+use crate::game::table::moves::Move;
+use crate::game::table::piece::position::Position;
+use crate::game::table::piece::{Piece, PieceType};
+use crate::game::table::symmetry::Symmetry;
+use crate::game::table::{
+    check_move_a, check_move_c, check_move_d, check_move_p, check_move_r, check_move_t,
+    is_path_clear, Table,
+};
+
+/// The symbols of the built-in pieces in table files and in FEN, which registered piece kinds
+/// cannot use.
+const RESERVED_SYMBOLS: [char; 10] = ['r', 'd', 'a', 'c', 't', 'p', 'k', 'q', 'b', 'n'];
+
+/// The piece kinds registered with `register_piece_kind`.
+static REGISTERED: RwLock<Vec<&'static dyn PieceKind>> = RwLock::new(Vec::new());
+
+/// A trait describing how a kind of piece moves and captures.
+///
+/// The built-in pieces implement it, and other kinds can be added with
+/// `register_piece_kind`: tables, move generation and notation then handle them like any
+/// other piece, as `PieceType::Custom`.
+pub trait PieceKind: Send + Sync {
+    /// Returns the name of the piece kind, such as `King`.
+    fn name(&self) -> &str;
+
+    /// Returns the lowercase letter of the piece kind in table files, such as `r` for the
+    /// king. It is written in uppercase for black pieces.
+    fn symbol(&self) -> char;
+
+    /// Returns the Betza notation of the piece kind, if it is defined by one, so that table
+    /// files can define it again. See `Betza`.
+    fn betza(&self) -> Option<&str> {
+        None
+    }
+
+    /// Checks if a piece of this kind can capture a piece standing on `target`.
+    ///
+    /// # Arguments
+    ///
+    /// * `table` - A reference to the `Table` the piece stands on, to check for pieces in the
+    ///   way.
+    /// * `piece` - A reference to the attacking `Piece`.
+    /// * `target` - The position of the square attacked.
+    ///
+    fn attacks(&self, table: &Table, piece: &Piece, target: &Position) -> bool;
+
+    /// Pushes the moves of a piece of this kind that follow its movement rules, without
+    /// checking whether they leave the king of its player in check.
+    ///
+    /// # Arguments
+    ///
+    /// * `table` - A reference to the `Table` the piece stands on.
+    /// * `piece` - A reference to the `Piece` to move.
+    /// * `moves` - The vector where the moves are pushed.
+    ///
+    fn moves(&self, table: &Table, piece: &Piece, moves: &mut Vec<Move>);
+
+    /// Checks if a transformation of the board leaves the movement of this kind of piece
+    /// unchanged, so that tables holding it can be compared by their canonical form.
+    ///
+    /// Only the identity is known to do so unless the kind tells otherwise, as pieces may
+    /// move differently forward and backward or to each side.
+    fn is_preserved_by(&self, symmetry: &Symmetry) -> bool {
+        *symmetry == Symmetry::IDENTITY
+    }
+}
+
+/// The king, which moves one square in any direction and castles.
+pub struct King;
+
+/// The queen, which slides along rows, columns and diagonals.
+pub struct Queen;
+
+/// The bishop, which slides along diagonals.
+pub struct Bishop;
+
+/// The knight, which jumps two squares in one direction and one in the other.
+pub struct Knight;
+
+/// The rook, which slides along rows and columns.
+pub struct Rook;
+
+/// The pawn, which pushes forward, captures diagonally and is promoted on the last row.
+pub struct Pawn;
+
+impl PieceKind for King {
+    fn name(&self) -> &str {
+        "King"
+    }
+
+    fn symbol(&self) -> char {
+        'r'
+    }
+
+    fn attacks(&self, _table: &Table, piece: &Piece, target: &Position) -> bool {
+        check_move_r(&piece.position, target)
+    }
+
+    fn moves(&self, table: &Table, piece: &Piece, moves: &mut Vec<Move>) {
+        push_steps(table, piece, &ROOK_STEPS, false, moves);
+        push_steps(table, piece, &BISHOP_STEPS, false, moves);
+        push_castlings(table, piece, moves);
+    }
+}
+
+impl PieceKind for Queen {
+    fn name(&self) -> &str {
+        "Queen"
+    }
+
+    fn symbol(&self) -> char {
+        'd'
+    }
+
+    fn attacks(&self, table: &Table, piece: &Piece, target: &Position) -> bool {
+        check_move_d(&piece.position, target) && is_path_clear(table, &piece.position, target)
+    }
+
+    fn moves(&self, table: &Table, piece: &Piece, moves: &mut Vec<Move>) {
+        push_steps(table, piece, &ROOK_STEPS, true, moves);
+        push_steps(table, piece, &BISHOP_STEPS, true, moves);
+    }
+}
+
+impl PieceKind for Bishop {
+    fn name(&self) -> &str {
+        "Bishop"
+    }
+
+    fn symbol(&self) -> char {
+        'a'
+    }
+
+    fn attacks(&self, table: &Table, piece: &Piece, target: &Position) -> bool {
+        check_move_a(&piece.position, target) && is_path_clear(table, &piece.position, target)
+    }
+
+    fn moves(&self, table: &Table, piece: &Piece, moves: &mut Vec<Move>) {
+        push_steps(table, piece, &BISHOP_STEPS, true, moves);
+    }
+}
+
+impl PieceKind for Knight {
+    fn name(&self) -> &str {
+        "Knight"
+    }
+
+    fn symbol(&self) -> char {
+        'c'
+    }
+
+    fn attacks(&self, _table: &Table, piece: &Piece, target: &Position) -> bool {
+        check_move_c(&piece.position, target)
+    }
+
+    fn moves(&self, table: &Table, piece: &Piece, moves: &mut Vec<Move>) {
+        push_steps(table, piece, &KNIGHT_JUMPS, false, moves);
+    }
+}
+
+impl PieceKind for Rook {
+    fn name(&self) -> &str {
+        "Rook"
+    }
+
+    fn symbol(&self) -> char {
+        't'
+    }
+
+    fn attacks(&self, table: &Table, piece: &Piece, target: &Position) -> bool {
+        check_move_t(&piece.position, target) && is_path_clear(table, &piece.position, target)
+    }
+
+    fn moves(&self, table: &Table, piece: &Piece, moves: &mut Vec<Move>) {
+        push_steps(table, piece, &ROOK_STEPS, true, moves);
+    }
+}
+
+impl PieceKind for Pawn {
+    fn name(&self) -> &str {
+        "Pawn"
+    }
+
+    fn symbol(&self) -> char {
+        'p'
+    }
+
+    fn attacks(&self, _table: &Table, piece: &Piece, target: &Position) -> bool {
+        check_move_p(piece, target)
+    }
+
+    fn moves(&self, table: &Table, piece: &Piece, moves: &mut Vec<Move>) {
+        push_pawn_moves(table, piece, moves);
+    }
+}
+
+/// Returns the movement rules of a piece type.
+///
+/// # Returns
+///
+/// The `PieceKind` of the piece type, or `None` for `PieceType::Empty` and for custom piece
+/// types that were not registered.
+///
+pub fn piece_kind(piece_type: PieceType) -> Option<&'static dyn PieceKind> {
+    match piece_type {
+        PieceType::R => Some(&King),
+        PieceType::D => Some(&Queen),
+        PieceType::A => Some(&Bishop),
+        PieceType::C => Some(&Knight),
+        PieceType::T => Some(&Rook),
+        PieceType::P => Some(&Pawn),
+        PieceType::Custom(symbol) => registered(symbol),
+        PieceType::Empty => None,
+    }
+}
+
+/// Returns the piece type written with a lowercase letter in table files, built-in or
+/// registered.
+pub fn piece_type_from_symbol(symbol: char) -> Option<PieceType> {
+    match symbol {
+        'r' => Some(PieceType::R),
+        'd' => Some(PieceType::D),
+        'a' => Some(PieceType::A),
+        'c' => Some(PieceType::C),
+        't' => Some(PieceType::T),
+        'p' => Some(PieceType::P),
+        _ => registered(symbol).map(|_| PieceType::Custom(symbol)),
+    }
+}
+
+/// Checks that a symbol can be given to a new kind of piece.
+///
+/// # Errors
+///
+/// Returns an error if the symbol is not a lowercase ASCII letter, or is used by a built-in
+/// piece in table files or in FEN, or by a registered piece kind.
+///
+pub(crate) fn check_symbol(symbol: char) -> Result<(), String> {
+    if !symbol.is_ascii_lowercase() {
+        return Err(format!("ERROR: Invalid piece symbol: {}", symbol));
+    }
+    if RESERVED_SYMBOLS.contains(&symbol) || registered(symbol).is_some() {
+        return Err(format!("ERROR: Piece symbol already in use: {}", symbol));
+    }
+    Ok(())
+}
+
+/// Registers a new kind of piece, so that it can be placed on tables and moved like the
+/// built-in pieces.
+///
+/// Registering the same kind again has no effect.
+///
+/// # Arguments
+///
+/// * `kind` - The movement rules of the piece kind. Its symbol must be a lowercase ASCII
+///   letter not used by another piece kind, nor by the built-in pieces in table files or in
+///   FEN.
+///
+/// # Returns
+///
+/// * `Ok(PieceType)` - The `PieceType::Custom` of the piece kind.
+///
+/// * `Err(String)` - An error message if the symbol is invalid or already in use.
+///
+pub fn register_piece_kind(kind: &'static dyn PieceKind) -> Result<PieceType, String> {
+    let symbol = kind.symbol();
+    if !symbol.is_ascii_lowercase() {
+        return Err(format!("ERROR: Invalid piece symbol: {}", symbol));
+    }
+    let mut registered = REGISTERED.write().unwrap_or_else(|e| e.into_inner());
+    match registered.iter().find(|other| other.symbol() == symbol) {
+        Some(other) if std::ptr::addr_eq(*other, kind) => {}
+        Some(_) => return Err(format!("ERROR: Piece symbol already in use: {}", symbol)),
+        None if RESERVED_SYMBOLS.contains(&symbol) => {
+            return Err(format!("ERROR: Piece symbol already in use: {}", symbol))
+        }
+        None => registered.push(kind),
+    }
+    Ok(PieceType::Custom(symbol))
+}
+
+/// Returns the registered piece kind with a symbol.
+fn registered(symbol: char) -> Option<&'static dyn PieceKind> {
+    REGISTERED
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .find(|kind| kind.symbol() == symbol)
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    /// A piece that moves like a king but cannot capture backwards, to test registration.
+    struct Guard;
+
+    impl super::PieceKind for Guard {
+        fn name(&self) -> &str {
+            "Guard"
+        }
+
+        fn symbol(&self) -> char {
+            'g'
+        }
+
+        fn attacks(
+            &self,
+            _table: &crate::game::table::Table,
+            piece: &crate::game::table::piece::Piece,
+            target: &crate::game::table::piece::position::Position,
+        ) -> bool {
+            piece.position.x.abs_diff(target.x) <= 1 && piece.position.y.abs_diff(target.y) == 1
+        }
+
+        fn moves(
+            &self,
+            table: &crate::game::table::Table,
+            piece: &crate::game::table::piece::Piece,
+            moves: &mut Vec<crate::game::table::moves::Move>,
+        ) {
+            use crate::game::table::generator::push_steps;
+            push_steps(
+                table,
+                piece,
+                &[(-1, -1), (0, -1), (1, -1), (-1, 1), (0, 1), (1, 1)],
+                false,
+                moves,
+            );
+        }
+    }
+
+    static GUARD: Guard = Guard;
+
+    #[test]
+    fn test_piece_kind() {
+        use super::*;
+        assert_eq!(piece_kind(PieceType::R).unwrap().name(), "King");
+        assert_eq!(piece_kind(PieceType::C).unwrap().symbol(), 'c');
+        assert!(piece_kind(PieceType::Empty).is_none());
+        assert!(piece_kind(PieceType::Custom('z')).is_none());
+        assert_eq!(piece_type_from_symbol('t'), Some(PieceType::T));
+        assert_eq!(piece_type_from_symbol('z'), None);
+    }
+
+    #[test]
+    fn test_register_piece_kind() {
+        use super::*;
+        use crate::game::table::fen::{format_fen, parse_fen};
+        use crate::game::table::generator::{is_in_check, legal_moves};
+        use crate::game::table::piece::Color;
+        use crate::game::table::{format_table, parse_position};
+
+        let guard = register_piece_kind(&GUARD).unwrap();
+        assert_eq!(guard, PieceType::Custom('g'));
+        assert_eq!(register_piece_kind(&GUARD), Ok(guard));
+        assert_eq!(piece_type_from_symbol('g'), Some(guard));
+        assert_eq!(piece_kind(guard).unwrap().name(), "Guard");
+
+        // Built-in letters are taken, as table file or FEN letters
+        struct Impostor;
+        impl PieceKind for Impostor {
+            fn name(&self) -> &str {
+                "Impostor"
+            }
+            fn symbol(&self) -> char {
+                'n'
+            }
+            fn attacks(&self, _: &Table, _: &Piece, _: &Position) -> bool {
+                false
+            }
+            fn moves(&self, _: &Table, _: &Piece, _: &mut Vec<Move>) {}
+        }
+        static IMPOSTOR: Impostor = Impostor;
+        assert_eq!(
+            register_piece_kind(&IMPOSTOR).err(),
+            Some("ERROR: Piece symbol already in use: n".to_string())
+        );
+
+        // The guard is read from files and FEN, gives check and moves sideways only when the
+        // square is also one row away
+        let table = parse_fen("4k3/8/8/8/8/8/3g4/4K3 w - - 0 1").unwrap();
+        assert!(is_in_check(&table, Color::White));
+        assert_eq!(format_fen(&table), "4k3/8/8/8/8/8/3g4/4K3 w - - 0 1");
+        let lines = format_table(&table);
+        assert_eq!(lines[6], "_ _ _ G _ _ _ _");
+        assert_eq!(parse_position(&lines).unwrap(), table);
+        let table = parse_fen("4k3/8/8/8/8/8/8/3GK3 w - - 0 1").unwrap();
+        let guard_moves = legal_moves(&table)
+            .into_iter()
+            .filter(|mv| mv.from == Position { x: 3, y: 7 })
+            .count();
+        assert_eq!(guard_moves, 3);
+    }
+}
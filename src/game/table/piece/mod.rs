@@ -1,9 +1,13 @@
+pub mod betza;
+pub mod kind;
 pub mod position;
 
+use crate::game::table::piece::kind::piece_kind;
 use crate::game::table::piece::position::Position;
+use crate::game::table::BOARD_SIZE;
 
 /// An enum representing the types of pieces in a table game.
-#[derive(PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum PieceType {
     /// King.
     R,
@@ -17,12 +21,20 @@ pub enum PieceType {
     T,
     /// Pawn.
     P,
+    /// A piece kind registered with `register_piece_kind` or defined by the `fairy` header of
+    /// a table file, identified by its symbol.
+    Custom(char),
     /// Empty.
     Empty,
 }
 
+impl PieceType {
+    /// The piece types a pawn can be promoted to when it reaches the last row.
+    pub const PROMOTIONS: [PieceType; 4] = [PieceType::D, PieceType::T, PieceType::A, PieceType::C];
+}
+
 /// An enum representing the colors of chess piece.
-#[derive(PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum Color {
     /// The white color.
     White,
@@ -32,8 +44,19 @@ pub enum Color {
     Empty,
 }
 
+impl Color {
+    /// Returns the color of the opponent. The empty color has no opponent.
+    pub fn opposite(&self) -> Color {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+            Color::Empty => Color::Empty,
+        }
+    }
+}
+
 /// A struct representing a chess piece.
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Piece {
     /// The type of the piece.
     pub piece_type: PieceType,
@@ -43,6 +66,12 @@ pub struct Piece {
     pub color: Color,
 }
 
+impl Default for Piece {
+    fn default() -> Self {
+        Piece::new()
+    }
+}
+
 impl Piece {
     /// Creates a new `Piece` instance with default values.
     pub fn new() -> Piece {
@@ -52,4 +81,35 @@ impl Piece {
             color: Color::Empty,
         }
     }
+
+    /// Returns `true` if the piece is a pawn that gets promoted when it moves to `position`,
+    /// that is, when `position` is on the last row from the point of view of its color.
+    pub fn promotes_at(&self, position: &Position) -> bool {
+        if self.piece_type != PieceType::P {
+            return false;
+        }
+        match self.color {
+            Color::White => position.y == 0,
+            Color::Black => position.y == BOARD_SIZE - 1,
+            Color::Empty => false,
+        }
+    }
+
+    /// Returns the character used to represent the piece in a table file.
+    ///
+    /// White pieces are written in lowercase and black pieces in uppercase, and empty
+    /// squares are written as `_`.
+    pub fn symbol(&self) -> char {
+        let c = match self.piece_type {
+            PieceType::Custom(symbol) => symbol,
+            piece_type => match piece_kind(piece_type) {
+                Some(kind) => kind.symbol(),
+                None => return '_',
+            },
+        };
+        match self.color {
+            Color::Black => c.to_ascii_uppercase(),
+            _ => c,
+        }
+    }
 }
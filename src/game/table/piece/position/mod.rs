@@ -1,8 +1,131 @@
+use crate::game::table::BOARD_SIZE;
+use std::fmt;
+use std::str::FromStr;
+
 /// A struct representing the position of a chess piece on the board.
-#[derive(Default, PartialEq, Debug)]
+///
+/// The x-coordinate is the column, from 0 for the a file to 7 for the h file, and the
+/// y-coordinate is the row of the table file, from 0 for the top row (rank 8) to 7 for the
+/// bottom row (rank 1). Positions are written as algebraic squares such as `e4`.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
 pub struct Position {
     /// The x-coordinate of the position.
     pub x: usize,
     /// The y-coordinate of the position.
     pub y: usize,
 }
+
+impl Position {
+    /// Creates a new `Position`, checking that it is on the board.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The column of the position, from 0 (a file) to 7 (h file).
+    /// * `y` - The row of the position, from 0 (rank 8) to 7 (rank 1).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either coordinate is off the board.
+    ///
+    pub fn new(x: usize, y: usize) -> Result<Position, String> {
+        if x >= BOARD_SIZE || y >= BOARD_SIZE {
+            return Err(format!(
+                "ERROR: Position ({}, {}) is off the board, coordinates must be lower than {}",
+                x, y, BOARD_SIZE
+            ));
+        }
+        Ok(Position { x, y })
+    }
+
+    /// Creates a new `Position` from a file letter (`a` to `h`) and a rank number (1 to 8).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file or the rank are off the board.
+    ///
+    pub fn from_file_rank(file: char, rank: usize) -> Result<Position, String> {
+        let x = (file as usize).wrapping_sub('a' as usize);
+        if x >= BOARD_SIZE || rank == 0 || rank > BOARD_SIZE {
+            return Err(format!("ERROR: Invalid square: {}{}", file, rank));
+        }
+        Ok(Position {
+            x,
+            y: BOARD_SIZE - rank,
+        })
+    }
+
+    /// Returns the file letter of the position, from `a` to `h`.
+    pub fn file(&self) -> char {
+        (b'a' + self.x as u8) as char
+    }
+
+    /// Returns the rank number of the position, from 1 to 8.
+    pub fn rank(&self) -> usize {
+        BOARD_SIZE - self.y
+    }
+}
+
+impl fmt::Display for Position {
+    /// Formats the position as an algebraic square such as `e4`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.file(), self.rank())
+    }
+}
+
+impl FromStr for Position {
+    type Err = String;
+
+    /// Parses an algebraic square such as `e4` into a `Position`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the string is not a file letter followed by a rank number on the
+    /// board.
+    ///
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        match (
+            chars.next(),
+            chars.next().and_then(|c| c.to_digit(10)),
+            chars.next(),
+        ) {
+            (Some(file), Some(rank), None) => Position::from_file_rank(file, rank as usize),
+            _ => Err(format!("ERROR: Invalid square: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_algebraic_notation() {
+        use super::*;
+        assert_eq!("a8".parse::<Position>(), Ok(Position { x: 0, y: 0 }));
+        assert_eq!("h1".parse::<Position>(), Ok(Position { x: 7, y: 7 }));
+        assert_eq!("e4".parse::<Position>(), Ok(Position { x: 4, y: 4 }));
+        assert_eq!(Position { x: 4, y: 4 }.to_string(), "e4");
+        assert_eq!(Position { x: 2, y: 6 }.to_string(), "c2");
+
+        for x in 0..BOARD_SIZE {
+            for y in 0..BOARD_SIZE {
+                let position = Position::new(x, y).unwrap();
+                assert_eq!(position.to_string().parse::<Position>(), Ok(position));
+            }
+        }
+
+        for square in ["", "e", "4", "i1", "a0", "a9", "e+4", "e44", "e04", "E4"] {
+            assert_eq!(
+                square.parse::<Position>(),
+                Err(format!("ERROR: Invalid square: {}", square))
+            );
+        }
+        assert_eq!(
+            Position::new(8, 0),
+            Err(
+                "ERROR: Position (8, 0) is off the board, coordinates must be lower than 8"
+                    .to_string()
+            )
+        );
+        assert!(Position::new(0, 8).is_err());
+    }
+}
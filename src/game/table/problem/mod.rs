@@ -0,0 +1,270 @@
+use crate::game::table::generator::{is_in_check, legal_moves};
+use crate::game::table::moves::{make_move, unmake_move, Move};
+use crate::game::table::notation::{format_san, format_uci, Language};
+use crate::game::table::transposition::TranspositionTable;
+use crate::game::table::zobrist::zobrist_hash;
+use crate::game::table::Table;
+
+/// The highest number of defenses refuting a first move for it to be reported as a try.
+pub const MAX_REFUTATIONS: usize = 1;
+
+/// The number of tables whose result is remembered while solving.
+const TRANSPOSITION_TABLE_SIZE: usize = 1 << 16;
+
+/// A struct representing a first move that almost solves a problem: it forces mate against
+/// every defense but a few, its refutations.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Try {
+    /// The first move of the attacker.
+    pub key: Move,
+    /// The defenses after which the attacker cannot force mate in time.
+    pub refutations: Vec<Move>,
+}
+
+/// A struct representing the solution of a mate-in-N problem.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Solution {
+    /// The first moves that force mate in at most N moves, whatever the defense.
+    pub keys: Vec<Move>,
+    /// The first moves refuted by at most `MAX_REFUTATIONS` defenses.
+    pub tries: Vec<Try>,
+}
+
+impl Solution {
+    /// Checks if the problem has a single key, as a sound problem must.
+    pub fn is_sound(&self) -> bool {
+        self.keys.len() == 1
+    }
+
+    /// Checks if the problem is cooked, that is, if it has more than one key.
+    pub fn is_cooked(&self) -> bool {
+        self.keys.len() > 1
+    }
+}
+
+/// Solves a problem where the player to move must force mate in at most `moves` moves.
+///
+/// Every first move of the attacker is searched exhaustively against every defense, so the
+/// solution is exact, but the time taken grows quickly with `moves`.
+///
+/// # Arguments
+///
+/// * `table` - A reference to the `Table` of the problem, with the attacker to move. It is
+///   left untouched.
+/// * `moves` - The number of moves of the attacker, counting the mating move.
+///
+/// # Returns
+///
+/// * `Ok(Solution)` - The keys of the problem, none if it has no solution and more than one
+///   if it is cooked, and its tries with their refutations.
+///
+/// * `Err(String)` - An error message if `moves` is 0.
+///
+pub fn solve_mate_in(table: &Table, moves: usize) -> Result<Solution, String> {
+    if moves == 0 {
+        return Err("ERROR: A mate takes at least 1 move".to_string());
+    }
+    let mut solver = Solver {
+        results: TranspositionTable::new(TRANSPOSITION_TABLE_SIZE),
+    };
+    let mut scratch = table.clone();
+    let mut solution = Solution {
+        keys: Vec::new(),
+        tries: Vec::new(),
+    };
+    for key in legal_moves(table) {
+        if make_move(&mut scratch, &key).is_err() {
+            continue;
+        }
+        match solver.refutations(&mut scratch, moves - 1, MAX_REFUTATIONS) {
+            Some(refutations) if refutations.is_empty() => solution.keys.push(key),
+            Some(refutations) if refutations.len() <= MAX_REFUTATIONS => {
+                solution.tries.push(Try { key, refutations })
+            }
+            _ => {}
+        }
+        unmake_move(&mut scratch);
+    }
+    Ok(solution)
+}
+
+/// Formats the solution of a problem as lines of text, with moves in algebraic notation.
+///
+/// # Arguments
+///
+/// * `table` - A reference to the `Table` of the problem.
+/// * `solution` - A reference to the `Solution` of the problem.
+/// * `language` - The language of the piece letters.
+///
+/// # Returns
+///
+/// One `Key: ...` line per key, or `No solution`, then one `Try: ... refuted by ...` line
+/// per try, and a last `Cooked: ...` line if the problem has more than one key.
+///
+pub fn format_solution(table: &Table, solution: &Solution, language: Language) -> Vec<String> {
+    let san = |table: &Table, mv: &Move| {
+        format_san(table, mv, language).unwrap_or_else(|_| format_uci(mv))
+    };
+    let mut lines: Vec<String> = solution
+        .keys
+        .iter()
+        .map(|key| format!("Key: {}", san(table, key)))
+        .collect();
+    if lines.is_empty() {
+        lines.push("No solution".to_string());
+    }
+    for try_move in &solution.tries {
+        let mut after = table.clone();
+        let refutations: Vec<String> = match make_move(&mut after, &try_move.key) {
+            Ok(()) => try_move
+                .refutations
+                .iter()
+                .map(|refutation| san(&after, refutation))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        lines.push(format!(
+            "Try: {} refuted by {}",
+            san(table, &try_move.key),
+            refutations.join(", ")
+        ));
+    }
+    if solution.is_cooked() {
+        lines.push(format!("Cooked: {} keys", solution.keys.len()));
+    }
+    lines
+}
+
+/// A struct holding the results remembered while solving a problem.
+struct Solver {
+    /// For each table with the attacker to move, the most moves in which the attacker is
+    /// known not to force mate, and the fewest moves in which it is known to.
+    results: TranspositionTable<(Option<usize>, Option<usize>)>,
+}
+
+impl Solver {
+    /// Finds the defenses after which the attacker cannot force mate in `moves` moves.
+    ///
+    /// # Arguments
+    ///
+    /// * `table` - A mutable reference to the `Table`, with the defender to move. Every move
+    ///   applied is taken back.
+    /// * `moves` - The number of moves left to the attacker.
+    /// * `limit` - The number of refutations after which the search stops, once one more
+    ///   refutation is found.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(refutations)` - The refuting defenses, none if the attacker forces mate. At
+    ///   most `limit + 1` refutations are returned.
+    ///
+    /// * `None` - If the defender is stalemated, which the attacker cannot turn into a win.
+    ///
+    fn refutations(&mut self, table: &mut Table, moves: usize, limit: usize) -> Option<Vec<Move>> {
+        let defenses = legal_moves(table);
+        if defenses.is_empty() {
+            return if is_in_check(table, table.state().side_to_move) {
+                Some(Vec::new())
+            } else {
+                None
+            };
+        }
+        let mut refutations = Vec::new();
+        for defense in defenses {
+            if make_move(table, &defense).is_err() {
+                continue;
+            }
+            if !self.forces_mate(table, moves) {
+                refutations.push(defense);
+            }
+            unmake_move(table);
+            if refutations.len() > limit {
+                break;
+            }
+        }
+        Some(refutations)
+    }
+
+    /// Checks if the player to move forces mate in at most `moves` moves.
+    fn forces_mate(&mut self, table: &mut Table, moves: usize) -> bool {
+        if moves == 0 {
+            return false;
+        }
+        let hash = zobrist_hash(table);
+        let (mut fails, mut mates) = self.results.get(hash).copied().unwrap_or((None, None));
+        if mates.is_some_and(|mates| mates <= moves) {
+            return true;
+        }
+        if fails.is_some_and(|fails| fails >= moves) {
+            return false;
+        }
+
+        let mut found = false;
+        for mv in legal_moves(table) {
+            if make_move(table, &mv).is_err() {
+                continue;
+            }
+            found = self
+                .refutations(table, moves - 1, 0)
+                .is_some_and(|refutations| refutations.is_empty());
+            unmake_move(table);
+            if found {
+                break;
+            }
+        }
+
+        if found {
+            mates = Some(mates.map_or(moves, |mates| mates.min(moves)));
+        } else {
+            fails = Some(fails.map_or(moves, |fails| fails.max(moves)));
+        }
+        self.results.insert(hash, (fails, mates));
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    fn setup_problem_test(path: &str) -> crate::game::table::Table {
+        use crate::game::table::parse_position;
+        use crate::utils::read_file;
+        parse_position(&read_file(&path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_solve_mate_in() {
+        use super::*;
+        use crate::game::table::notation::parse_uci;
+
+        // A quiet key, with two checks that fail against a single defense each
+        let table = setup_problem_test("tables/mate_in_2.txt");
+        assert!(solve_mate_in(&table, 1).unwrap().keys.is_empty());
+        let solution = solve_mate_in(&table, 2).unwrap();
+        assert!(solution.is_sound());
+        assert_eq!(solution.keys, vec![parse_uci("a2f7").unwrap()]);
+        assert_eq!(
+            format_solution(&table, &solution, Language::Spanish),
+            vec![
+                "Key: Df7",
+                "Try: Cc6+ refuted by Rc7",
+                "Try: Tb8+ refuted by Re7",
+            ]
+        );
+
+        // The king can step aside on two squares before the rook mates
+        let table = setup_problem_test("tables/cooked.txt");
+        let solution = solve_mate_in(&table, 2).unwrap();
+        assert!(solution.is_cooked());
+        assert_eq!(
+            solution.keys,
+            vec![parse_uci("f6g6").unwrap(), parse_uci("f6f7").unwrap()]
+        );
+        let lines = format_solution(&table, &solution, Language::English);
+        assert_eq!(lines.last().unwrap(), "Cooked: 2 keys");
+
+        let table = setup_problem_test("tables/back_rank.txt");
+        let solution = solve_mate_in(&table, 1).unwrap();
+        assert_eq!(solution.keys, vec![parse_uci("e1e8").unwrap()]);
+        assert!(solve_mate_in(&table, 0).is_err());
+    }
+}
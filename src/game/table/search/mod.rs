@@ -0,0 +1,536 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::game::table::evaluation::{evaluate, piece_value};
+use crate::game::table::generator::{is_in_check, is_legal_after, pseudo_legal_moves};
+use crate::game::table::moves::{is_capture, make_move, unmake_move, Move};
+use crate::game::table::piece::PieceType;
+use crate::game::table::transposition::TranspositionTable;
+use crate::game::table::variant::variant_winner;
+use crate::game::table::zobrist::zobrist_hash;
+use crate::game::table::Table;
+
+/// The score of a checkmate, in centipawns. A mate found `n` moves into the search scores
+/// `MATE_SCORE - n`, so that shorter mates are preferred.
+pub const MATE_SCORE: i32 = 100_000;
+
+/// The deepest search run when no depth limit is given.
+pub const MAX_DEPTH: usize = 64;
+
+/// The number of nodes searched between two checks of the time limit.
+const TIME_CHECK_INTERVAL: u64 = 1024;
+
+/// The number of tables whose score is remembered during a search.
+const TRANSPOSITION_TABLE_SIZE: usize = 1 << 16;
+
+/// A struct representing the limits of a search. A search without limits goes on until
+/// `MAX_DEPTH`.
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct SearchLimits {
+    /// The maximum number of moves to search ahead, not counting the captures searched by
+    /// the quiescence search.
+    pub depth: Option<usize>,
+    /// The time after which the search stops, keeping the result of the last depth fully
+    /// searched. The first depth is always completed, whatever the time limit.
+    pub time: Option<Duration>,
+    /// The moves the search is restricted to at the root, or `None` to search every legal
+    /// move. The moves must be legal.
+    pub root_moves: Option<Vec<Move>>,
+}
+
+/// A struct representing the result of a search.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchResult {
+    /// The best move found, or `None` if the player to move has no legal moves.
+    pub best_move: Option<Move>,
+    /// The score of the best move, in centipawns, from the point of view of the player to
+    /// move.
+    pub score: i32,
+    /// The last depth fully searched.
+    pub depth: usize,
+    /// The number of positions visited.
+    pub nodes: u64,
+    /// The moves expected to be played by both players, starting with the best move.
+    pub principal_variation: Vec<Move>,
+}
+
+/// An enum representing how the score of a table relates to its exact score.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Bound {
+    /// The score is exact.
+    Exact,
+    /// The exact score is at least the score, since the search stopped at a good enough move.
+    Lower,
+    /// The exact score is at most the score, since no move was good enough.
+    Upper,
+}
+
+/// A struct representing what a search remembers about a table it already searched.
+#[derive(Clone, Copy, Debug)]
+struct Entry {
+    /// The depth the table was searched to.
+    depth: usize,
+    /// The score of the table, with checkmates counted from the table itself.
+    score: i32,
+    /// How the score relates to the exact score.
+    bound: Bound,
+    /// The best move found, which is searched first the next time.
+    best_move: Option<Move>,
+}
+
+/// A struct holding the progress of a running search.
+struct Searcher<'a> {
+    /// The moment the search started.
+    start: Instant,
+    /// The time after which the search stops.
+    time: Option<Duration>,
+    /// A flag set by another thread to stop the search.
+    stop: &'a AtomicBool,
+    /// The moves searched at the root, if not every legal move.
+    root_moves: Option<&'a [Move]>,
+    /// The number of positions visited so far.
+    nodes: u64,
+    /// Whether the search can be stopped, which is only the case once a depth is complete.
+    can_stop: bool,
+    /// Whether the time ran out, in which case the scores being computed are meaningless.
+    stopped: bool,
+    /// The principal variation of the last depth searched, whose moves are tried first.
+    previous: Vec<Move>,
+    /// The tables already searched, by Zobrist hash, so that tables reached again through
+    /// another order of moves or at the next depth are not searched again.
+    transpositions: TranspositionTable<Entry>,
+}
+
+/// Searches the best move of the player to move with an iterative-deepening alpha-beta
+/// search.
+///
+/// Each depth is searched in turn, trying first the moves that were best at the previous
+/// depth. The scores of the tables searched are remembered in a transposition table, so
+/// that tables reached again are not searched again. Once the depth is reached, captures are
+/// searched until the position is quiet, so that pieces left hanging are not missed.
+///
+/// # Arguments
+///
+/// * `table` - A reference to the `Table` to search. Moves are applied to a copy, so the
+///   table and its history are left untouched.
+/// * `limits` - The depth and time limits of the search, and the moves it is restricted
+///   to.
+///
+/// # Returns
+///
+/// The result of the last depth fully searched.
+///
+pub fn search(table: &Table, limits: &SearchLimits) -> SearchResult {
+    search_with(table, limits, &AtomicBool::new(false), |_| {})
+}
+
+/// Searches the best move of the player to move like `search`, but can be stopped from
+/// another thread and reports the result of each depth as soon as it is searched.
+///
+/// # Arguments
+///
+/// * `table` - A reference to the `Table` to search, which is left untouched.
+/// * `limits` - The depth and time limits of the search, and the moves it is restricted
+///   to.
+/// * `stop` - A flag that stops the search once set, keeping the result of the last depth
+///   fully searched. The first depth is always completed.
+/// * `report` - A function called with the result of each depth fully searched.
+///
+/// # Returns
+///
+/// The result of the last depth fully searched.
+///
+pub fn search_with(
+    table: &Table,
+    limits: &SearchLimits,
+    stop: &AtomicBool,
+    mut report: impl FnMut(&SearchResult),
+) -> SearchResult {
+    let mut scratch = table.clone();
+    let mut searcher = Searcher {
+        start: Instant::now(),
+        time: limits.time,
+        stop,
+        root_moves: limits.root_moves.as_deref(),
+        nodes: 0,
+        can_stop: false,
+        stopped: false,
+        previous: Vec::new(),
+        transpositions: TranspositionTable::new(TRANSPOSITION_TABLE_SIZE),
+    };
+    let mut result = SearchResult {
+        best_move: None,
+        score: 0,
+        depth: 0,
+        nodes: 0,
+        principal_variation: Vec::new(),
+    };
+
+    let max_depth = limits.depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH);
+    for depth in 1..=max_depth {
+        let mut pv = Vec::new();
+        let score = searcher.alpha_beta(&mut scratch, depth, 0, -MATE_SCORE, MATE_SCORE, &mut pv);
+        if searcher.stopped {
+            break;
+        }
+        result.best_move = pv.first().copied();
+        result.score = score;
+        result.depth = depth;
+        result.principal_variation = pv.clone();
+        result.nodes = searcher.nodes;
+        report(&result);
+        searcher.previous = pv;
+        searcher.can_stop = true;
+        if score.abs() >= MATE_SCORE - MAX_DEPTH as i32 || searcher.check_stop() {
+            break;
+        }
+    }
+    result.nodes = searcher.nodes;
+    result
+}
+
+/// Searches the best move of the player to move, down to the given depth.
+pub fn search_depth(table: &Table, depth: usize) -> SearchResult {
+    search(
+        table,
+        &SearchLimits {
+            depth: Some(depth),
+            ..SearchLimits::default()
+        },
+    )
+}
+
+/// Searches the best move of the player to move for about the given time.
+pub fn search_time(table: &Table, time: Duration) -> SearchResult {
+    search(
+        table,
+        &SearchLimits {
+            time: Some(time),
+            ..SearchLimits::default()
+        },
+    )
+}
+
+/// Returns the number of moves until checkmate when a score is a checkmate score.
+///
+/// # Returns
+///
+/// * `Some(n)` - The player to move mates in `n` moves if `n` is positive, or gets mated in
+///   `-n` moves if `n` is negative. `Some(0)` means the player to move is already mated.
+///
+/// * `None` - If the score is not a checkmate score.
+///
+pub fn mate_in(score: i32) -> Option<i32> {
+    if score.abs() < MATE_SCORE - MAX_DEPTH as i32 {
+        return None;
+    }
+    let moves = (MATE_SCORE - score.abs() + 1) / 2;
+    Some(if score > 0 { moves } else { -moves })
+}
+
+impl Searcher<'_> {
+    /// Searches a table to the given depth, returning its score for the player to move.
+    ///
+    /// Scores outside of the `alpha`-`beta` window are not exact: a score of at most `alpha`
+    /// means the opponent already has a better option elsewhere, and a score of at least
+    /// `beta` means the player already had a better option. `pv` is filled with the best
+    /// line found when the score is inside the window.
+    fn alpha_beta(
+        &mut self,
+        table: &mut Table,
+        depth: usize,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+        pv: &mut Vec<Move>,
+    ) -> i32 {
+        pv.clear();
+        if let Some(score) = variant_score(table, ply) {
+            return score;
+        }
+        if depth == 0 {
+            return self.quiescence(table, ply, alpha, beta);
+        }
+        self.nodes += 1;
+        if self.out_of_time() {
+            return 0;
+        }
+
+        let hash = zobrist_hash(table);
+        let stored = self.transpositions.get(hash).copied();
+        if let Some(entry) = stored.filter(|entry| entry.depth >= depth && ply > 0) {
+            let score = from_stored_score(entry.score, ply);
+            let usable = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => score >= beta,
+                Bound::Upper => score <= alpha,
+            };
+            if usable {
+                pv.extend(entry.best_move);
+                return score;
+            }
+        }
+
+        let original_alpha = alpha;
+        let color = table.state().side_to_move;
+        let mut moves = pseudo_legal_moves(table);
+        if let Some(root_moves) = self.root_moves.filter(|_| ply == 0) {
+            moves.retain(|mv| root_moves.contains(mv));
+        }
+        let hash_move = stored.and_then(|entry| entry.best_move);
+        self.order_moves(table, &mut moves, ply, hash_move);
+        let mut best = -MATE_SCORE;
+        let mut best_move = None;
+        let mut legal = 0;
+        let mut line = Vec::new();
+        for mv in moves {
+            if make_move(table, &mv).is_err() {
+                continue;
+            }
+            if !is_legal_after(table, color) {
+                unmake_move(table);
+                continue;
+            }
+            legal += 1;
+            let score = -self.alpha_beta(table, depth - 1, ply + 1, -beta, -alpha, &mut line);
+            unmake_move(table);
+            if self.stopped {
+                return 0;
+            }
+            if score > best {
+                best = score;
+                best_move = Some(mv);
+            }
+            if score > alpha {
+                alpha = score;
+                pv.clear();
+                pv.push(mv);
+                pv.extend_from_slice(&line);
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        if legal == 0 {
+            return if is_in_check(table, color) {
+                -MATE_SCORE + ply as i32
+            } else {
+                0
+            };
+        }
+
+        let bound = if best <= original_alpha {
+            Bound::Upper
+        } else if best >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        self.transpositions.insert(
+            hash,
+            Entry {
+                depth,
+                score: to_stored_score(best, ply),
+                bound,
+                best_move,
+            },
+        );
+        best
+    }
+
+    /// Searches the captures and promotions of a table until no more are left, so that the
+    /// evaluation is only trusted in quiet positions.
+    ///
+    /// The player to move can always decline to capture, so the evaluation of the table is a
+    /// lower bound of its score.
+    fn quiescence(&mut self, table: &mut Table, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        if self.out_of_time() {
+            return 0;
+        }
+        if let Some(score) = variant_score(table, ply) {
+            return score;
+        }
+
+        let stand_pat = evaluate(table);
+        if stand_pat >= beta {
+            return stand_pat;
+        }
+        alpha = alpha.max(stand_pat);
+
+        let color = table.state().side_to_move;
+        let mut moves = pseudo_legal_moves(table);
+        moves.retain(|mv| is_capture(table, mv) || mv.promotion.is_some());
+        moves.sort_by_cached_key(|mv| -capture_order(table, mv));
+        let mut best = stand_pat;
+        for mv in moves {
+            if make_move(table, &mv).is_err() {
+                continue;
+            }
+            if !is_legal_after(table, color) {
+                unmake_move(table);
+                continue;
+            }
+            let score = -self.quiescence(table, ply + 1, -beta, -alpha);
+            unmake_move(table);
+            if self.stopped {
+                return 0;
+            }
+            if score > best {
+                best = score;
+            }
+            if score >= beta {
+                break;
+            }
+            alpha = alpha.max(score);
+        }
+        best
+    }
+
+    /// Sorts the moves to search the most promising ones first: the best move found the last
+    /// time the table was searched, the move of the previous principal variation, then
+    /// captures of the most valuable pieces by the least valuable ones, then the other moves.
+    fn order_moves(&self, table: &Table, moves: &mut [Move], ply: usize, hash_move: Option<Move>) {
+        let previous = self.previous.get(ply).copied();
+        moves.sort_by_cached_key(|mv| {
+            if Some(*mv) == hash_move {
+                i32::MIN
+            } else if Some(*mv) == previous {
+                i32::MIN + 1
+            } else {
+                -capture_order(table, mv)
+            }
+        });
+    }
+
+    /// Checks if the time limit is over or the search was stopped from another thread,
+    /// stopping the search if it can be stopped.
+    fn out_of_time(&mut self) -> bool {
+        if self.can_stop && self.nodes.is_multiple_of(TIME_CHECK_INTERVAL) {
+            self.check_stop();
+        }
+        self.stopped
+    }
+
+    /// Stops the search if the time limit is over or the search was stopped from another
+    /// thread, returning whether it is stopped.
+    fn check_stop(&mut self) -> bool {
+        let timed_out = self.time.is_some_and(|time| self.start.elapsed() >= time);
+        if timed_out || self.stop.load(Ordering::Relaxed) {
+            self.stopped = true;
+        }
+        self.stopped
+    }
+}
+
+/// Converts a score found `ply` moves into the search into the score stored for its table,
+/// counting checkmates from the table rather than from the root of the search.
+fn to_stored_score(score: i32, ply: usize) -> i32 {
+    if score >= MATE_SCORE - MAX_DEPTH as i32 {
+        score + ply as i32
+    } else if score <= -MATE_SCORE + MAX_DEPTH as i32 {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+/// Converts a score stored for a table into its score when found `ply` moves into the
+/// search.
+fn from_stored_score(score: i32, ply: usize) -> i32 {
+    if score >= MATE_SCORE - MAX_DEPTH as i32 {
+        score - ply as i32
+    } else if score <= -MATE_SCORE + MAX_DEPTH as i32 {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+/// Returns the score of a game won by the rules of its variant for the player to move,
+/// scored like a checkmate `ply` moves into the search, or `None` if the game goes on.
+fn variant_score(table: &Table, ply: usize) -> Option<i32> {
+    let winner = variant_winner(table)?;
+    Some(if winner == table.state().side_to_move {
+        MATE_SCORE - ply as i32
+    } else {
+        -MATE_SCORE + ply as i32
+    })
+}
+
+/// Returns how promising a move is: captures of valuable pieces by cheap ones and promotions
+/// score highest, and quiet moves score `0`.
+fn capture_order(table: &Table, mv: &Move) -> i32 {
+    let promotion = mv.promotion.map_or(0, piece_value);
+    if !is_capture(table, mv) {
+        return promotion;
+    }
+    let victim = table
+        .piece_at(&mv.to)
+        .map_or(piece_value(PieceType::P), |piece| {
+            piece_value(piece.piece_type)
+        });
+    let attacker = table
+        .piece_at(&mv.from)
+        .map_or(0, |piece| piece_value(piece.piece_type));
+    10 * victim - attacker + promotion + 1
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_search() {
+        use super::*;
+        use crate::game::table::fen::parse_fen;
+        use crate::game::table::notation::parse_uci;
+        use crate::game::table::parse_position;
+        use crate::utils::read_file;
+
+        // Mate in one on the back row
+        let table = parse_position(&read_file(&"tables/back_rank.txt").unwrap()).unwrap();
+        let result = search_depth(&table, 3);
+        assert_eq!(result.best_move, Some(parse_uci("e1e8").unwrap()));
+        assert_eq!(result.score, MATE_SCORE - 1);
+        assert_eq!(result.depth, 2);
+        assert_eq!(mate_in(result.score), Some(1));
+
+        // A queen left hanging is taken, and the table is left untouched
+        let table = parse_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+        let original = table.clone();
+        let result = search_depth(&table, 2);
+        assert_eq!(result.best_move, Some(parse_uci("d2d5").unwrap()));
+        assert!(result.score > 300);
+        assert_eq!(table, original);
+
+        // Taking the defended pawn would lose the queen
+        let table = parse_fen("4k3/2p5/3p4/8/8/8/3Q4/4K3 w - - 0 1").unwrap();
+        let result = search_depth(&table, 1);
+        assert_ne!(result.best_move, Some(parse_uci("d2d6").unwrap()));
+
+        // Nothing to play once checkmated
+        let table = parse_position(&read_file(&"tables/mate.txt").unwrap()).unwrap();
+        let result = search_depth(&table, 2);
+        assert_eq!(result.best_move, None);
+        assert_eq!(result.score, -MATE_SCORE);
+        assert_eq!(mate_in(result.score), Some(0));
+        assert_eq!(mate_in(-MATE_SCORE + 2), Some(-1));
+        assert_eq!(mate_in(300), None);
+    }
+
+    #[test]
+    fn test_search_time() {
+        use super::*;
+        use crate::game::table::fen::{parse_fen, START_FEN};
+        let table = parse_fen(START_FEN).unwrap();
+        let start = Instant::now();
+        let result = search_time(&table, Duration::from_millis(200));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(result.best_move.is_some());
+        assert!(result.depth >= 1);
+        assert_eq!(
+            result.principal_variation.first(),
+            result.best_move.as_ref()
+        );
+    }
+}
@@ -0,0 +1,589 @@
+use crate::game::table::piece::betza::parse_fairy_pieces;
+use crate::game::table::piece::position::Position;
+use crate::game::table::piece::{Color, PieceType};
+use crate::game::table::variant::Variant;
+use crate::game::table::BOARD_SIZE;
+
+/// A struct representing which castlings are still available to each player.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct CastlingRights {
+    /// White can still castle on the king's side.
+    pub white_king_side: bool,
+    /// White can still castle on the queen's side.
+    pub white_queen_side: bool,
+    /// Black can still castle on the king's side.
+    pub black_king_side: bool,
+    /// Black can still castle on the queen's side.
+    pub black_queen_side: bool,
+}
+
+/// A struct representing the files of the rooks each player castles with in Chess960.
+///
+/// Both players start with their rooks on the same files, so the files are shared.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CastlingFiles {
+    /// The file of the rook on the king's side, to the right of the king.
+    pub king_side: usize,
+    /// The file of the rook on the queen's side, to the left of the king.
+    pub queen_side: usize,
+}
+
+impl CastlingFiles {
+    /// The files of the rooks in standard chess.
+    pub const STANDARD: CastlingFiles = CastlingFiles {
+        king_side: BOARD_SIZE - 1,
+        queen_side: 0,
+    };
+
+    /// Returns the file of the rook on one side.
+    pub fn file(&self, king_side: bool) -> usize {
+        if king_side {
+            self.king_side
+        } else {
+            self.queen_side
+        }
+    }
+}
+
+/// A struct representing the pieces a player holds at Crazyhouse, captured from the other
+/// player, which they can drop on an empty square instead of moving.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Pocket {
+    /// The number of pieces of each of the `Pocket::PIECE_TYPES`.
+    counts: [u32; 5],
+}
+
+impl Pocket {
+    /// The piece types a pocket can hold, in the order they are written.
+    pub const PIECE_TYPES: [PieceType; 5] = [
+        PieceType::D,
+        PieceType::T,
+        PieceType::A,
+        PieceType::C,
+        PieceType::P,
+    ];
+
+    /// Returns the number of pieces of a type in the pocket.
+    pub fn count(&self, piece_type: PieceType) -> u32 {
+        Pocket::index(piece_type).map_or(0, |i| self.counts[i])
+    }
+
+    /// Adds a piece to the pocket. Kings and pieces of registered kinds cannot be held, and
+    /// are left out.
+    pub fn add(&mut self, piece_type: PieceType) {
+        if let Some(i) = Pocket::index(piece_type) {
+            self.counts[i] += 1;
+        }
+    }
+
+    /// Takes a piece out of the pocket.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the pocket held a piece of that type.
+    ///
+    pub fn remove(&mut self, piece_type: PieceType) -> bool {
+        match Pocket::index(piece_type) {
+            Some(i) if self.counts[i] > 0 => {
+                self.counts[i] -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if the pocket holds no piece.
+    pub fn is_empty(&self) -> bool {
+        self.counts.iter().all(|count| *count == 0)
+    }
+
+    /// Returns the index of a piece type in `Pocket::PIECE_TYPES`.
+    fn index(piece_type: PieceType) -> Option<usize> {
+        Pocket::PIECE_TYPES.iter().position(|p| *p == piece_type)
+    }
+}
+
+/// A struct representing the part of a game that cannot be deduced from the pieces on the
+/// table, because it depends on the moves that were played before.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct State {
+    /// The color of the player that moves next.
+    pub side_to_move: Color,
+    /// The castlings that are still available.
+    pub castling: CastlingRights,
+    /// The square a pawn skipped over with a double step on the previous move, if any.
+    pub en_passant: Option<Position>,
+    /// The number of moves since the last capture or pawn move, used by the fifty-move rule.
+    pub halfmove_clock: u32,
+    /// The number of the current move, starting at 1 and incremented after black moves.
+    pub fullmove_number: u32,
+    /// The files of the castling rooks if the game is played with the Chess960 rules, where
+    /// a king castles by moving onto its own rook, or `None` in standard chess.
+    pub chess960: Option<CastlingFiles>,
+    /// The variant whose rules the game is played with.
+    pub variant: Variant,
+    /// The number of checks given by white and by black, counted at Three-check.
+    pub checks: (u32, u32),
+    /// The pieces held by white and by black at Crazyhouse.
+    pub pockets: (Pocket, Pocket),
+    /// The squares of the pieces that were promoted from pawns at Crazyhouse, which go back
+    /// to being pawns when they are captured, with one bit per square, `y * BOARD_SIZE + x`.
+    pub promoted: u64,
+}
+
+impl State {
+    /// Returns the pocket of a player. The empty color has an empty pocket.
+    pub fn pocket(&self, color: Color) -> Pocket {
+        match color {
+            Color::White => self.pockets.0,
+            Color::Black => self.pockets.1,
+            Color::Empty => Pocket::default(),
+        }
+    }
+
+    /// Returns a mutable reference to the pocket of a player, or `None` for the empty color.
+    pub fn pocket_mut(&mut self, color: Color) -> Option<&mut Pocket> {
+        match color {
+            Color::White => Some(&mut self.pockets.0),
+            Color::Black => Some(&mut self.pockets.1),
+            Color::Empty => None,
+        }
+    }
+
+    /// Returns `true` if the piece on `position` was promoted from a pawn.
+    pub fn is_promoted(&self, position: &Position) -> bool {
+        self.promoted & square_bit(position) != 0
+    }
+
+    /// Marks the piece on `position` as promoted from a pawn, or not.
+    pub fn set_promoted(&mut self, position: &Position, promoted: bool) {
+        if promoted {
+            self.promoted |= square_bit(position);
+        } else {
+            self.promoted &= !square_bit(position);
+        }
+    }
+}
+
+/// Returns the bit of a square in `State::promoted`.
+fn square_bit(position: &Position) -> u64 {
+    1 << (position.y * BOARD_SIZE + position.x)
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            side_to_move: Color::White,
+            castling: CastlingRights::default(),
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            chess960: None,
+            variant: Variant::Standard,
+            checks: (0, 0),
+            pockets: (Pocket::default(), Pocket::default()),
+            promoted: 0,
+        }
+    }
+}
+
+/// Returns `true` if `line` is a header line of a table file, that is, a line of the form
+/// `key: value`.
+pub fn is_header_line(line: &str) -> bool {
+    line.contains(':')
+}
+
+/// Parses the header lines of a table file into a `State`.
+///
+/// Every header line has the form `key: value`, and every key is optional:
+///
+/// * `turn` - `B` if white moves next or `N` if black moves next. Defaults to `B`.
+/// * `castling` - The available castlings, using the letter of the king (`r`) for the king's
+///   side and the letter of the queen (`d`) for the queen's side, lowercase for white and
+///   uppercase for black, or `-` if there are none. Defaults to `-`.
+/// * `en_passant` - The square skipped over by a pawn on the previous move, such as `e3`, or
+///   `-` if there is none. Defaults to `-`.
+/// * `halfmove` - The halfmove clock. Defaults to `0`.
+/// * `fullmove` - The fullmove number. Defaults to `1`.
+/// * `chess960` - The files of the rooks on the queen's and king's sides, such as `bg`, if the
+///   game is played with the Chess960 castling rules. Defaults to standard chess.
+/// * `variant` - The name of the variant the game is played with, such as `atomic`, as listed
+///   in `Variant`. Defaults to `standard`.
+/// * `checks` - The number of checks given by white and by black at Three-check, separated by
+///   a space, such as `2 0`. Defaults to `0 0`.
+/// * `pocket` - The pieces held at Crazyhouse, with the letters of the table files, lowercase
+///   for white and uppercase for black, such as `ddP`, or `-` if there are none. Defaults to
+///   `-`.
+/// * `promoted` - The squares of the pieces promoted from pawns at Crazyhouse, separated by
+///   spaces, such as `e8 b1`, or `-` if there are none. Defaults to `-`.
+/// * `fairy` - The fairy pieces used on the table, each a letter and a Betza notation such as
+///   `z=BN`, separated by spaces. They are only checked here, and kept by the table read
+///   from the file, see `Table::fairy_pieces`.
+///
+/// # Arguments
+///
+/// * `lines` - The header lines of the table file.
+///
+/// # Errors
+///
+/// Returns an error if a line has an unknown key, a key is repeated, or a value is invalid.
+///
+pub fn parse_header(lines: &[String]) -> Result<State, String> {
+    let mut state = State::default();
+    let mut seen: Vec<&str> = Vec::new();
+    for line in lines {
+        let (key, value) = match line.split_once(':') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => return Err(format!("ERROR: Invalid header line: {}", line)),
+        };
+        if seen.contains(&key) {
+            return Err(format!("ERROR: Repeated header: {}", key));
+        }
+        match key {
+            "turn" => {
+                state.side_to_move = match value {
+                    "B" => Color::White,
+                    "N" => Color::Black,
+                    _ => return Err(format!("ERROR: Invalid turn: {}", value)),
+                }
+            }
+            "castling" => state.castling = parse_castling(value)?,
+            "en_passant" => {
+                state.en_passant = match value {
+                    "-" => None,
+                    _ => Some(parse_en_passant(value)?),
+                }
+            }
+            "halfmove" => state.halfmove_clock = parse_counter(key, value)?,
+            "fullmove" => {
+                state.fullmove_number = parse_counter(key, value)?;
+                if state.fullmove_number == 0 {
+                    return Err("ERROR: Invalid fullmove: 0".to_string());
+                }
+            }
+            "chess960" => state.chess960 = Some(parse_castling_files(value)?),
+            "variant" => state.variant = value.parse()?,
+            "checks" => state.checks = parse_checks(value)?,
+            "pocket" => state.pockets = parse_pockets(value)?,
+            "promoted" => {
+                for square in value.split_whitespace().filter(|square| *square != "-") {
+                    state.set_promoted(&square.parse::<Position>()?, true);
+                }
+            }
+            "fairy" => {
+                parse_fairy_pieces(value)?;
+            }
+            _ => return Err(format!("ERROR: Unknown header: {}", key)),
+        }
+        seen.push(key);
+    }
+    Ok(state)
+}
+
+/// Formats a `State` into the header lines of a table file.
+///
+/// Only the values that differ from the defaults described in `parse_header` are written, so
+/// a table without history produces no header lines at all.
+///
+/// # Arguments
+///
+/// * `state` - A reference to the `State` to format.
+///
+pub fn format_header(state: &State) -> Vec<String> {
+    let default = State::default();
+    let mut lines = Vec::new();
+    if state.side_to_move != default.side_to_move {
+        let turn = match state.side_to_move {
+            Color::Black => "N",
+            _ => "B",
+        };
+        lines.push(format!("turn: {}", turn));
+    }
+    if state.castling != default.castling {
+        lines.push(format!("castling: {}", format_castling(&state.castling)));
+    }
+    if let Some(position) = state.en_passant {
+        lines.push(format!("en_passant: {}", position));
+    }
+    if state.halfmove_clock != default.halfmove_clock {
+        lines.push(format!("halfmove: {}", state.halfmove_clock));
+    }
+    if state.fullmove_number != default.fullmove_number {
+        lines.push(format!("fullmove: {}", state.fullmove_number));
+    }
+    if let Some(files) = state.chess960 {
+        lines.push(format!(
+            "chess960: {}{}",
+            file_letter(files.queen_side),
+            file_letter(files.king_side)
+        ));
+    }
+    if state.variant != default.variant {
+        lines.push(format!("variant: {}", state.variant));
+    }
+    if state.checks != default.checks {
+        lines.push(format!("checks: {} {}", state.checks.0, state.checks.1));
+    }
+    if state.pockets != default.pockets {
+        lines.push(format!("pocket: {}", format_pockets(&state.pockets)));
+    }
+    if state.promoted != default.promoted {
+        let squares: Vec<String> = (0..BOARD_SIZE * BOARD_SIZE)
+            .map(|i| Position {
+                x: i % BOARD_SIZE,
+                y: i / BOARD_SIZE,
+            })
+            .filter(|position| state.is_promoted(position))
+            .map(|position| position.to_string())
+            .collect();
+        lines.push(format!("promoted: {}", squares.join(" ")));
+    }
+    lines
+}
+
+/// Parses the value of the `checks` header.
+fn parse_checks(value: &str) -> Result<(u32, u32), String> {
+    match value.split_whitespace().collect::<Vec<&str>>()[..] {
+        [white, black] => Ok((
+            parse_counter("checks", white)?,
+            parse_counter("checks", black)?,
+        )),
+        _ => Err(format!("ERROR: Invalid checks: {}", value)),
+    }
+}
+
+/// Parses the value of the `pocket` header.
+fn parse_pockets(value: &str) -> Result<(Pocket, Pocket), String> {
+    let mut pockets = (Pocket::default(), Pocket::default());
+    if value == "-" {
+        return Ok(pockets);
+    }
+    for c in value.chars() {
+        let piece_type = match c.to_ascii_lowercase() {
+            'd' => PieceType::D,
+            't' => PieceType::T,
+            'a' => PieceType::A,
+            'c' => PieceType::C,
+            'p' => PieceType::P,
+            _ => return Err(format!("ERROR: Invalid pocket: {}", value)),
+        };
+        if c.is_ascii_lowercase() {
+            pockets.0.add(piece_type);
+        } else {
+            pockets.1.add(piece_type);
+        }
+    }
+    Ok(pockets)
+}
+
+/// Formats the pockets of both players as the value of the `pocket` header.
+fn format_pockets(pockets: &(Pocket, Pocket)) -> String {
+    let letters = ['d', 't', 'a', 'c', 'p'];
+    let mut value = String::new();
+    for (pocket, black) in [(&pockets.0, false), (&pockets.1, true)] {
+        for (piece_type, letter) in Pocket::PIECE_TYPES.iter().zip(letters) {
+            for _ in 0..pocket.count(*piece_type) {
+                value.push(if black {
+                    letter.to_ascii_uppercase()
+                } else {
+                    letter
+                });
+            }
+        }
+    }
+    if value.is_empty() {
+        "-".to_string()
+    } else {
+        value
+    }
+}
+
+/// Parses the value of the `castling` header.
+fn parse_castling(value: &str) -> Result<CastlingRights, String> {
+    let mut castling = CastlingRights::default();
+    if value == "-" {
+        return Ok(castling);
+    }
+    for c in value.chars() {
+        let right = match c {
+            'r' => &mut castling.white_king_side,
+            'd' => &mut castling.white_queen_side,
+            'R' => &mut castling.black_king_side,
+            'D' => &mut castling.black_queen_side,
+            _ => return Err(format!("ERROR: Invalid castling: {}", value)),
+        };
+        if *right {
+            return Err(format!("ERROR: Invalid castling: {}", value));
+        }
+        *right = true;
+    }
+    Ok(castling)
+}
+
+/// Formats castling rights as the value of the `castling` header.
+fn format_castling(castling: &CastlingRights) -> String {
+    let rights = [
+        (castling.white_king_side, 'r'),
+        (castling.white_queen_side, 'd'),
+        (castling.black_king_side, 'R'),
+        (castling.black_queen_side, 'D'),
+    ];
+    let value: String = rights
+        .iter()
+        .filter(|(available, _)| *available)
+        .map(|(_, c)| c)
+        .collect();
+    if value.is_empty() {
+        "-".to_string()
+    } else {
+        value
+    }
+}
+
+/// Parses the value of the `chess960` header: the files of the rooks on the queen's and
+/// king's sides, in that order.
+fn parse_castling_files(value: &str) -> Result<CastlingFiles, String> {
+    let files: Vec<usize> = value
+        .chars()
+        .filter_map(|c| {
+            let file = (c as usize).checked_sub('a' as usize)?;
+            (file < BOARD_SIZE).then_some(file)
+        })
+        .collect();
+    match files[..] {
+        [queen_side, king_side] if value.len() == 2 && queen_side + 1 < king_side => {
+            Ok(CastlingFiles {
+                king_side,
+                queen_side,
+            })
+        }
+        _ => Err(format!("ERROR: Invalid chess960 files: {}", value)),
+    }
+}
+
+/// Returns the letter of a file.
+fn file_letter(file: usize) -> char {
+    (b'a' + file as u8) as char
+}
+
+/// Parses the value of the `en_passant` header, which must be on the third or sixth row.
+fn parse_en_passant(value: &str) -> Result<Position, String> {
+    match value.parse::<Position>() {
+        Ok(position) if position.y == 2 || position.y == BOARD_SIZE - 3 => Ok(position),
+        _ => Err(format!("ERROR: Invalid en passant square: {}", value)),
+    }
+}
+
+/// Parses the value of a counter header.
+fn parse_counter(key: &str, value: &str) -> Result<u32, String> {
+    match value.parse::<u32>() {
+        Ok(n) => Ok(n),
+        Err(_) => Err(format!("ERROR: Invalid {}: {}", key, value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_parse_header() {
+        use super::*;
+        let lines: Vec<String> = vec![
+            "turn: N".to_string(),
+            "castling: rD".to_string(),
+            "en_passant: e3".to_string(),
+            "halfmove: 0".to_string(),
+            "fullmove: 12".to_string(),
+        ];
+        let state = parse_header(&lines).unwrap();
+        assert_eq!(
+            state,
+            State {
+                side_to_move: Color::Black,
+                castling: CastlingRights {
+                    white_king_side: true,
+                    white_queen_side: false,
+                    black_king_side: false,
+                    black_queen_side: true,
+                },
+                en_passant: Some(Position { x: 4, y: 5 }),
+                halfmove_clock: 0,
+                fullmove_number: 12,
+                chess960: None,
+                variant: Variant::Standard,
+                checks: (0, 0),
+                pockets: (Pocket::default(), Pocket::default()),
+                promoted: 0,
+            }
+        );
+        assert_eq!(
+            format_header(&state),
+            vec![
+                "turn: N".to_string(),
+                "castling: rD".to_string(),
+                "en_passant: e3".to_string(),
+                "fullmove: 12".to_string(),
+            ]
+        );
+        assert!(format_header(&State::default()).is_empty());
+
+        let state = parse_header(&["chess960: bg".to_string()]).unwrap();
+        assert_eq!(
+            state.chess960,
+            Some(CastlingFiles {
+                king_side: 6,
+                queen_side: 1,
+            })
+        );
+        assert_eq!(format_header(&state), vec!["chess960: bg".to_string()]);
+        assert_eq!(
+            parse_header(&["chess960: gb".to_string()]).err(),
+            Some("ERROR: Invalid chess960 files: gb".to_string())
+        );
+
+        let lines = vec!["variant: threecheck".to_string(), "checks: 2 1".to_string()];
+        let state = parse_header(&lines).unwrap();
+        assert_eq!(state.variant, Variant::ThreeCheck);
+        assert_eq!(state.checks, (2, 1));
+        assert_eq!(format_header(&state), lines);
+        assert_eq!(
+            parse_header(&["checks: 2".to_string()]).err(),
+            Some("ERROR: Invalid checks: 2".to_string())
+        );
+
+        let lines = vec!["pocket: ddpTA".to_string(), "promoted: a8 e1".to_string()];
+        let state = parse_header(&lines).unwrap();
+        assert_eq!(state.pocket(Color::White).count(PieceType::D), 2);
+        assert_eq!(state.pocket(Color::Black).count(PieceType::T), 1);
+        assert!(state.is_promoted(&Position { x: 4, y: 7 }));
+        assert_eq!(format_header(&state), lines);
+        assert_eq!(
+            parse_header(&["pocket: r".to_string()]).err(),
+            Some("ERROR: Invalid pocket: r".to_string())
+        );
+
+        assert_eq!(
+            parse_header(&["turn: X".to_string()]).err(),
+            Some("ERROR: Invalid turn: X".to_string())
+        );
+        assert_eq!(
+            parse_header(&["castling: rr".to_string()]).err(),
+            Some("ERROR: Invalid castling: rr".to_string())
+        );
+        assert_eq!(
+            parse_header(&["en_passant: e4".to_string()]).err(),
+            Some("ERROR: Invalid en passant square: e4".to_string())
+        );
+        assert_eq!(
+            parse_header(&["halfmove: -1".to_string()]).err(),
+            Some("ERROR: Invalid halfmove: -1".to_string())
+        );
+        assert_eq!(
+            parse_header(&["turn: B".to_string(), "turn: N".to_string()]).err(),
+            Some("ERROR: Repeated header: turn".to_string())
+        );
+        assert_eq!(
+            parse_header(&["color: B".to_string()]).err(),
+            Some("ERROR: Unknown header: color".to_string())
+        );
+    }
+}
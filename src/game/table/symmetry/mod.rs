@@ -0,0 +1,381 @@
+use std::collections::HashMap;
+
+use crate::game::table::piece::position::Position;
+use crate::game::table::piece::{Piece, PieceType};
+use crate::game::table::state::{CastlingRights, State};
+use crate::game::table::zobrist::zobrist_hash;
+use crate::game::table::{format_table, Table, BOARD_SIZE};
+
+/// A struct representing a transformation of the board that keeps the result of
+/// `check_moves` consistent.
+///
+/// The transformations are applied in the order in which the fields are declared.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Symmetry {
+    /// Swaps files and ranks, reflecting the board across the a8-h1 diagonal.
+    pub transpose: bool,
+    /// Mirrors the board horizontally, exchanging the a and h files.
+    pub mirror_files: bool,
+    /// Flips the board vertically, exchanging the first and last rows.
+    pub flip_ranks: bool,
+    /// Exchanges the colors of the white and black pieces.
+    pub swap_colors: bool,
+}
+
+impl Symmetry {
+    /// The transformation that leaves the table unchanged.
+    pub const IDENTITY: Symmetry = Symmetry {
+        transpose: false,
+        mirror_files: false,
+        flip_ranks: false,
+        swap_colors: false,
+    };
+
+    /// Returns the 16 transformations made of the symmetries of the square and a color swap.
+    pub fn all() -> Vec<Symmetry> {
+        (0..16)
+            .map(|bits| Symmetry {
+                transpose: bits & 1 != 0,
+                mirror_files: bits & 2 != 0,
+                flip_ranks: bits & 4 != 0,
+                swap_colors: bits & 8 != 0,
+            })
+            .collect()
+    }
+
+    /// Returns `true` if the transformation keeps every pawn moving forward.
+    ///
+    /// Pawns only capture towards the opponent's side, so a table with pawns can only be
+    /// mirrored horizontally, or flipped vertically when the colors are swapped as well.
+    pub fn preserves_pawn_direction(&self) -> bool {
+        !self.transpose && self.flip_ranks == self.swap_colors
+    }
+
+    /// Returns `true` if the transformation keeps kings and rooks on the squares they castle
+    /// from, which only happens when the board is not mirrored nor transposed.
+    pub fn preserves_castling(&self) -> bool {
+        !self.transpose && !self.mirror_files && self.flip_ranks == self.swap_colors
+    }
+
+    /// Maps the result of `check_moves` on a table to the result on the transformed table.
+    ///
+    /// # Arguments
+    ///
+    /// * `result` - The `(white, black)` tuple returned by `check_moves` on the original table.
+    ///
+    /// # Returns
+    ///
+    /// The `(white, black)` tuple that `check_moves` returns on the transformed table.
+    ///
+    pub fn map_result(&self, result: (bool, bool)) -> (bool, bool) {
+        if self.swap_colors {
+            (result.1, result.0)
+        } else {
+            result
+        }
+    }
+
+    /// Applies the transformation to an offset between two squares, given as `(file, row)`
+    /// differences.
+    pub fn apply_offset(&self, (mut dx, mut dy): (isize, isize)) -> (isize, isize) {
+        if self.transpose {
+            (dx, dy) = (dy, dx);
+        }
+        if self.mirror_files {
+            dx = -dx;
+        }
+        if self.flip_ranks {
+            dy = -dy;
+        }
+        (dx, dy)
+    }
+
+    /// Applies the transformation to a position on the board.
+    pub fn apply_position(&self, position: &Position) -> Position {
+        let (mut x, mut y) = (position.x, position.y);
+        if self.transpose {
+            (x, y) = (y, x);
+        }
+        if self.mirror_files {
+            x = BOARD_SIZE - 1 - x;
+        }
+        if self.flip_ranks {
+            y = BOARD_SIZE - 1 - y;
+        }
+        Position { x, y }
+    }
+
+    /// Applies the transformation to a `Table`, returning the transformed copy.
+    ///
+    /// # Arguments
+    ///
+    /// * `table` - A reference to the `Table` to transform.
+    ///
+    pub fn apply(&self, table: &Table) -> Table {
+        let pieces = table
+            .pieces
+            .iter()
+            .map(|piece| Piece {
+                piece_type: piece.piece_type,
+                position: self.apply_position(&piece.position),
+                color: if self.swap_colors {
+                    piece.color.opposite()
+                } else {
+                    piece.color
+                },
+            })
+            .collect();
+        let mut transformed = Table::new(pieces, self.apply_state(&table.state));
+        transformed.fairy = table.fairy.clone();
+        transformed
+    }
+
+    /// Applies the transformation to the `State` of a game.
+    ///
+    /// Swapping colors also swaps the player to move and the castling rights of each player.
+    pub fn apply_state(&self, state: &State) -> State {
+        let mut transformed = *state;
+        transformed.en_passant = state.en_passant.map(|p| self.apply_position(&p));
+        transformed.promoted = 0;
+        for i in 0..BOARD_SIZE * BOARD_SIZE {
+            let position = Position {
+                x: i % BOARD_SIZE,
+                y: i / BOARD_SIZE,
+            };
+            if state.is_promoted(&position) {
+                transformed.set_promoted(&self.apply_position(&position), true);
+            }
+        }
+        if self.swap_colors {
+            transformed.pockets = (state.pockets.1, state.pockets.0);
+            transformed.checks = (state.checks.1, state.checks.0);
+            transformed.side_to_move = state.side_to_move.opposite();
+            transformed.castling = CastlingRights {
+                white_king_side: state.castling.black_king_side,
+                white_queen_side: state.castling.black_queen_side,
+                black_king_side: state.castling.white_king_side,
+                black_queen_side: state.castling.white_queen_side,
+            };
+        }
+        transformed
+    }
+}
+
+/// Returns the transformations that can be applied to a table without changing the rules of
+/// the game, that is, without making a pawn move backwards, moving a piece that can still
+/// castle away from its square, nor changing the movement of a custom piece. Custom pieces
+/// whose movement is not known to be kept by a transformation only allow `IDENTITY`, see
+/// `PieceKind::is_preserved_by`.
+///
+/// # Arguments
+///
+/// * `table` - A reference to the `Table` to inspect.
+///
+pub fn symmetries(table: &Table) -> Vec<Symmetry> {
+    let has_pawns = table
+        .pieces
+        .iter()
+        .any(|piece| piece.piece_type == PieceType::P);
+    let can_castle = table.state.castling != CastlingRights::default();
+    let mut custom: Vec<PieceType> = Vec::new();
+    for piece in &table.pieces {
+        if matches!(piece.piece_type, PieceType::Custom(_)) && !custom.contains(&piece.piece_type) {
+            custom.push(piece.piece_type);
+        }
+    }
+    Symmetry::all()
+        .into_iter()
+        .filter(|symmetry| !has_pawns || symmetry.preserves_pawn_direction())
+        .filter(|symmetry| !can_castle || symmetry.preserves_castling())
+        .filter(|symmetry| {
+            custom.iter().all(|piece_type| {
+                table
+                    .piece_kind(*piece_type)
+                    .is_some_and(|kind| kind.is_preserved_by(symmetry))
+            })
+        })
+        .collect()
+}
+
+/// Computes the canonical form of a `Table`.
+///
+/// Two tables have the same canonical form if and only if one can be transformed into the
+/// other with one of the `symmetries` allowed for it. The canonical form is the transformed
+/// table whose table file is the smallest in lexicographic order.
+///
+/// # Arguments
+///
+/// * `table` - A reference to the `Table` to canonicalize.
+///
+/// # Returns
+///
+/// A tuple with the canonical table and the transformation that maps `table` to it.
+///
+pub fn canonical_form(table: &Table) -> (Table, Symmetry) {
+    let mut best = (table.clone(), Symmetry::IDENTITY);
+    let mut best_lines = format_table(table);
+    for symmetry in symmetries(table) {
+        let transformed = symmetry.apply(table);
+        let lines = format_table(&transformed);
+        if lines < best_lines {
+            best_lines = lines;
+            best = (transformed, symmetry);
+        }
+    }
+    best
+}
+
+/// Groups named tables that are equivalent under the allowed symmetries.
+///
+/// # Arguments
+///
+/// * `tables` - A slice of `(name, table)` pairs.
+///
+/// # Returns
+///
+/// The names of the tables grouped by canonical form. Groups keep the order in which their
+/// first table appears in `tables`, and names inside a group keep their relative order.
+///
+pub fn group_equivalent(tables: &[(String, Table)]) -> Vec<Vec<String>> {
+    let mut groups: Vec<(Table, Vec<String>)> = Vec::new();
+    let mut by_hash: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (name, table) in tables {
+        let (canonical, _) = canonical_form(table);
+        let candidates = by_hash.entry(zobrist_hash(&canonical)).or_default();
+        match candidates.iter().find(|i| groups[**i].0 == canonical) {
+            Some(i) => groups[*i].1.push(name.clone()),
+            None => {
+                candidates.push(groups.len());
+                groups.push((canonical, vec![name.clone()]));
+            }
+        }
+    }
+    groups.into_iter().map(|(_, names)| names).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_symmetries_keep_check_moves() {
+        use super::*;
+        use crate::game::table::check_moves;
+        use crate::game::table::piece::Color;
+        let types = [
+            PieceType::R,
+            PieceType::D,
+            PieceType::A,
+            PieceType::C,
+            PieceType::T,
+            PieceType::P,
+        ];
+        for white_type in types {
+            for black_type in types {
+                for w in 0..BOARD_SIZE * BOARD_SIZE {
+                    for b in 0..BOARD_SIZE * BOARD_SIZE {
+                        if w == b {
+                            continue;
+                        }
+                        let table = Table::new(
+                            vec![
+                                Piece {
+                                    piece_type: white_type,
+                                    position: Position {
+                                        x: w % BOARD_SIZE,
+                                        y: w / BOARD_SIZE,
+                                    },
+                                    color: Color::White,
+                                },
+                                Piece {
+                                    piece_type: black_type,
+                                    position: Position {
+                                        x: b % BOARD_SIZE,
+                                        y: b / BOARD_SIZE,
+                                    },
+                                    color: Color::Black,
+                                },
+                            ],
+                            State::default(),
+                        );
+                        let result = check_moves(&table);
+                        for symmetry in symmetries(&table) {
+                            assert_eq!(
+                                check_moves(&symmetry.apply(&table)),
+                                symmetry.map_result(result),
+                                "{:?} on {:?}",
+                                symmetry,
+                                table
+                            );
+                        }
+                        if (w + b) % 11 == 0 {
+                            let (canonical, symmetry) = canonical_form(&table);
+                            assert_eq!(check_moves(&canonical), symmetry.map_result(result));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_canonical_form() {
+        use super::*;
+        use crate::game::table::{check_moves, parse_table};
+        use crate::utils::read_file;
+        let table = parse_table(&read_file(&"tables/dedup/t_left.txt").unwrap()).unwrap();
+        let mirrored = parse_table(&read_file(&"tables/dedup/t_right.txt").unwrap()).unwrap();
+        let swapped = parse_table(&read_file(&"tables/dedup/t_swapped.txt").unwrap()).unwrap();
+        let pawn = parse_table(&read_file(&"tables/p.txt").unwrap()).unwrap();
+        let en_passant = parse_table(&read_file(&"tables/en_passant.txt").unwrap()).unwrap();
+
+        assert_eq!(canonical_form(&table).0, canonical_form(&mirrored).0);
+        assert_eq!(canonical_form(&table).0, canonical_form(&swapped).0);
+        assert_ne!(canonical_form(&table).0, canonical_form(&pawn).0);
+
+        let flipped = Symmetry {
+            flip_ranks: true,
+            ..Symmetry::IDENTITY
+        };
+        assert!(!symmetries(&pawn).contains(&flipped));
+        assert!(symmetries(&table).contains(&flipped));
+
+        // A fairy piece moving only forward can be mirrored, but not flipped
+        let forward = parse_table(&read_file(&"tables/dedup/f_low.txt").unwrap()).unwrap();
+        let mirrored_forward =
+            parse_table(&read_file(&"tables/dedup/f_mirrored.txt").unwrap()).unwrap();
+        let flipped_forward = parse_table(&read_file(&"tables/dedup/f_high.txt").unwrap()).unwrap();
+        assert!(!symmetries(&forward).contains(&flipped));
+        assert_eq!(
+            canonical_form(&forward).0,
+            canonical_form(&mirrored_forward).0
+        );
+        assert_ne!(
+            canonical_form(&forward).0,
+            canonical_form(&flipped_forward).0
+        );
+        assert_ne!(check_moves(&forward), check_moves(&flipped_forward));
+
+        for symmetry in symmetries(&en_passant) {
+            let transformed = symmetry.apply(&en_passant);
+            assert_eq!(
+                parse_table(&format_table(&transformed)).unwrap(),
+                transformed
+            );
+            assert_eq!(
+                check_moves(&transformed),
+                symmetry.map_result(check_moves(&en_passant))
+            );
+        }
+
+        assert_eq!(
+            group_equivalent(&[
+                ("t_left".to_string(), table),
+                ("p".to_string(), pawn),
+                ("t_right".to_string(), mirrored),
+            ]),
+            vec![
+                vec!["t_left".to_string(), "t_right".to_string()],
+                vec!["p".to_string()]
+            ]
+        );
+    }
+}
@@ -0,0 +1,740 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::game::table::generator::{is_in_check, legal_moves};
+use crate::game::table::moves::{make_move, unmake_move};
+use crate::game::table::notation::{piece_from_letter, piece_letter, Language};
+use crate::game::table::piece::position::Position;
+use crate::game::table::piece::{Color, Piece, PieceType};
+use crate::game::table::state::{CastlingRights, State};
+use crate::game::table::symmetry::Symmetry;
+use crate::game::table::{Table, BOARD_SIZE};
+
+/// The most pieces a tablebase can hold, counting both sides.
+pub const MAX_PIECES: usize = 4;
+
+/// The bytes every tablebase file starts with.
+const MAGIC: &[u8; 6] = b"TP1TB\0";
+
+/// The version of the tablebase file format.
+const VERSION: u8 = 1;
+
+/// The squares the first piece is moved to by a symmetry of the board, a triangle in the
+/// corner of a8 under the diagonal running from a8 to h1.
+const TRIANGLE: [(usize, usize); 10] = [
+    (0, 0),
+    (0, 1),
+    (1, 1),
+    (0, 2),
+    (1, 2),
+    (2, 2),
+    (0, 3),
+    (1, 3),
+    (2, 3),
+    (3, 3),
+];
+
+/// The squares the first piece is moved to in endgames with pawns, which can only be
+/// mirrored: the half of the board on the files a to d.
+const HALF_BOARD: [(usize, usize); BOARD_SIZE * BOARD_SIZE / 2] = {
+    let mut squares = [(0, 0); BOARD_SIZE * BOARD_SIZE / 2];
+    let mut i = 0;
+    while i < squares.len() {
+        squares[i] = (i % (BOARD_SIZE / 2), i / (BOARD_SIZE / 2));
+        i += 1;
+    }
+    squares
+};
+
+/// The stored value of a position whose result is not known yet, or a draw once the
+/// tablebase is generated.
+const DRAW: u8 = 0;
+
+/// The first stored value of a lost position. Values from `1` up to `LOSS` are wins.
+const LOSS: u8 = 128;
+
+/// The stored value of an index that is not a legal position.
+const INVALID: u8 = 255;
+
+/// An enum representing the result of a position with perfect play, for the player to
+/// move.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Outcome {
+    /// The player to move wins, mating or capturing the last piece of the opponent in the
+    /// given number of plies.
+    Win(u32),
+    /// Neither player can force a win.
+    Draw,
+    /// The player to move loses in the given number of plies. `Loss(0)` means the player
+    /// is already mated, or has no pieces left.
+    Loss(u32),
+}
+
+/// A struct representing the pieces of each side of an endgame, such as `KQvK`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Material {
+    /// The white pieces, kings first.
+    pub white: Vec<PieceType>,
+    /// The black pieces, kings first.
+    pub black: Vec<PieceType>,
+}
+
+impl Material {
+    /// Returns the material of the pieces on a table.
+    pub fn of(table: &Table) -> Material {
+        let side = |color: Color| {
+            let mut types: Vec<PieceType> = table
+                .pieces()
+                .iter()
+                .filter(|piece| piece.color == color)
+                .map(|piece| piece.piece_type)
+                .collect();
+            types.sort_by_key(|piece_type| type_order(*piece_type));
+            types
+        };
+        Material {
+            white: side(Color::White),
+            black: side(Color::Black),
+        }
+    }
+
+    /// Checks that a tablebase can be generated for the material.
+    fn check(&self) -> Result<(), String> {
+        let pieces = self.pieces();
+        if self.white.is_empty() || self.black.is_empty() {
+            return Err(format!("ERROR: Invalid material: {}", self));
+        }
+        if pieces.len() > MAX_PIECES {
+            return Err(format!(
+                "ERROR: Tablebases have at most {} pieces",
+                MAX_PIECES
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the pieces of both sides, white first, with their color.
+    fn pieces(&self) -> Vec<(PieceType, Color)> {
+        let white = self.white.iter().map(|t| (*t, Color::White));
+        let black = self.black.iter().map(|t| (*t, Color::Black));
+        white.chain(black).collect()
+    }
+
+    /// Returns `true` if either side has pawns, which the board can only be mirrored with.
+    fn has_pawns(&self) -> bool {
+        self.white.contains(&PieceType::P) || self.black.contains(&PieceType::P)
+    }
+
+    /// Returns the squares the first piece is moved to by a symmetry of the board.
+    fn first_squares(&self) -> &'static [(usize, usize)] {
+        if self.has_pawns() {
+            &HALF_BOARD
+        } else {
+            &TRIANGLE
+        }
+    }
+}
+
+impl FromStr for Material {
+    type Err = String;
+
+    /// Parses material written with the English piece letters, the white pieces first, such
+    /// as `KQvK`, `KBNvK`, `KPvK` or `QvN`.
+    fn from_str(s: &str) -> Result<Material, String> {
+        let invalid = || format!("ERROR: Invalid material: {}", s);
+        let (white, black) = s.split_once('v').ok_or_else(invalid)?;
+        let side = |letters: &str| -> Result<Vec<PieceType>, String> {
+            let mut types = letters
+                .chars()
+                .map(|c| match c {
+                    'P' => Ok(PieceType::P),
+                    c => piece_from_letter(c, Language::English).ok_or_else(invalid),
+                })
+                .collect::<Result<Vec<PieceType>, String>>()?;
+            types.sort_by_key(|piece_type| type_order(*piece_type));
+            Ok(types)
+        };
+        let material = Material {
+            white: side(white)?,
+            black: side(black)?,
+        };
+        material.check()?;
+        Ok(material)
+    }
+}
+
+impl fmt::Display for Material {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let letters = |types: &[PieceType]| -> String {
+            types
+                .iter()
+                .filter_map(|t| match t {
+                    PieceType::P => Some('P'),
+                    _ => piece_letter(*t, Language::English),
+                })
+                .collect()
+        };
+        write!(f, "{}v{}", letters(&self.white), letters(&self.black))
+    }
+}
+
+/// A struct representing the result and distance to mate of every position of an endgame,
+/// for both players to move.
+///
+/// Any pieces can be held, pawns and boards without kings included, up to `MAX_PIECES`
+/// with at least one on each side. The positions are those of standard chess without
+/// castling rights. Positions where a pawn can be taken en passant are not stored, but the
+/// double pushes leading to them are scored with the capture. Tables with castling rights or
+/// an en passant square are not probed: their result is left to a search. Every two-piece
+/// table of `check_moves` has a tablebase.
+///
+/// Positions are stored once for the 8 symmetries of the board, or for the 2 mirror images
+/// of endgames with pawns, with one byte each.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Tablebase {
+    /// The pieces of the endgame.
+    material: Material,
+    /// The value of each position, by index.
+    values: Vec<u8>,
+}
+
+impl Tablebase {
+    /// Generates the tablebase of an endgame with retrograde analysis.
+    ///
+    /// Mates are found first, then the positions one ply further from mate, and so on until
+    /// no more positions are found: the positions left are draws. Captures and promotions
+    /// lead to other endgames, which are generated first. A side whose last piece is
+    /// captured loses, so endgames without kings follow the rules of the two-piece game.
+    ///
+    /// # Arguments
+    ///
+    /// * `material` - A reference to the `Material` of the endgame.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the endgame has a side without pieces, or more than `MAX_PIECES`
+    /// pieces.
+    ///
+    pub fn generate(material: &Material) -> Result<Tablebase, String> {
+        material.check()?;
+        Ok(generate_with(material, &mut HashMap::new()))
+    }
+
+    /// Returns the material of the tablebase.
+    pub fn material(&self) -> &Material {
+        &self.material
+    }
+
+    /// Looks up the result of a table.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(Outcome)` - The result of the table with perfect play.
+    ///
+    /// * `None` - If the table does not have the material of the tablebase, has castling
+    ///   rights or an en passant square, or is not a legal position.
+    ///
+    pub fn probe(&self, table: &Table) -> Option<Outcome> {
+        if Material::of(table) != self.material
+            || table.state().castling != CastlingRights::default()
+            || table.state().en_passant.is_some()
+        {
+            return None;
+        }
+        let index = index_of(&self.material, table)?;
+        decode(self.values[index])
+    }
+
+    /// Returns the longest win of the tablebase, in plies.
+    pub fn longest_win(&self) -> u32 {
+        self.values
+            .iter()
+            .filter_map(|value| match decode(*value) {
+                Some(Outcome::Win(plies)) => Some(plies),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Writes the tablebase to a file.
+    ///
+    /// The file holds `MAGIC`, the version of the format, the length and letters of the
+    /// material, and then one byte per position.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    ///
+    pub fn write(&self, path: &impl AsRef<Path>) -> Result<(), String> {
+        let name = self.material.to_string();
+        let mut bytes = Vec::with_capacity(MAGIC.len() + 2 + name.len() + self.values.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.push(name.len() as u8);
+        bytes.extend_from_slice(name.as_bytes());
+        bytes.extend_from_slice(&self.values);
+        fs::write(path, bytes).map_err(|e| format!("ERROR: {}", e))
+    }
+
+    /// Reads a tablebase written with `write`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a tablebase file.
+    ///
+    pub fn read(path: &impl AsRef<Path>) -> Result<Tablebase, String> {
+        let bytes = fs::read(path).map_err(|e| format!("ERROR: {}", e))?;
+        let invalid = || "ERROR: Invalid tablebase file".to_string();
+        let header = MAGIC.len() + 2;
+        if bytes.len() < header || &bytes[..MAGIC.len()] != MAGIC || bytes[MAGIC.len()] != VERSION {
+            return Err(invalid());
+        }
+        let name_end = header + bytes[header - 1] as usize;
+        let name = bytes
+            .get(header..name_end)
+            .and_then(|name| std::str::from_utf8(name).ok())
+            .ok_or_else(invalid)?;
+        let material: Material = name.parse()?;
+        let values = bytes[name_end..].to_vec();
+        if values.len() != index_count(&material) {
+            return Err(invalid());
+        }
+        Ok(Tablebase { material, values })
+    }
+}
+
+/// Generates the tablebase of an endgame, reusing the tablebases of smaller endgames in
+/// `generated`, and adding the ones it generates.
+fn generate_with(material: &Material, generated: &mut HashMap<String, Tablebase>) -> Tablebase {
+    let count = index_count(material);
+    let mut graph = Graph {
+        values: vec![DRAW; count],
+        children: vec![Vec::new(); count],
+        captures: vec![Vec::new(); count],
+        en_passant: HashMap::new(),
+        pending: Vec::new(),
+    };
+
+    for index in 0..count {
+        let mut table = match table_at(material, index) {
+            Some(table) => table,
+            None => {
+                graph.values[index] = INVALID;
+                continue;
+            }
+        };
+        let moves = legal_moves(&table);
+        if moves.is_empty() {
+            let mated = is_in_check(&table, table.state().side_to_move);
+            graph.values[index] = if mated { LOSS } else { DRAW };
+            continue;
+        }
+        graph.add_moves(material, index, &mut table, generated);
+    }
+    while let Some((node, mut table)) = graph.pending.pop() {
+        graph.add_moves(material, node, &mut table, generated);
+    }
+    let Graph {
+        mut values,
+        children,
+        captures,
+        ..
+    } = graph;
+
+    // Positions are resolved one ply further from mate at a time. Captures can lead to long
+    // wins of smaller endgames, so the search goes on until no value is left to reach.
+    let longest_capture = captures
+        .iter()
+        .flatten()
+        .filter_map(|value| decode(*value))
+        .map(|outcome| match outcome {
+            Outcome::Win(plies) | Outcome::Loss(plies) => plies,
+            Outcome::Draw => 0,
+        })
+        .max()
+        .unwrap_or(0);
+    let mut plies = 1;
+    loop {
+        let mut changed = false;
+        let mut resolved = Vec::new();
+        for index in 0..values.len() {
+            if values[index] != DRAW || (children[index].is_empty() && captures[index].is_empty()) {
+                continue;
+            }
+            let outcomes = children[index]
+                .iter()
+                .map(|child| decode(values[*child]))
+                .chain(captures[index].iter().map(|value| decode(*value)));
+            let mut wins = true;
+            let mut longest = 0;
+            let mut mates = false;
+            for outcome in outcomes {
+                match outcome {
+                    Some(Outcome::Loss(d)) if d + 1 == plies => mates = true,
+                    Some(Outcome::Win(d)) => longest = longest.max(d),
+                    _ => wins = false,
+                }
+            }
+            if mates {
+                resolved.push((index, encode(Outcome::Win(plies))));
+            } else if wins && longest + 1 == plies {
+                resolved.push((index, encode(Outcome::Loss(plies))));
+            }
+        }
+        for (index, value) in resolved {
+            values[index] = value;
+            changed = true;
+        }
+        if !changed && plies > longest_capture + 1 {
+            break;
+        }
+        plies += 1;
+    }
+
+    values.truncate(count);
+    let tablebase = Tablebase {
+        material: material.clone(),
+        values,
+    };
+    generated.insert(material.to_string(), tablebase.clone());
+    tablebase
+}
+
+/// A struct representing the positions of an endgame being generated and the moves between
+/// them.
+///
+/// Positions are the indexes of the tablebase, followed by the positions where a pawn can
+/// be taken en passant, which are not stored but are needed to score the double pushes
+/// leading to them.
+struct Graph {
+    /// The value of each position, by index.
+    values: Vec<u8>,
+    /// For each position, the positions of the same endgame reached by its legal moves.
+    children: Vec<Vec<usize>>,
+    /// For each position, the values of its captures and promotions, known from the other
+    /// endgames.
+    captures: Vec<Vec<u8>>,
+    /// The positions where a pawn can be taken en passant, by the index of the same position
+    /// without it and the en passant square moved by the same symmetry.
+    en_passant: HashMap<(usize, usize), usize>,
+    /// The positions where a pawn can be taken en passant whose moves are not added yet.
+    pending: Vec<(usize, Table)>,
+}
+
+impl Graph {
+    /// Adds the legal moves of the table of a position, which has at least one.
+    fn add_moves(
+        &mut self,
+        material: &Material,
+        node: usize,
+        table: &mut Table,
+        generated: &mut HashMap<String, Tablebase>,
+    ) {
+        for mv in legal_moves(table) {
+            if make_move(table, &mv).is_err() {
+                continue;
+            }
+            let reached = Material::of(table);
+            if reached == *material {
+                if let Some(child) = self.node_of(material, table) {
+                    self.children[node].push(child);
+                }
+            } else {
+                let value = capture_value(&reached, table, generated);
+                self.captures[node].push(value);
+            }
+            unmake_move(table);
+        }
+    }
+
+    /// Returns the position of a table of the endgame, adding it if it is a position where a
+    /// pawn can be taken en passant that was not reached before.
+    fn node_of(&mut self, material: &Material, table: &Table) -> Option<usize> {
+        let (index, symmetry) = index_and_symmetry(material, table)?;
+        let Some(square) = table.state().en_passant else {
+            return Some(index);
+        };
+        let captured = legal_moves(table).iter().any(|mv| {
+            mv.to == square
+                && table
+                    .piece_at(&mv.from)
+                    .is_some_and(|piece| piece.piece_type == PieceType::P)
+        });
+        if !captured {
+            return Some(index);
+        }
+        let square = symmetry.apply_position(&square);
+        let key = (index, square.y * BOARD_SIZE + square.x);
+        if let Some(node) = self.en_passant.get(&key) {
+            return Some(*node);
+        }
+        let node = self.values.len();
+        self.values.push(DRAW);
+        self.children.push(Vec::new());
+        self.captures.push(Vec::new());
+        self.en_passant.insert(key, node);
+        self.pending.push((node, table.clone()));
+        Some(node)
+    }
+}
+
+/// Returns the stored value of the table reached by a capture or a promotion, generating
+/// the tablebase of its material if needed.
+fn capture_value(
+    material: &Material,
+    table: &Table,
+    generated: &mut HashMap<String, Tablebase>,
+) -> u8 {
+    if material.white.is_empty() || material.black.is_empty() {
+        return LOSS;
+    }
+    let key = material.to_string();
+    if !generated.contains_key(&key) {
+        generate_with(material, generated);
+    }
+    let tablebase = &generated[&key];
+    index_of(material, table).map_or(DRAW, |index| tablebase.values[index])
+}
+
+/// Returns the number of indexes of the positions of an endgame: the player to move, the
+/// first piece on one of the squares of `Material::first_squares`, and the other pieces on
+/// any square.
+fn index_count(material: &Material) -> usize {
+    let others = material.white.len() + material.black.len() - 1;
+    2 * material.first_squares().len() * (BOARD_SIZE * BOARD_SIZE).pow(others as u32)
+}
+
+/// Returns the table of the position at an index, or `None` if it is not a legal
+/// position: two pieces on the same square, a pawn on the first or last row, or the player
+/// who just moved in check.
+fn table_at(material: &Material, index: usize) -> Option<Table> {
+    let squares = BOARD_SIZE * BOARD_SIZE;
+    let pieces = material.pieces();
+    let mut rest = index;
+    let mut positions = Vec::with_capacity(pieces.len());
+    for _ in 1..pieces.len() {
+        let square = rest % squares;
+        positions.push(Position {
+            x: square % BOARD_SIZE,
+            y: square / BOARD_SIZE,
+        });
+        rest /= squares;
+    }
+    let first_squares = material.first_squares();
+    let (x, y) = first_squares[rest % first_squares.len()];
+    positions.insert(0, Position { x, y });
+    let side_to_move = if rest / first_squares.len() == 0 {
+        Color::White
+    } else {
+        Color::Black
+    };
+
+    for (i, position) in positions.iter().enumerate() {
+        if positions[..i].contains(position) {
+            return None;
+        }
+        if pieces[i].0 == PieceType::P && (position.y == 0 || position.y == BOARD_SIZE - 1) {
+            return None;
+        }
+    }
+    let pieces = pieces
+        .iter()
+        .zip(positions)
+        .map(|((piece_type, color), position)| Piece {
+            piece_type: *piece_type,
+            position,
+            color: *color,
+        })
+        .collect();
+    let table = Table::new(
+        pieces,
+        State {
+            side_to_move,
+            ..State::default()
+        },
+    );
+    if is_in_check(&table, side_to_move.opposite()) {
+        return None;
+    }
+    Some(table)
+}
+
+/// Returns the index of a table with the given material, after moving its first piece to
+/// one of the squares of `Material::first_squares` with a symmetry of the board.
+fn index_of(material: &Material, table: &Table) -> Option<usize> {
+    index_and_symmetry(material, table).map(|(index, _)| index)
+}
+
+/// Returns the index of a table like `index_of`, with the symmetry applied to it.
+fn index_and_symmetry(material: &Material, table: &Table) -> Option<(usize, Symmetry)> {
+    let pieces = material.pieces();
+    let mut slots: Vec<Option<Position>> = vec![None; pieces.len()];
+    for piece in table.pieces() {
+        let slot = slots
+            .iter()
+            .zip(&pieces)
+            .position(|(slot, (piece_type, color))| {
+                slot.is_none() && *piece_type == piece.piece_type && *color == piece.color
+            })?;
+        slots[slot] = Some(piece.position);
+    }
+    let positions: Vec<Position> = slots.into_iter().collect::<Option<Vec<Position>>>()?;
+
+    let first_squares = material.first_squares();
+    let symmetry = Symmetry::all().into_iter().find(|symmetry| {
+        let first = symmetry.apply_position(&positions[0]);
+        !symmetry.swap_colors
+            && (symmetry.preserves_pawn_direction() || !material.has_pawns())
+            && first_squares.contains(&(first.x, first.y))
+    })?;
+    let positions: Vec<Position> = positions
+        .iter()
+        .map(|position| symmetry.apply_position(position))
+        .collect();
+
+    let squares = BOARD_SIZE * BOARD_SIZE;
+    let mut index = match table.state().side_to_move {
+        Color::Black => 1,
+        _ => 0,
+    };
+    index = index * first_squares.len()
+        + first_squares
+            .iter()
+            .position(|square| *square == (positions[0].x, positions[0].y))?;
+    for position in positions[1..].iter().rev() {
+        index = index * squares + position.y * BOARD_SIZE + position.x;
+    }
+    Some((index, symmetry))
+}
+
+/// Returns the order of piece types in a material: king, queen, rook, bishop, knight and
+/// registered kinds.
+fn type_order(piece_type: PieceType) -> usize {
+    match piece_type {
+        PieceType::R => 0,
+        PieceType::D => 1,
+        PieceType::T => 2,
+        PieceType::A => 3,
+        PieceType::C => 4,
+        PieceType::P => 5,
+        PieceType::Custom(_) => 6,
+        PieceType::Empty => 7,
+    }
+}
+
+/// Encodes an outcome into a stored value.
+fn encode(outcome: Outcome) -> u8 {
+    match outcome {
+        Outcome::Win(plies) => plies.min(LOSS as u32 - 1) as u8,
+        Outcome::Draw => DRAW,
+        Outcome::Loss(plies) => LOSS + plies.min((INVALID - LOSS - 1) as u32) as u8,
+    }
+}
+
+/// Decodes a stored value into an outcome, or `None` for invalid positions.
+fn decode(value: u8) -> Option<Outcome> {
+    match value {
+        DRAW => Some(Outcome::Draw),
+        INVALID => None,
+        value if value < LOSS => Some(Outcome::Win(value as u32)),
+        value => Some(Outcome::Loss((value - LOSS) as u32)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_material() {
+        use super::*;
+        let material: Material = "KQvK".parse().unwrap();
+        assert_eq!(material.white, vec![PieceType::R, PieceType::D]);
+        assert_eq!(material.black, vec![PieceType::R]);
+        assert_eq!("NBKvK".parse::<Material>().unwrap().to_string(), "KBNvK");
+        assert_eq!(
+            "KQK".parse::<Material>().err(),
+            Some("ERROR: Invalid material: KQK".to_string())
+        );
+        assert_eq!("PKvK".parse::<Material>().unwrap().to_string(), "KPvK");
+        assert_eq!(
+            "KQRvKR".parse::<Material>().err(),
+            Some("ERROR: Tablebases have at most 4 pieces".to_string())
+        );
+    }
+
+    #[test]
+    fn test_tablebase() {
+        use super::*;
+        use crate::game::table::fen::parse_fen;
+        use crate::game::table::parse_table;
+        use crate::utils::read_file;
+
+        let tablebase = Tablebase::generate(&"KQvK".parse().unwrap()).unwrap();
+        // The longest win with a queen takes 10 moves
+        assert_eq!(tablebase.longest_win(), 19);
+        let probe = |fen: &str| tablebase.probe(&parse_fen(fen).unwrap());
+        assert_eq!(probe("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1"), Some(Outcome::Draw));
+        assert_eq!(
+            probe("7k/8/6K1/8/8/8/8/5Q2 w - - 0 1"),
+            Some(Outcome::Win(1))
+        );
+        assert_eq!(
+            probe("7k/4Q3/6K1/8/8/8/8/8 b - - 0 1"),
+            Some(Outcome::Loss(2))
+        );
+        // The king takes the undefended queen
+        assert_eq!(probe("7k/6Q1/8/8/8/8/8/K7 b - - 0 1"), Some(Outcome::Draw));
+        assert_eq!(probe("8/8/8/8/8/8/8/K6k w - - 0 1"), None);
+
+        // The written file is read back, and only holds one byte per position
+        let path = std::env::temp_dir().join("tp1_rust_kqvk.tb");
+        tablebase.write(&path).unwrap();
+        assert_eq!(
+            std::fs::metadata(&path).unwrap().len() as usize,
+            8 + 4 + 2 * 10 * 64 * 64
+        );
+        assert_eq!(Tablebase::read(&path).unwrap(), tablebase);
+        std::fs::remove_file(&path).unwrap();
+        assert!(Tablebase::read(&"tables/d.txt").is_err());
+
+        // Two-piece tables: the queen takes the rook first
+        let table = parse_table(&read_file(&"tables/d.txt").unwrap()).unwrap();
+        let tablebase = Tablebase::generate(&Material::of(&table)).unwrap();
+        assert_eq!(tablebase.material().to_string(), "QvR");
+        assert_eq!(tablebase.probe(&table), Some(Outcome::Win(1)));
+
+        // Pawns are only mirrored, and their promotions lead to other endgames: the rook
+        // waits on the file of the pawn to take the piece it is promoted to
+        let tablebase = Tablebase::generate(&"PvR".parse().unwrap()).unwrap();
+        assert_eq!(tablebase.values.len(), 2 * 32 * 64);
+        let probe = |fen: &str| tablebase.probe(&parse_fen(fen).unwrap());
+        assert_eq!(
+            probe("8/8/8/4r3/3P4/8/8/8 w - - 0 1"),
+            Some(Outcome::Win(1))
+        );
+        assert_eq!(probe("8/1P6/8/8/8/8/8/7r b - - 0 1"), Some(Outcome::Win(3)));
+        assert_eq!(probe("8/6P1/8/8/8/8/8/r7 b - - 0 1"), Some(Outcome::Win(3)));
+        assert_eq!(
+            probe("1r6/8/8/8/8/8/1P6/8 w - - 0 1"),
+            Some(Outcome::Loss(2))
+        );
+
+        // Positions where a pawn can be taken en passant are not stored
+        let tablebase = Tablebase::generate(&"PvP".parse().unwrap()).unwrap();
+        let probe = |fen: &str| tablebase.probe(&parse_fen(fen).unwrap());
+        assert_eq!(probe("8/8/8/3p4/3P4/8/8/8 w - - 0 1"), Some(Outcome::Draw));
+        assert!(probe("8/8/8/8/1Pp5/8/8/8 b - - 0 1").is_some());
+        assert_eq!(probe("8/8/8/8/1Pp5/8/8/8 b - b3 0 1"), None);
+
+        // Double pushes are scored with the en passant capture they allow: black takes the
+        // pawn on b3 whichever way it moves, and the pawn pushed to b5 is taken on b6
+        assert_eq!(
+            probe("8/8/8/8/p7/8/1P6/8 w - - 0 1"),
+            Some(Outcome::Loss(2))
+        );
+        assert_eq!(probe("8/1p6/8/8/P7/8/8/8 w - - 0 1"), Some(Outcome::Win(3)));
+    }
+}
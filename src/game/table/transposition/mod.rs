@@ -0,0 +1,98 @@
+/// A struct representing a fixed-size table of values keyed by the Zobrist hash of tables.
+///
+/// Each hash has a single slot, chosen by its lowest bits. Inserting a value replaces the
+/// value stored in its slot, even if it belongs to another hash, so the table never grows
+/// and old values are forgotten as new ones are inserted.
+#[derive(Clone, Debug)]
+pub struct TranspositionTable<T> {
+    /// The slots of the table, holding the full hash of their table with its value.
+    entries: Vec<Option<(u64, T)>>,
+    /// The number of slots holding a value.
+    len: usize,
+}
+
+impl<T> TranspositionTable<T> {
+    /// Creates an empty table with at least `capacity` slots.
+    ///
+    /// The number of slots is rounded up to a power of two, so that the slot of a hash is
+    /// found with a mask.
+    pub fn new(capacity: usize) -> TranspositionTable<T> {
+        let capacity = capacity.max(1).next_power_of_two();
+        TranspositionTable {
+            entries: (0..capacity).map(|_| None).collect(),
+            len: 0,
+        }
+    }
+
+    /// Returns the number of slots of the table.
+    pub fn capacity(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns the number of values stored in the table.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the table holds no values.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the value stored for a hash, if it was not replaced since.
+    pub fn get(&self, hash: u64) -> Option<&T> {
+        match &self.entries[self.slot(hash)] {
+            Some((stored, value)) if *stored == hash => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Stores a value for a hash, replacing the value stored in its slot, if any.
+    pub fn insert(&mut self, hash: u64, value: T) {
+        let slot = self.slot(hash);
+        if self.entries[slot].is_none() {
+            self.len += 1;
+        }
+        self.entries[slot] = Some((hash, value));
+    }
+
+    /// Removes every value from the table, keeping its capacity.
+    pub fn clear(&mut self) {
+        self.entries.iter_mut().for_each(|entry| *entry = None);
+        self.len = 0;
+    }
+
+    /// Returns the slot of a hash.
+    fn slot(&self, hash: u64) -> usize {
+        (hash as usize) & (self.entries.len() - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_transposition_table() {
+        use super::*;
+        let mut table = TranspositionTable::new(3);
+        assert_eq!(table.capacity(), 4);
+        assert!(table.is_empty());
+
+        table.insert(1, "a");
+        table.insert(2, "b");
+        assert_eq!(table.get(1), Some(&"a"));
+        assert_eq!(table.get(2), Some(&"b"));
+        assert_eq!(table.get(3), None);
+        assert_eq!(table.len(), 2);
+
+        // 5 shares the slot of 1, and replaces it
+        table.insert(5, "c");
+        assert_eq!(table.get(5), Some(&"c"));
+        assert_eq!(table.get(1), None);
+        assert_eq!(table.len(), 2);
+
+        table.clear();
+        assert_eq!(table.get(2), None);
+        assert!(table.is_empty());
+        assert_eq!(table.capacity(), 4);
+    }
+}
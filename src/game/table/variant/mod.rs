@@ -0,0 +1,347 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::game::table::generator::pseudo_legal_moves;
+use crate::game::table::piece::position::Position;
+use crate::game::table::piece::{Color, Piece, PieceType};
+use crate::game::table::Table;
+
+/// The number of checks a player must give to win at Three-check.
+pub const CHECK_LIMIT: u32 = 3;
+
+/// The central squares a king must reach to win at King of the Hill: d5, e5, d4 and e4.
+pub const HILL: [Position; 4] = [
+    Position { x: 3, y: 3 },
+    Position { x: 4, y: 3 },
+    Position { x: 3, y: 4 },
+    Position { x: 4, y: 4 },
+];
+
+/// An enum representing the set of rules a game is played with.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub enum Variant {
+    /// Standard chess.
+    #[default]
+    Standard,
+    /// A player also wins by bringing their king to one of the `HILL` squares.
+    KingOfTheHill,
+    /// A player also wins by giving check `CHECK_LIMIT` times.
+    ThreeCheck,
+    /// Every capture explodes the capturing piece and the pieces around the square of the
+    /// capture but pawns, and a player wins by exploding the king of the other.
+    Atomic,
+    /// Capturing is compulsory, kings are ordinary pieces, and a player wins by losing all
+    /// their pieces or having no moves left.
+    Antichess,
+    /// Captured pieces go to the pocket of the capturing player, who can drop them back onto
+    /// any empty square instead of moving.
+    Crazyhouse,
+}
+
+impl Variant {
+    /// Every variant, in the order they are listed to the user.
+    pub const ALL: [Variant; 6] = [
+        Variant::Standard,
+        Variant::KingOfTheHill,
+        Variant::ThreeCheck,
+        Variant::Atomic,
+        Variant::Antichess,
+        Variant::Crazyhouse,
+    ];
+
+    /// Returns the piece types a pawn can be promoted to, which include the king at
+    /// Antichess.
+    pub fn promotions(&self) -> &'static [PieceType] {
+        match self {
+            Variant::Antichess => &[
+                PieceType::D,
+                PieceType::T,
+                PieceType::A,
+                PieceType::C,
+                PieceType::R,
+            ],
+            _ => &PieceType::PROMOTIONS,
+        }
+    }
+
+    /// Returns `true` if kings are royal, that is, if they can be in check and the moves
+    /// that leave them attacked are illegal.
+    pub fn has_royal_king(&self) -> bool {
+        *self != Variant::Antichess
+    }
+
+    /// Returns the name of the variant in the `Variant` tag of Portable Game Notation, such
+    /// as `King of the Hill`.
+    pub fn pgn_name(&self) -> &'static str {
+        match self {
+            Variant::Standard => "Standard",
+            Variant::KingOfTheHill => "King of the Hill",
+            Variant::ThreeCheck => "Three-check",
+            Variant::Atomic => "Atomic",
+            Variant::Antichess => "Antichess",
+            Variant::Crazyhouse => "Crazyhouse",
+        }
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Variant::Standard => "standard",
+            Variant::KingOfTheHill => "kingofthehill",
+            Variant::ThreeCheck => "threecheck",
+            Variant::Atomic => "atomic",
+            Variant::Antichess => "antichess",
+            Variant::Crazyhouse => "crazyhouse",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Variant {
+    type Err = String;
+
+    /// Parses the name of a variant, as written by `Display`, or one of the usual
+    /// abbreviations `koth` and `3check`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "koth" => Ok(Variant::KingOfTheHill),
+            "3check" => Ok(Variant::ThreeCheck),
+            _ => Variant::ALL
+                .into_iter()
+                .find(|variant| variant.to_string() == s)
+                .ok_or(format!("ERROR: Unknown variant: {}", s)),
+        }
+    }
+}
+
+/// Returns the winner of a game decided by the own rules of its variant, rather than by a
+/// checkmate.
+///
+/// # Arguments
+///
+/// * `table` - A reference to the `Table` of the game.
+///
+/// # Returns
+///
+/// * `Some(Color)` - The color of the winner if the game is over: a king stands on the hill
+///   at King of the Hill, a player gave `CHECK_LIMIT` checks at Three-check, a king exploded
+///   at Atomic, or the player to move has no pieces or no moves left at Antichess.
+///
+/// * `None` - If the game goes on, and always in standard chess and Crazyhouse.
+///
+pub fn variant_winner(table: &Table) -> Option<Color> {
+    let state = table.state();
+    let kings = || {
+        table
+            .pieces()
+            .iter()
+            .filter(|piece| piece.piece_type == PieceType::R)
+    };
+    match state.variant {
+        Variant::Standard | Variant::Crazyhouse => None,
+        Variant::KingOfTheHill => kings()
+            .find(|king| HILL.contains(&king.position))
+            .map(|king| king.color),
+        Variant::ThreeCheck => {
+            let (white, black) = state.checks;
+            if white >= CHECK_LIMIT {
+                Some(Color::White)
+            } else if black >= CHECK_LIMIT {
+                Some(Color::Black)
+            } else {
+                None
+            }
+        }
+        Variant::Atomic => [Color::White, Color::Black]
+            .into_iter()
+            .find(|color| !kings().any(|king| king.color == *color))
+            .map(|color| color.opposite()),
+        Variant::Antichess => pseudo_legal_moves(table)
+            .is_empty()
+            .then_some(state.side_to_move),
+    }
+}
+
+/// Returns the pieces exploded by an Atomic capture on a square, besides the capturing and
+/// the captured pieces: every piece on the squares around it but pawns.
+///
+/// # Arguments
+///
+/// * `table` - A reference to the `Table` before the capture.
+/// * `square` - The square the capturing piece lands on.
+/// * `capturer` - The square the capturing piece comes from, which is left out.
+///
+pub(crate) fn explosion(table: &Table, square: &Position, capturer: &Position) -> Vec<Piece> {
+    table
+        .pieces()
+        .iter()
+        .filter(|piece| {
+            piece.position != *square
+                && piece.position != *capturer
+                && piece.position.x.abs_diff(square.x) <= 1
+                && piece.position.y.abs_diff(square.y) <= 1
+                && piece.piece_type != PieceType::P
+        })
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::table::fen::parse_fen;
+    use crate::game::table::generator::{is_checkmate, is_in_check, is_stalemate, legal_moves};
+    use crate::game::table::moves::{make_move, unmake_move, Move};
+    use crate::game::table::notation::parse_uci;
+    use crate::game::table::parse_position;
+    use crate::game::table::perft::perft;
+    use crate::utils::read_file;
+
+    fn setup_variant_test(file_path: &str) -> Table {
+        parse_position(&read_file(&file_path).unwrap()).unwrap()
+    }
+
+    fn play(table: &mut Table, moves: &[&str]) {
+        for mv in moves {
+            make_move(table, &parse_uci(mv).unwrap()).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_variant_names() {
+        for variant in Variant::ALL {
+            assert_eq!(variant.to_string().parse::<Variant>(), Ok(variant));
+        }
+        assert_eq!("koth".parse::<Variant>(), Ok(Variant::KingOfTheHill));
+        assert_eq!(
+            "horde".parse::<Variant>(),
+            Err("ERROR: Unknown variant: horde".to_string())
+        );
+    }
+
+    #[test]
+    fn test_king_of_the_hill() {
+        // The white king steps onto e4 and wins, and the game stops there
+        let mut table = setup_variant_test("tables/variant_koth.txt");
+        assert_eq!(table.state().variant, Variant::KingOfTheHill);
+        assert_eq!(variant_winner(&table), None);
+        play(&mut table, &["e3e4"]);
+        assert_eq!(variant_winner(&table), Some(Color::White));
+        assert!(legal_moves(&table).is_empty());
+        assert!(!is_stalemate(&table));
+        unmake_move(&mut table);
+        assert_eq!(variant_winner(&table), None);
+    }
+
+    #[test]
+    fn test_three_check() {
+        // The queen gives a third check from h5 and wins
+        let mut table = setup_variant_test("tables/variant_three_check.txt");
+        assert_eq!(table.state().checks, (2, 0));
+        play(&mut table, &["d1h5"]);
+        assert_eq!(table.state().checks, (3, 0));
+        assert_eq!(variant_winner(&table), Some(Color::White));
+        unmake_move(&mut table);
+        assert_eq!(table.state().checks, (2, 0));
+        play(&mut table, &["d1e2"]);
+        assert_eq!(variant_winner(&table), None);
+    }
+
+    #[test]
+    fn test_atomic() {
+        // The knight takes on f7 and explodes along with the king on e8, but not the pawns
+        let mut table = setup_variant_test("tables/variant_atomic.txt");
+        let before = table.clone();
+        play(&mut table, &["g5f7"]);
+        assert_eq!(variant_winner(&table), Some(Color::White));
+        assert_eq!(table.pieces().len(), before.pieces().len() - 4);
+        assert!(table.piece_at(&"g7".parse().unwrap()).is_some());
+        unmake_move(&mut table);
+        assert_eq!(table, before);
+
+        // Kings never capture, and kings standing side by side give no check
+        let mut table = parse_fen("8/8/8/8/8/8/2kq4/3K4 w - - 0 1").unwrap();
+        table.set_variant(Variant::Atomic);
+        assert!(!is_in_check(&table, Color::White));
+        assert!(!is_checkmate(&table));
+        let moves = legal_moves(&table);
+        assert!(!moves.contains(&parse_uci("d1d2").unwrap()));
+        assert_eq!(moves, vec![parse_uci("d1c1").unwrap()]);
+
+        let mut table = setup_variant_test("tables/start.txt");
+        table.set_variant(Variant::Atomic);
+        assert_eq!(perft(&mut table, 3), 8902);
+    }
+
+    #[test]
+    fn test_antichess() {
+        // The capture is compulsory, and the king is an ordinary piece
+        let mut table = setup_variant_test("tables/variant_antichess.txt");
+        let moves = legal_moves(&table);
+        assert_eq!(moves, vec![parse_uci("e4d5").unwrap()]);
+        play(&mut table, &["e4d5", "c6d5"]);
+        assert!(legal_moves(&table).contains(&parse_uci("e1e2").unwrap()));
+
+        // A pawn can be promoted to a king, and the player left without pieces wins
+        let mut table = parse_fen("8/8/8/8/8/8/1p6/8 b - - 0 1").unwrap();
+        table.set_variant(Variant::Antichess);
+        assert_eq!(legal_moves(&table).len(), 5);
+        assert!(legal_moves(&table).contains(&parse_uci("b2b1k").unwrap()));
+        let mut table = parse_fen("8/8/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+        table.set_variant(Variant::Antichess);
+        assert_eq!(variant_winner(&table), Some(Color::Black));
+
+        let mut table = setup_variant_test("tables/start.txt");
+        table.set_variant(Variant::Antichess);
+        assert_eq!(perft(&mut table, 3), 8067);
+    }
+
+    #[test]
+    fn test_crazyhouse() {
+        use crate::game::table::fen::format_fen;
+        use crate::game::table::notation::{format_san, parse_san, Language};
+        use crate::game::table::state::Pocket;
+
+        // White can drop the knight on any of the 60 empty squares
+        let mut table = setup_variant_test("tables/variant_crazyhouse.txt");
+        assert_eq!(format_fen(&table), "4k3/8/8/3r~4/8/5B2/8/4K3[Np] w - - 0 1");
+        assert_eq!(parse_fen(&format_fen(&table)).unwrap(), table);
+        let drops: Vec<Move> = legal_moves(&table)
+            .into_iter()
+            .filter(|mv| mv.drop.is_some())
+            .collect();
+        assert_eq!(drops.len(), 60);
+        let drop = parse_san(&table, "N@f6", Language::English).unwrap();
+        assert_eq!(drop, parse_uci("N@f6").unwrap());
+        assert_eq!(
+            format_san(&table, &drop, Language::Spanish).unwrap(),
+            "C@f6+"
+        );
+        assert!(make_move(&mut table, &parse_uci("N@e8").unwrap()).is_err());
+        assert!(make_move(&mut table, &parse_uci("Q@e4").unwrap()).is_err());
+
+        // The promoted rook goes back to the pocket as a pawn, which cannot be dropped on the
+        // first or last row
+        let before = table.clone();
+        play(&mut table, &["f3d5"]);
+        assert_eq!(table.state().pocket(Color::White).count(PieceType::P), 1);
+        assert_eq!(table.state().pocket(Color::White).count(PieceType::T), 0);
+        assert_eq!(table.state().promoted, 0);
+        play(&mut table, &["P@e2"]);
+        assert!(table.state().pocket(Color::Black).is_empty());
+        assert!(make_move(&mut table, &parse_uci("P@a8").unwrap()).is_err());
+        play(&mut table, &["P@a7"]);
+        assert!(!table.state().pockets.0.is_empty());
+        unmake_move(&mut table);
+        unmake_move(&mut table);
+        unmake_move(&mut table);
+        assert_eq!(table, before);
+
+        // Drops only start once a piece is captured
+        let mut table = setup_variant_test("tables/start.txt");
+        table.set_variant(Variant::Crazyhouse);
+        assert_eq!(table.state().pocket(Color::White), Pocket::default());
+        assert_eq!(perft(&mut table, 3), 8902);
+    }
+}
@@ -0,0 +1,211 @@
+use crate::game::table::piece::position::Position;
+use crate::game::table::piece::{Color, PieceType};
+use crate::game::table::state::Pocket;
+use crate::game::table::{Table, BOARD_SIZE};
+
+/// The number of squares of the board.
+const SQUARES: usize = BOARD_SIZE * BOARD_SIZE;
+
+/// The index of the key of the player to move, after the keys of every piece on every square.
+const SIDE_KEY: usize = 6 * 2 * SQUARES;
+
+/// The index of the first key of the castling rights, in the order white king side, white
+/// queen side, black king side and black queen side.
+const CASTLING_KEYS: usize = SIDE_KEY + 1;
+
+/// The index of the first key of the files of the en passant square.
+const EN_PASSANT_KEYS: usize = CASTLING_KEYS + 4;
+
+/// The number of keys.
+const KEY_COUNT: usize = EN_PASSANT_KEYS + BOARD_SIZE;
+
+/// The seed of the keys. Changing it changes every hash, so it must never change.
+const SEED: u64 = 0x7470_315f_7275_7374;
+
+/// The random keys combined into hashes, generated with SplitMix64 from `SEED`.
+static KEYS: [u64; KEY_COUNT] = generate_keys();
+
+/// Generates the keys at compile time, so that they are the same on every platform and in
+/// every version of the crate.
+const fn generate_keys() -> [u64; KEY_COUNT] {
+    let mut keys = [0; KEY_COUNT];
+    let mut state = SEED;
+    let mut i = 0;
+    while i < KEY_COUNT {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        keys[i] = mix(state);
+        i += 1;
+    }
+    keys
+}
+
+/// Mixes the bits of a value, as the output function of SplitMix64.
+const fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Computes the Zobrist hash of a table.
+///
+/// The hash combines a random key for each piece type, color and square of the pieces, for
+/// the player to move, for each castling right, for the file of the en passant square and
+/// for the checks given at Three-check, the pockets at Crazyhouse and the promoted pieces.
+/// The en passant square is only taken into account when a pawn of the player to move
+/// stands next to the pawn that can be captured, so that tables allowing the same moves
+/// have the same hash.
+///
+/// The keys are fixed, so the hash of a table never changes between runs nor versions of
+/// the crate, and it can be stored as the key of a table in a database.
+///
+/// # Arguments
+///
+/// * `table` - A reference to the `Table` to hash.
+///
+/// # Returns
+///
+/// A 64-bit hash. Equal tables have equal hashes, whatever the order of their pieces or
+/// their history, and different tables almost never do.
+///
+pub fn zobrist_hash(table: &Table) -> u64 {
+    let mut hash = 0;
+    for piece in table.pieces() {
+        let color = match piece.color {
+            Color::White => 0,
+            Color::Black => 1,
+            Color::Empty => continue,
+        };
+        let square = piece.position.y * BOARD_SIZE + piece.position.x;
+        let kind = match piece.piece_type {
+            PieceType::R => 0,
+            PieceType::D => 1,
+            PieceType::A => 2,
+            PieceType::C => 3,
+            PieceType::T => 4,
+            PieceType::P => 5,
+            // Registered kinds have no keys of their own, so theirs are mixed from the symbol
+            PieceType::Custom(symbol) => {
+                let seed = SEED ^ ((symbol as u64) << 16) ^ ((color as u64) << 8) ^ square as u64;
+                hash ^= mix(seed);
+                continue;
+            }
+            PieceType::Empty => continue,
+        };
+        hash ^= KEYS[(kind * 2 + color) * SQUARES + square];
+    }
+
+    let state = table.state();
+    if state.side_to_move == Color::Black {
+        hash ^= KEYS[SIDE_KEY];
+    }
+    let castling = [
+        state.castling.white_king_side,
+        state.castling.white_queen_side,
+        state.castling.black_king_side,
+        state.castling.black_queen_side,
+    ];
+    for (i, right) in castling.iter().enumerate() {
+        if *right {
+            hash ^= KEYS[CASTLING_KEYS + i];
+        }
+    }
+    if let Some(en_passant) = state.en_passant {
+        if can_capture_en_passant(table, &en_passant) {
+            hash ^= KEYS[EN_PASSANT_KEYS + en_passant.x];
+        }
+    }
+    // Checks are only counted at Three-check, so they have no keys of their own either
+    if state.checks != (0, 0) {
+        hash ^= mix(SEED
+            ^ (0x6368_6563_6b73 << 16)
+            ^ ((state.checks.0 as u64) << 8)
+            ^ state.checks.1 as u64);
+    }
+    // Pockets and promoted pieces are only kept at Crazyhouse, and are mixed the same way
+    for (color, pocket) in [state.pockets.0, state.pockets.1].iter().enumerate() {
+        for (kind, piece_type) in Pocket::PIECE_TYPES.iter().enumerate() {
+            let count = pocket.count(*piece_type) as u64;
+            if count > 0 {
+                hash ^= mix(SEED
+                    ^ (0x706f_636b_6574 << 16)
+                    ^ (count << 8)
+                    ^ ((kind as u64) << 1)
+                    ^ color as u64);
+            }
+        }
+    }
+    if state.promoted != 0 {
+        hash ^= mix(SEED ^ state.promoted.rotate_left(17));
+    }
+    hash
+}
+
+/// Checks if a pawn of the player to move stands next to the pawn that can be captured en
+/// passant.
+fn can_capture_en_passant(table: &Table, en_passant: &Position) -> bool {
+    let color = table.state().side_to_move;
+    let row = match color {
+        Color::White => en_passant.y + 1,
+        Color::Black if en_passant.y > 0 => en_passant.y - 1,
+        _ => return false,
+    };
+    [en_passant.x.checked_sub(1), Some(en_passant.x + 1)]
+        .iter()
+        .flatten()
+        .filter(|x| **x < BOARD_SIZE)
+        .any(|x| {
+            table
+                .piece_at(&Position { x: *x, y: row })
+                .is_some_and(|piece| piece.piece_type == PieceType::P && piece.color == color)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_zobrist_hash() {
+        use super::*;
+        use crate::game::table::fen::{parse_fen, START_FEN};
+        use crate::game::table::moves::{make_move, unmake_move};
+        use crate::game::table::notation::parse_uci;
+        use crate::game::table::parse_position;
+        use crate::utils::read_file;
+
+        // The hash is stable and does not depend on how the table was loaded
+        let table = parse_fen(START_FEN).unwrap();
+        assert_eq!(zobrist_hash(&table), 0xcd90_6899_52a1_338d);
+        let file = parse_position(&read_file(&"tables/start.txt").unwrap()).unwrap();
+        assert_eq!(zobrist_hash(&table), zobrist_hash(&file));
+
+        // Transpositions reach the same hash, and taking moves back restores it
+        let play = |moves: &[&str]| {
+            let mut table = parse_fen(START_FEN).unwrap();
+            for mv in moves {
+                make_move(&mut table, &parse_uci(mv).unwrap()).unwrap();
+            }
+            table
+        };
+        let mut first = play(&["e2e4", "e7e5", "g1f3"]);
+        let second = play(&["g1f3", "e7e5", "e2e4"]);
+        assert_eq!(zobrist_hash(&first), zobrist_hash(&second));
+        unmake_move(&mut first);
+        unmake_move(&mut first);
+        unmake_move(&mut first);
+        assert_eq!(zobrist_hash(&first), zobrist_hash(&table));
+
+        // The player to move and castling rights change the hash
+        let white = parse_fen("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1").unwrap();
+        let black = parse_fen("4k3/8/8/8/8/8/8/R3K3 b Q - 0 1").unwrap();
+        let no_castling = parse_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert_ne!(zobrist_hash(&white), zobrist_hash(&black));
+        assert_ne!(zobrist_hash(&white), zobrist_hash(&no_castling));
+
+        // The en passant square only counts when the pawn can be captured
+        let with = parse_fen("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1").unwrap();
+        let without = parse_fen("4k3/8/8/8/4P3/8/8/4K3 b - - 0 1").unwrap();
+        assert_eq!(zobrist_hash(&with), zobrist_hash(&without));
+        let with = parse_fen("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1").unwrap();
+        let without = parse_fen("4k3/8/8/8/3pP3/8/8/4K3 b - - 0 1").unwrap();
+        assert_ne!(zobrist_hash(&with), zobrist_hash(&without));
+    }
+}
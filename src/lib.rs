@@ -1,2 +1,5 @@
+pub mod ffi;
 pub mod game;
+pub mod protocol;
 pub mod utils;
+pub mod wasm;
@@ -1,59 +1,418 @@
-mod game;
-mod utils;
-
-use crate::game::play_game;
-use crate::utils::read_file;
 use std::env;
+use std::io::BufRead;
+use std::io::{self, Read, Write};
+use std::process::{Command as Process, Stdio};
+use std::sync::Arc;
+use std::time::Duration;
+use tp1_rust::game::table::fen::START_FEN;
+use tp1_rust::game::table::notation::{format_uci, Language};
+use tp1_rust::game::table::perft::divide;
+use tp1_rust::game::table::piece::Color;
+use tp1_rust::game::table::problem::{format_solution, solve_mate_in};
+use tp1_rust::game::table::search::SearchLimits;
+use tp1_rust::game::table::tablebase::{Material, Tablebase};
+use tp1_rust::game::table::variant::Variant;
+use tp1_rust::game::table::Table;
+use tp1_rust::game::{dedup_tables, load_position, play_game_with_variant};
+use tp1_rust::protocol::tui::{parse_keys, Tui};
+use tp1_rust::protocol::versus::Versus;
+use tp1_rust::utils::read_file;
+
+/// An enum representing the commands that the program can run.
+#[derive(PartialEq, Debug)]
+enum Command {
+    /// Plays the game described by a table file.
+    Play(String),
+    /// Groups the equivalent table files of a directory.
+    Dedup(String),
+    /// Counts the move paths of a position, given as a table file or FEN, to a depth.
+    Perft(String, usize),
+    /// Solves the mate-in-N problem of a position, given as a table file or FEN.
+    Solve(String, usize),
+    /// Generates the tablebase of an endgame, such as `KQvK`, and writes it to a file.
+    Tablebase(String, String),
+    /// Lets two players play in the terminal, from a table file or FEN or the start position.
+    Tui(Option<String>),
+    /// Plays against the engine from a table file or FEN or the start position, with the
+    /// color of the user, the limits of the engine and the file the game is saved to.
+    Versus(Option<String>, Color, SearchLimits, String),
+}
+
+/// The depth the engine searches when playing against the user, unless told otherwise.
+const VERSUS_DEPTH: usize = 4;
+
+/// The file games against the engine are saved to, unless told otherwise.
+const VERSUS_PGN: &str = "game.pgn";
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    match parse_args(&args) {
-        Ok(_) => {}
+    let (command, variant) = match parse_variant(&args)
+        .and_then(|(args, variant)| parse_args(&args).map(|command| (command, variant)))
+    {
+        Ok(parsed) => parsed,
         Err(e) => {
             println!("{}", e);
             return;
         }
+    };
+
+    match command {
+        Command::Play(file) => play(&file, variant),
+        Command::Dedup(dir) => dedup(&dir),
+        Command::Perft(source, depth) => perft(&source, depth, variant),
+        Command::Solve(source, moves) => solve(&source, moves, variant),
+        Command::Tablebase(material, file) => tablebase(&material, &file),
+        Command::Tui(source) => tui(source.as_deref().unwrap_or(START_FEN), variant),
+        Command::Versus(source, color, limits, pgn) => versus(
+            source.as_deref().unwrap_or(START_FEN),
+            variant,
+            color,
+            limits,
+            pgn,
+        ),
     }
+}
+
+/// Loads the position in `source`, played with `variant` if one is given.
+fn load(source: &str, variant: Option<Variant>) -> Result<Table, String> {
+    let mut table = load_position(source)?;
+    if let Some(variant) = variant {
+        table.set_variant(variant);
+    }
+    Ok(table)
+}
 
-    let lines: Vec<String> = match read_file(&args[1]) {
+/// Plays the game described by the table file `file` and prints its result.
+fn play(file: &str, variant: Option<Variant>) {
+    let lines: Vec<String> = match read_file(&file) {
         Ok(file_contents) => file_contents,
         Err(e) => {
             println!("{}", e);
             return;
         }
     };
-    match play_game(lines) {
+    match play_game_with_variant(lines, variant) {
         Ok(res) => println!("{}", res),
         Err(e) => println!("{}", e),
     }
 }
 
+/// Prints the groups of equivalent table files found in `dir`, one group per line.
+fn dedup(dir: &str) {
+    match dedup_tables(&dir) {
+        Ok(groups) => {
+            for group in groups {
+                println!("{}", group.join(" "));
+            }
+        }
+        Err(e) => println!("{}", e),
+    }
+}
+
+/// Prints the number of move paths of the position in `source` after each legal move, and
+/// their total, to the given depth.
+fn perft(source: &str, depth: usize, variant: Option<Variant>) {
+    let mut table = match load(source, variant) {
+        Ok(table) => table,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    let result = divide(&mut table, depth);
+    for (mv, nodes) in &result {
+        println!("{}: {}", format_uci(mv), nodes);
+    }
+    println!();
+    println!(
+        "Nodes: {}",
+        result.iter().map(|(_, nodes)| nodes).sum::<u64>()
+    );
+}
+
+/// Prints the keys and tries of the problem in `source`, where the player to move mates in
+/// `moves` moves.
+fn solve(source: &str, moves: usize, variant: Option<Variant>) {
+    let table = match load(source, variant) {
+        Ok(table) => table,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    match solve_mate_in(&table, moves) {
+        Ok(solution) => {
+            for line in format_solution(&table, &solution, Language::Spanish) {
+                println!("{}", line);
+            }
+        }
+        Err(e) => println!("{}", e),
+    }
+}
+
+/// Generates the tablebase of `material`, writes it to `file` and prints its longest win.
+fn tablebase(material: &str, file: &str) {
+    let tablebase = match material
+        .parse::<Material>()
+        .and_then(|material| Tablebase::generate(&material))
+    {
+        Ok(tablebase) => tablebase,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    match tablebase.write(&file) {
+        Ok(()) => println!(
+            "{}: longest win in {} plies",
+            tablebase.material(),
+            tablebase.longest_win()
+        ),
+        Err(e) => println!("{}", e),
+    }
+}
+
+/// Lets two players play the position in `source` in the terminal, until one of them quits.
+///
+/// The terminal is switched to reading keys one at a time without echoing them with `stty`,
+/// and restored when leaving.
+fn tui(source: &str, variant: Option<Variant>) {
+    let table = match load(source, variant) {
+        Ok(table) => table,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    let saved = match stty(&["-g"]) {
+        Ok(saved) => saved,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    if let Err(e) = stty(&["-icanon", "-echo", "-isig", "min", "1"]) {
+        println!("{}", e);
+        return;
+    }
+
+    let mut tui = Tui::new(table);
+    let mut stdout = io::stdout();
+    let mut buffer = [0; 64];
+    // Hide the cursor of the terminal while playing
+    print!("\x1b[?25l{}", tui.render());
+    let _ = stdout.flush();
+    'play: while let Ok(read @ 1..) = io::stdin().read(&mut buffer) {
+        for key in parse_keys(&buffer[..read]) {
+            if !tui.handle_key(key) {
+                break 'play;
+            }
+        }
+        print!("{}", tui.render());
+        let _ = stdout.flush();
+    }
+    print!("\x1b[?25h");
+    let _ = stdout.flush();
+    let _ = stty(&[saved.trim()]);
+}
+
+/// Lets the user play `color` against the engine from the position in `source`, reading
+/// moves and commands from the standard input, and saves the game to `pgn`.
+fn versus(source: &str, variant: Option<Variant>, color: Color, limits: SearchLimits, pgn: String) {
+    let table = match load(source, variant) {
+        Ok(table) => table,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    let output = Arc::new(|line: &str| println!("{}", line));
+    let mut versus = Versus::new(table, color, limits, Some(pgn), output);
+    versus.start();
+    for line in io::stdin().lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        if !versus.handle_command(&line) {
+            return;
+        }
+    }
+    versus.handle_command("quit");
+}
+
+/// Parses the arguments of the `play` command: the color of the user, `--depth <n>` or
+/// `--time <ms>` for the engine, `--pgn <file>` for the saved game, and the position to
+/// start from, in any order.
+///
+/// # Returns
+///
+/// * `Ok(Command)` - The `Versus` command, playing white against the engine searching to
+///   `VERSUS_DEPTH` from the start position and saving to `VERSUS_PGN` by default.
+///
+/// * `Err(String)` - An error message if an option is missing its value or has an invalid
+///   one, or if more than one position is given.
+///
+fn parse_play_args(args: &[String]) -> Result<Command, String> {
+    let mut source = None;
+    let mut color = Color::White;
+    let mut limits = SearchLimits {
+        depth: Some(VERSUS_DEPTH),
+        time: None,
+        root_moves: None,
+    };
+    let mut pgn = VERSUS_PGN.to_string();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or("ERROR: Not enough arguments".to_string());
+        let number = |value: &String| match value.parse::<u64>() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => Err(format!("ERROR: Invalid number: {}", value)),
+        };
+        match arg.as_str() {
+            "white" => color = Color::White,
+            "black" => color = Color::Black,
+            "--depth" => {
+                limits = SearchLimits {
+                    depth: Some(number(value()?)? as usize),
+                    time: None,
+                    root_moves: None,
+                }
+            }
+            "--time" => {
+                limits = SearchLimits {
+                    depth: None,
+                    time: Some(Duration::from_millis(number(value()?)?)),
+                    root_moves: None,
+                }
+            }
+            "--pgn" => pgn = value()?.clone(),
+            _ if source.is_none() => source = Some(arg.clone()),
+            _ => return Err("ERROR: Too many arguments passed".to_string()),
+        }
+    }
+    Ok(Command::Versus(source, color, limits, pgn))
+}
+
+/// Runs `stty` on the terminal of the standard input with the given arguments.
+///
+/// # Returns
+///
+/// * `Ok(String)` - What `stty` printed, such as the settings asked with `-g`.
+///
+/// * `Err(String)` - An error message if `stty` could not be run or failed, such as when
+///   the standard input is not a terminal.
+///
+fn stty(args: &[&str]) -> Result<String, String> {
+    let output = Process::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .output()
+        .map_err(|e| format!("ERROR: {}", e))?;
+    if !output.status.success() {
+        return Err("ERROR: The standard input is not a terminal".to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Takes the `--variant <name>` option out of the command-line arguments.
+///
+/// The option chooses the variant of the games played from a table file and by the `play`,
+/// `perft`, `solve` and `tui` commands, instead of the one declared in the table file, and
+/// may appear anywhere after the program name.
+///
+/// # Returns
+///
+/// * `Ok((Vec<String>, Option<Variant>))` - The other arguments, and the variant if the option
+///   was given.
+///
+/// * `Err(String)` - An error message if the name of the variant is missing or unknown.
+///
+fn parse_variant(args: &[String]) -> Result<(Vec<String>, Option<Variant>), String> {
+    let mut rest = Vec::new();
+    let mut variant = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--variant" {
+            match args.next() {
+                Some(name) => variant = Some(name.parse::<Variant>()?),
+                None => return Err("ERROR: Not enough arguments".to_string()),
+            }
+        } else {
+            rest.push(arg.clone());
+        }
+    }
+    Ok((rest, variant))
+}
+
 /// Parses the command-line arguments passed to the program and ensures that they are valid.
 ///
 /// The input `args` should be a vector of strings containing the command-line arguments.
 ///
-/// Checks if only one argument was passed and if it is a .txt file. If not, returns an error.
+/// Accepts either a single .txt file, the `dedup` command followed by a directory, or the
+/// `perft` or `solve` command followed by a .txt file or FEN and a number, the
+/// `tablebase` command followed by a material and an output file, or the `tui` command
+/// optionally followed by a .txt file or FEN, or the `play` command followed by the
+/// arguments read by `parse_play_args`. Otherwise, returns an error.
 ///
 /// # Arguments
 ///
-/// * `args` - A vector of strings containing the command-line arguments.
+/// * `args` - A slice of strings containing the command-line arguments.
 ///
 /// # Returns
 ///
-/// * `Ok(())` - If the command-line arguments are valid.
+/// * `Ok(Command)` - The command to run, if the command-line arguments are valid.
 ///
 /// * `Err(String)` - An error message describing why the command-line arguments are invalid.
 ///
-fn parse_args(args: &Vec<String>) -> Result<(), String> {
+fn parse_args(args: &[String]) -> Result<Command, String> {
     if args.len() < 2 {
         return Err("ERROR: Not enough arguments".to_string());
     }
+    if args[1] == "dedup" {
+        return match args.len() {
+            3 => Ok(Command::Dedup(args[2].clone())),
+            2 => Err("ERROR: Not enough arguments".to_string()),
+            _ => Err("ERROR: Too many arguments passed".to_string()),
+        };
+    }
+    if args[1] == "tablebase" {
+        return match args.len() {
+            4 => Ok(Command::Tablebase(args[2].clone(), args[3].clone())),
+            0..=3 => Err("ERROR: Not enough arguments".to_string()),
+            _ => Err("ERROR: Too many arguments passed".to_string()),
+        };
+    }
+    if args[1] == "play" {
+        return parse_play_args(&args[2..]);
+    }
+    if args[1] == "tui" {
+        return match args.len() {
+            2 => Ok(Command::Tui(None)),
+            3 => Ok(Command::Tui(Some(args[2].clone()))),
+            _ => Err("ERROR: Too many arguments passed".to_string()),
+        };
+    }
+    if args[1] == "perft" || args[1] == "solve" {
+        return match args.len() {
+            4 => match args[3].parse::<usize>() {
+                Ok(n) if n > 0 && args[1] == "perft" => Ok(Command::Perft(args[2].clone(), n)),
+                Ok(n) if n > 0 => Ok(Command::Solve(args[2].clone(), n)),
+                _ => Err(format!("ERROR: Invalid number: {}", args[3])),
+            },
+            0..=3 => Err("ERROR: Not enough arguments".to_string()),
+            _ => Err("ERROR: Too many arguments passed".to_string()),
+        };
+    }
     if args.len() >= 3 {
         return Err("ERROR: Too many arguments passed".to_string());
     }
     if !args[1].ends_with(".txt") {
-        return Err("ERROR: Arguments should be entered in format: -- <file.txt>".to_string());
+        return Err(
+            "ERROR: Arguments should be entered in format: -- <file.txt>, -- dedup <dir>, -- perft <file.txt|fen> <depth>, -- solve <file.txt|fen> <moves> or -- tablebase <material> <file> , -- tui [<file.txt|fen>] or -- play [white|black] [--depth <n>|--time <ms>] [--pgn <file>] [<file.txt|fen>], optionally with --variant <name>"
+                .to_string(),
+        );
     }
 
-    Ok(())
+    Ok(Command::Play(args[1].clone()))
 }
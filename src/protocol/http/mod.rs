@@ -0,0 +1,522 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::game::game_result;
+use crate::game::table::fen::{format_fen, parse_fen};
+use crate::game::table::piece::{Color, Piece};
+use crate::game::table::variant::Variant;
+use crate::game::table::{check_moves, parse_position, Table};
+use crate::protocol::json::{parse_json, Json};
+
+/// The largest request body accepted, in bytes.
+pub const MAX_BODY: usize = 64 * 1024;
+
+/// The longest request line or header line accepted, in bytes.
+pub const MAX_LINE: usize = 8 * 1024;
+
+/// The most headers accepted in a request.
+pub const MAX_HEADERS: usize = 100;
+
+/// The most connections answered at the same time. The others are refused with status 503.
+pub const MAX_CONNECTIONS: usize = 64;
+
+/// How long a client may leave a connection silent, or unread, before it is dropped.
+pub(crate) const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the rest of a request is read and dropped after answering it, so that the client
+/// gets the answer instead of a reset connection. Refused connections wait less, since they
+/// hold up the accepting thread.
+const LINGER: Duration = Duration::from_secs(1);
+const REFUSED_LINGER: Duration = Duration::from_millis(100);
+
+/// A struct representing the answer to a request: an HTTP status code and a JSON body.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Response {
+    /// The HTTP status code, such as 200 or 400.
+    pub status: u16,
+    /// The body of the answer.
+    pub body: Json,
+}
+
+impl Response {
+    /// Creates a successful response.
+    fn ok(body: Json) -> Response {
+        Response { status: 200, body }
+    }
+
+    /// Creates an error response, whose body is `{"error": {"status": ..., "message": ...}}`.
+    pub(crate) fn error(status: u16, message: &str) -> Response {
+        Response {
+            status,
+            body: Json::object(vec![(
+                "error",
+                Json::object(vec![
+                    ("status", Json::Number(status as f64)),
+                    ("message", message.into()),
+                ]),
+            )]),
+        }
+    }
+}
+
+/// Answers a request to the analysis service.
+///
+/// The endpoints are:
+///
+/// * `GET /health` - Answers `{"status": "ok"}`.
+/// * `POST /analyze` - Analyzes the board given in the JSON body, either in the table file
+///   format as `{"table": "..."}`, a string or an array of lines, or as `{"fen": "..."}`,
+///   with an optional `"variant"`. Answers the pieces, the player to move, the FEN, the
+///   results of `check_moves` and the verdict of the game, as given by `play_game`. The
+///   FEN is `null` for boards with the fairy pieces of a `fairy` header, which it cannot
+///   describe.
+///
+/// # Arguments
+///
+/// * `method` - The HTTP method, such as `GET`.
+/// * `path` - The path of the request, without the query string.
+/// * `body` - The body of the request.
+///
+/// # Returns
+///
+/// The response, with status 400 and the error of the parser when the board is invalid, 404
+/// for an unknown path and 405 for a method not allowed on a known one.
+///
+pub fn handle_request(method: &str, path: &str, body: &str) -> Response {
+    match (method, path) {
+        ("GET", "/health") => Response::ok(Json::object(vec![("status", "ok".into())])),
+        ("POST", "/analyze") => match parse_board(body) {
+            Ok(table) => Response::ok(analysis(&table)),
+            Err(e) => Response::error(400, &e),
+        },
+        (_, "/health" | "/analyze") => Response::error(405, "ERROR: Method not allowed"),
+        _ => Response::error(404, &format!("ERROR: Not found: {}", path)),
+    }
+}
+
+/// Parses the board of the body of an `/analyze` request.
+fn parse_board(body: &str) -> Result<Table, String> {
+    board_from_json(&parse_json(body)?)
+}
+
+/// Reads a board from the members of a JSON object: either `"table"`, in the table file
+/// format, as a string or an array of lines, or `"fen"`, with an optional `"variant"`.
+///
+/// # Errors
+///
+/// Returns an error if the members are missing or of the wrong kind, or if the board cannot
+/// be parsed.
+///
+pub(crate) fn board_from_json(request: &Json) -> Result<Table, String> {
+    let mut table = match (request.get("table"), request.get("fen")) {
+        (Some(Json::String(text)), None) => {
+            let lines: Vec<String> = text.lines().map(|line| line.to_string()).collect();
+            parse_position(&lines)?
+        }
+        (Some(Json::Array(values)), None) => {
+            let lines = values
+                .iter()
+                .map(|value| value.as_str().map(|line| line.to_string()))
+                .collect::<Option<Vec<String>>>()
+                .ok_or("ERROR: The lines of the table must be strings".to_string())?;
+            parse_position(&lines)?
+        }
+        (None, Some(Json::String(fen))) => parse_fen(fen)?,
+        _ => {
+            return Err(
+                "ERROR: The body must have either a \"table\" or a \"fen\" string".to_string(),
+            )
+        }
+    };
+    match request.get("variant") {
+        Some(Json::String(name)) => table.set_variant(name.parse::<Variant>()?),
+        Some(_) => return Err("ERROR: The variant must be a string".to_string()),
+        None => {}
+    }
+    Ok(table)
+}
+
+/// Returns the analysis of a table answered by `/analyze`.
+pub(crate) fn analysis(table: &Table) -> Json {
+    let (white, black) = check_moves(table);
+    // The letters of the fairy pieces of the table would not be read back from the FEN
+    let fen = if table.fairy_pieces().is_empty() {
+        format_fen(table).into()
+    } else {
+        Json::Null
+    };
+    Json::object(vec![
+        (
+            "pieces",
+            Json::Array(table.pieces().iter().map(piece_json).collect()),
+        ),
+        ("side_to_move", color_json(table.state().side_to_move)),
+        ("variant", table.state().variant.to_string().into()),
+        ("fen", fen),
+        (
+            "check_moves",
+            Json::object(vec![("white", white.into()), ("black", black.into())]),
+        ),
+        ("verdict", game_result(table).code().into()),
+    ])
+}
+
+/// Returns a piece as `{"type": "D", "color": "white", "square": "d1"}`, where the type is
+/// the letter of the table files.
+pub(crate) fn piece_json(piece: &Piece) -> Json {
+    Json::object(vec![
+        (
+            "type",
+            piece.symbol().to_ascii_uppercase().to_string().into(),
+        ),
+        ("color", color_json(piece.color)),
+        ("square", piece.position.to_string().into()),
+    ])
+}
+
+/// Returns the name of a color.
+pub(crate) fn color_json(color: Color) -> Json {
+    match color {
+        Color::White => "white",
+        Color::Black => "black",
+        Color::Empty => "empty",
+    }
+    .into()
+}
+
+/// Serves the analysis service on a listener, answering each connection in its own thread.
+///
+/// Each connection carries a single request, and is closed once it is answered. At most
+/// `MAX_CONNECTIONS` are answered at the same time, and a client silent for `TIMEOUT` is
+/// dropped.
+pub fn serve(listener: TcpListener) {
+    let connections = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming().flatten() {
+        spawn_connection(stream, &connections, handle_connection);
+    }
+}
+
+/// Handles a connection in its own thread with a timeout of `TIMEOUT`, unless
+/// `MAX_CONNECTIONS` are already being handled, in which case it is refused with status 503.
+///
+/// # Arguments
+///
+/// * `stream` - The accepted connection.
+/// * `connections` - The number of connections being handled, shared by the server.
+/// * `handle` - The function handling the connection.
+///
+pub(crate) fn spawn_connection(
+    mut stream: TcpStream,
+    connections: &Arc<AtomicUsize>,
+    handle: impl FnOnce(TcpStream) -> io::Result<()> + Send + 'static,
+) {
+    if stream.set_read_timeout(Some(TIMEOUT)).is_err()
+        || stream.set_write_timeout(Some(TIMEOUT)).is_err()
+    {
+        return;
+    }
+    if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+        connections.fetch_sub(1, Ordering::SeqCst);
+        let refused = Response::error(503, "ERROR: Too many connections");
+        if write_response(&mut stream, &refused).is_ok() {
+            linger(&mut stream, REFUSED_LINGER);
+        }
+        return;
+    }
+    let connections = Arc::clone(connections);
+    thread::spawn(move || {
+        let _ = handle(stream);
+        connections.fetch_sub(1, Ordering::SeqCst);
+    });
+}
+
+/// Reads a request from a connection and writes its response.
+fn handle_connection(mut stream: TcpStream) -> io::Result<()> {
+    let response = match read_request(&mut stream) {
+        Ok((method, path, body)) => handle_request(&method, &path, &body),
+        Err(response) => response,
+    };
+    respond(&mut stream, &response)
+}
+
+/// Writes a response, then closes the connection once the client has read it.
+pub(crate) fn respond(stream: &mut TcpStream, response: &Response) -> io::Result<()> {
+    write_response(stream, response)?;
+    linger(stream, LINGER);
+    Ok(())
+}
+
+/// Closes the writing half of a connection, then reads and drops what the client still sends
+/// for at most `duration`.
+fn linger(stream: &mut TcpStream, duration: Duration) {
+    if stream.shutdown(Shutdown::Write).is_err() || stream.set_read_timeout(Some(duration)).is_err()
+    {
+        return;
+    }
+    let _ = io::copy(&mut stream.take(MAX_BODY as u64), &mut io::sink());
+}
+
+/// Writes a response, announcing that the connection is closed after it.
+fn write_response(stream: &mut TcpStream, response: &Response) -> io::Result<()> {
+    let body = response.body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        reason_phrase(response.status),
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// A struct representing the request line and the headers of an HTTP/1.1 request.
+pub(crate) struct RequestHead {
+    /// The method, such as `GET`.
+    pub method: String,
+    /// The path, without the query string.
+    pub path: String,
+    /// The names and values of the headers, in their order.
+    pub headers: Vec<(String, String)>,
+}
+
+impl RequestHead {
+    /// Returns the value of a header, whose name is compared ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Reads the request line and the headers of an HTTP/1.1 request, up to the empty line that
+/// ends them.
+///
+/// # Errors
+///
+/// Returns the error response to send if the connection fails, if the request line is
+/// malformed or longer than `MAX_LINE`, with status 400, or if a header line is longer than
+/// `MAX_LINE` or there are more than `MAX_HEADERS`, with status 431.
+///
+pub(crate) fn read_head<R: BufRead>(reader: &mut R) -> Result<RequestHead, Response> {
+    let bad_request = || Response::error(400, "ERROR: Malformed request");
+    let too_large = || Response::error(431, "ERROR: Request headers too large");
+    let mut line = String::new();
+    if !read_line(reader, &mut line).map_err(|_| bad_request())? {
+        return Err(Response::error(400, "ERROR: Request line too long"));
+    }
+    let mut words = line.split_whitespace();
+    let (method, target) = match (words.next(), words.next()) {
+        (Some(method), Some(target)) => (method.to_string(), target),
+        _ => return Err(bad_request()),
+    };
+    let path = target.split('?').next().unwrap_or("").to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        if !read_line(reader, &mut line).map_err(|_| bad_request())? {
+            return Err(too_large());
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if headers.len() == MAX_HEADERS {
+            return Err(too_large());
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    Ok(RequestHead {
+        method,
+        path,
+        headers,
+    })
+}
+
+/// Reads a line of at most `MAX_LINE` bytes, with its newline.
+///
+/// # Returns
+///
+/// `false` if the line is longer than `MAX_LINE`.
+///
+/// # Errors
+///
+/// Returns an error if the connection fails or is closed before the end of the line.
+///
+fn read_line<R: BufRead>(reader: &mut R, line: &mut String) -> io::Result<bool> {
+    let read = reader.take(MAX_LINE as u64 + 1).read_line(line)?;
+    if line.ends_with('\n') {
+        Ok(true)
+    } else if read > MAX_LINE {
+        Ok(false)
+    } else {
+        Err(io::ErrorKind::UnexpectedEof.into())
+    }
+}
+
+/// Reads the method, path and body of an HTTP/1.1 request.
+///
+/// # Errors
+///
+/// Returns the error response to send if the head of the request cannot be read, as
+/// described in `read_head`, if the request is malformed, or if its body is larger than
+/// `MAX_BODY`.
+///
+fn read_request(stream: &mut TcpStream) -> Result<(String, String, String), Response> {
+    let bad_request = || Response::error(400, "ERROR: Malformed request");
+    let mut reader = BufReader::new(stream);
+    let head = read_head(&mut reader)?;
+    let length = match head.header("content-length") {
+        Some(value) => value.parse().map_err(|_| bad_request())?,
+        None => 0,
+    };
+    if length > MAX_BODY {
+        return Err(Response::error(413, "ERROR: Request body too large"));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).map_err(|_| bad_request())?;
+    let body = String::from_utf8(body).map_err(|_| bad_request())?;
+    Ok((head.method, head.path, body))
+}
+
+/// Returns the reason phrase of the status codes answered.
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::table::parse_table;
+    use crate::utils::read_file;
+
+    #[test]
+    fn test_handle_request() {
+        // A white queen on d1 against a black rook on d8, on the same file
+        let board = "_ _ _ T _ _ _ _\n_ _ _ _ _ _ _ _\n_ _ _ _ _ _ _ _\n_ _ _ _ _ _ _ _\n\
+                     _ _ _ _ _ _ _ _\n_ _ _ _ _ _ _ _\n_ _ _ _ _ _ _ _\n_ _ _ d _ _ _ _";
+        let body = Json::object(vec![("table", board.into())]).to_string();
+        let response = handle_request("POST", "/analyze", &body);
+        assert_eq!(response.status, 200);
+        assert_eq!(
+            response.body.to_string(),
+            "{\"pieces\":[{\"type\":\"T\",\"color\":\"black\",\"square\":\"d8\"},\
+             {\"type\":\"D\",\"color\":\"white\",\"square\":\"d1\"}],\
+             \"side_to_move\":\"white\",\"variant\":\"standard\",\"fen\":\"3r4/8/8/8/8/8/8/3Q4 w - - 0 1\",\
+             \"check_moves\":{\"white\":true,\"black\":true},\"verdict\":\"E\"}"
+        );
+
+        // The queen can capture the knight, but at Antichess that makes black win
+        let body = r#"{"fen": "3n4/8/8/8/8/8/8/3Q4 w - - 0 1", "variant": "antichess"}"#;
+        let response = handle_request("POST", "/analyze", body);
+        assert_eq!(response.body.get("verdict"), Some(&Json::from("N")));
+
+        // The FEN answered is read back, unless the table defines its own fairy pieces
+        let analyze_file = |path: &str| {
+            let lines = read_file(&path).unwrap();
+            let body = Json::object(vec![("table", lines.join("\n").into())]).to_string();
+            (handle_request("POST", "/analyze", &body).body, lines)
+        };
+        let (analysis, lines) = analyze_file("tables/en_passant.txt");
+        let fen = analysis.get("fen").and_then(Json::as_str).unwrap();
+        assert_eq!(parse_fen(fen), parse_table(&lines));
+        let (analysis, lines) = analyze_file("tables/fairy.txt");
+        assert!(parse_table(&lines).is_ok());
+        assert_eq!(analysis.get("fen"), Some(&Json::Null));
+
+        let error = |body: &str| {
+            let response = handle_request("POST", "/analyze", body);
+            assert_eq!(response.status, 400);
+            let error = response.body.get("error").unwrap().clone();
+            error
+                .get("message")
+                .and_then(Json::as_str)
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(
+            error(r#"{"fen": "8/8 w"}"#),
+            "ERROR: FEN has 2 fields, expected 4 to 6"
+        );
+        assert_eq!(
+            error(r#"{"table": ["_ _"]}"#),
+            "ERROR: Table formated incorrectly. Table has 1 rows, expected 8"
+        );
+        assert!(error("{").starts_with("ERROR: Invalid JSON"));
+        assert!(error(&"[".repeat(60_000)).ends_with("nested more than 64 levels deep"));
+        assert!(error("{}").contains("\"table\" or a \"fen\""));
+        assert_eq!(handle_request("GET", "/analyze", "").status, 405);
+        assert_eq!(handle_request("GET", "/", "").status, 404);
+        assert_eq!(handle_request("GET", "/health", "").status, 200);
+    }
+
+    #[test]
+    fn test_serve() {
+        // The whole round trip against localhost, on a port chosen by the system
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || serve(listener));
+
+        let request = |text: String| {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream.write_all(text.as_bytes()).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+        let body = r#"{"fen": "4k3/8/8/8/8/8/8/4R1K1 w - - 0 1"}"#;
+        let response = request(format!(
+            "POST /analyze HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        ));
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        let (_, json) = response.split_once("\r\n\r\n").unwrap();
+        let json = parse_json(json).unwrap();
+        assert_eq!(json.get("verdict"), Some(&Json::from("B")));
+
+        let response = request(format!(
+            "POST /analyze HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY + 1
+        ));
+        assert!(response.starts_with("HTTP/1.1 413 Payload Too Large\r\n"));
+        let response = request("GET /health?verbose=1 HTTP/1.1\r\n\r\n".to_string());
+        assert!(response.ends_with("{\"status\":\"ok\"}"));
+
+        // The lines and the number of headers are bounded
+        let long = "a".repeat(MAX_LINE);
+        let response = request(format!("GET /{} HTTP/1.1\r\n\r\n", long));
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        let response = request(format!("GET /health HTTP/1.1\r\nX: {}\r\n\r\n", long));
+        assert!(response.starts_with("HTTP/1.1 431 Request Header Fields Too Large\r\n"));
+        let headers = "X: 1\r\n".repeat(MAX_HEADERS + 1);
+        let response = request(format!("GET /health HTTP/1.1\r\n{}\r\n", headers));
+        assert!(response.starts_with("HTTP/1.1 431 Request Header Fields Too Large\r\n"));
+
+        // Clients that hold their connections open use up the connections answered at once
+        let idle: Vec<TcpStream> = (0..MAX_CONNECTIONS)
+            .map(|_| TcpStream::connect(address).unwrap())
+            .collect();
+        let response = request("GET /health HTTP/1.1\r\n\r\n".to_string());
+        assert!(response.starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+        drop(idle);
+    }
+}
@@ -0,0 +1,363 @@
+use std::fmt;
+
+/// An enum representing a JSON value.
+///
+/// Objects keep their members in the order they were inserted, so the text written for a
+/// value is always the same.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Json {
+    /// The `null` value.
+    Null,
+    /// `true` or `false`.
+    Bool(bool),
+    /// A number.
+    Number(f64),
+    /// A string.
+    String(String),
+    /// An array of values.
+    Array(Vec<Json>),
+    /// An object, as its members in order.
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Creates an object from its members.
+    pub fn object(members: Vec<(&str, Json)>) -> Json {
+        Json::Object(
+            members
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    /// Returns the value of a member of an object, or `None` if the value is not an object
+    /// or has no such member.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Returns the string of a string value.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Json {
+        Json::String(s.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Json {
+        Json::String(s)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Json {
+        Json::Bool(b)
+    }
+}
+
+impl fmt::Display for Json {
+    /// Writes the value as compact JSON text, without spaces.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Number(n) if n.is_finite() => write!(f, "{}", n),
+            Json::Number(_) => write!(f, "null"),
+            Json::String(s) => write_string(f, s),
+            Json::Array(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(members) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+/// Writes a string between quotes, escaping the characters JSON does not allow in it.
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+/// The deepest arrays and objects may be nested in a JSON text.
+pub const MAX_DEPTH: usize = 64;
+
+/// Parses a JSON text into a `Json` value.
+///
+/// # Errors
+///
+/// Returns an error if the text is not a single valid JSON value, surrounded by whitespace
+/// at most, or if its arrays and objects are nested more than `MAX_DEPTH` levels deep.
+///
+pub fn parse_json(text: &str) -> Result<Json, String> {
+    let mut parser = Parser {
+        chars: text.chars().collect(),
+        i: 0,
+        depth: 0,
+    };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.i < parser.chars.len() {
+        return Err(parser.error());
+    }
+    Ok(value)
+}
+
+/// A struct reading a JSON text one character at a time.
+struct Parser {
+    /// The characters of the text.
+    chars: Vec<char>,
+    /// The index of the next character to read.
+    i: usize,
+    /// The number of values being read, one inside the other.
+    depth: usize,
+}
+
+impl Parser {
+    /// Returns the error for the character at the current index.
+    fn error(&self) -> String {
+        format!("ERROR: Invalid JSON at character {}", self.i)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.get(self.i).is_some_and(|c| c.is_whitespace()) {
+            self.i += 1;
+        }
+    }
+
+    /// Reads the next character, which must be `expected`.
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        if self.chars.get(self.i) != Some(&expected) {
+            return Err(self.error());
+        }
+        self.i += 1;
+        Ok(())
+    }
+
+    /// Reads a value, after any whitespace, refusing to nest arrays and objects deeper than
+    /// `MAX_DEPTH`, so that a hostile text cannot overflow the stack.
+    fn value(&mut self) -> Result<Json, String> {
+        if self.depth == MAX_DEPTH {
+            return Err(format!(
+                "{}: nested more than {} levels deep",
+                self.error(),
+                MAX_DEPTH
+            ));
+        }
+        self.depth += 1;
+        let value = self.unnested_value();
+        self.depth -= 1;
+        value
+    }
+
+    /// Reads a value, after any whitespace, as `value` does.
+    fn unnested_value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.chars.get(self.i) {
+            Some('n') => self.literal("null", Json::Null),
+            Some('t') => self.literal("true", Json::Bool(true)),
+            Some('f') => self.literal("false", Json::Bool(false)),
+            Some('"') => self.string().map(Json::String),
+            Some('[') => {
+                self.i += 1;
+                let mut values = Vec::new();
+                self.skip_whitespace();
+                if self.chars.get(self.i) == Some(&']') {
+                    self.i += 1;
+                    return Ok(Json::Array(values));
+                }
+                loop {
+                    values.push(self.value()?);
+                    self.skip_whitespace();
+                    match self.chars.get(self.i) {
+                        Some(',') => self.i += 1,
+                        Some(']') => {
+                            self.i += 1;
+                            return Ok(Json::Array(values));
+                        }
+                        _ => return Err(self.error()),
+                    }
+                }
+            }
+            Some('{') => {
+                self.i += 1;
+                let mut members = Vec::new();
+                self.skip_whitespace();
+                if self.chars.get(self.i) == Some(&'}') {
+                    self.i += 1;
+                    return Ok(Json::Object(members));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let key = self.string()?;
+                    self.skip_whitespace();
+                    self.expect(':')?;
+                    members.push((key, self.value()?));
+                    self.skip_whitespace();
+                    match self.chars.get(self.i) {
+                        Some(',') => self.i += 1,
+                        Some('}') => {
+                            self.i += 1;
+                            return Ok(Json::Object(members));
+                        }
+                        _ => return Err(self.error()),
+                    }
+                }
+            }
+            Some(c) if *c == '-' || c.is_ascii_digit() => self.number(),
+            _ => Err(self.error()),
+        }
+    }
+
+    /// Reads a literal word such as `null`.
+    fn literal(&mut self, word: &str, value: Json) -> Result<Json, String> {
+        for c in word.chars() {
+            self.expect(c)?;
+        }
+        Ok(value)
+    }
+
+    /// Reads a number.
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.i;
+        while self
+            .chars
+            .get(self.i)
+            .is_some_and(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+        {
+            self.i += 1;
+        }
+        let text: String = self.chars[start..self.i].iter().collect();
+        text.parse().map(Json::Number).map_err(|_| {
+            self.i = start;
+            self.error()
+        })
+    }
+
+    /// Reads a string between quotes, with its escape sequences.
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            let c = *self.chars.get(self.i).ok_or_else(|| self.error())?;
+            self.i += 1;
+            match c {
+                '"' => return Ok(s),
+                '\\' => {
+                    let escaped = *self.chars.get(self.i).ok_or_else(|| self.error())?;
+                    self.i += 1;
+                    s.push(match escaped {
+                        '"' | '\\' | '/' => escaped,
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'u' => self.unicode_escape()?,
+                        _ => return Err(self.error()),
+                    });
+                }
+                c if (c as u32) < 0x20 => return Err(self.error()),
+                c => s.push(c),
+            }
+        }
+    }
+
+    /// Reads the four hexadecimal digits of a `\u` escape, and the low surrogate that follows
+    /// a high one.
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let mut code = self.hex4()?;
+        if (0xd800..0xdc00).contains(&code) {
+            self.expect('\\')?;
+            self.expect('u')?;
+            let low = self.hex4()?;
+            if !(0xdc00..0xe000).contains(&low) {
+                return Err(self.error());
+            }
+            code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+        }
+        char::from_u32(code).ok_or_else(|| self.error())
+    }
+
+    /// Reads four hexadecimal digits.
+    fn hex4(&mut self) -> Result<u32, String> {
+        let digits: String = self.chars.iter().skip(self.i).take(4).collect();
+        if digits.len() != 4 {
+            return Err(self.error());
+        }
+        let code = u32::from_str_radix(&digits, 16).map_err(|_| self.error())?;
+        self.i += 4;
+        Ok(code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_json() {
+        use super::*;
+        let text = r#" {"board": "a\nb \"q\" \u00e9\ud83d\ude00", "n": [1, -2.5e1, true, null], "o": {}} "#;
+        let value = parse_json(text).unwrap();
+        assert_eq!(
+            value.get("board").and_then(Json::as_str),
+            Some("a\nb \"q\" é😀")
+        );
+        assert_eq!(
+            value.to_string(),
+            r#"{"board":"a\nb \"q\" é😀","n":[1,-25,true,null],"o":{}}"#
+        );
+        assert_eq!(parse_json(&value.to_string()), Ok(value));
+
+        // Deep nesting is refused instead of overflowing the stack
+        let nested = |depth: usize| "[".repeat(depth) + &"]".repeat(depth);
+        assert!(parse_json(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(
+            parse_json(&nested(MAX_DEPTH + 1)),
+            Err("ERROR: Invalid JSON at character 64: nested more than 64 levels deep".to_string())
+        );
+        assert!(parse_json(&"[".repeat(60_000)).is_err());
+
+        for invalid in ["", "{", "[1,]", "{\"a\" 1}", "tru", "\"\\x\"", "1 2"] {
+            assert!(parse_json(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,161 @@
+pub mod http;
+pub mod json;
+pub mod repl;
+pub mod tui;
+pub mod uci;
+pub mod versus;
+pub mod websocket;
+pub mod xboard;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::game::table::draw::{draw_reason, DrawReason};
+use crate::game::table::fen::{parse_fen, START_FEN};
+use crate::game::table::generator::{is_checkmate, is_stalemate, legal_moves};
+use crate::game::table::moves::{make_move, Move};
+use crate::game::table::notation::{parse_san, parse_uci, Language};
+use crate::game::table::piece::Color;
+use crate::game::table::variant::variant_winner;
+use crate::game::table::Table;
+
+/// A function that sends a line of text to the program driving the engine, usually by
+/// printing it to the standard output.
+///
+/// It is shared with the threads running searches, so that they can report their results.
+pub type Output = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// The number of moves assumed to be left in the game when the clock does not say how many
+/// moves must be played before the next time control.
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+/// The time kept on the clock, to account for the time taken to send the move.
+const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+
+/// Returns an `Output` that collects the lines it is sent, for the tests of the protocols,
+/// with the vector they are collected in.
+#[cfg(test)]
+pub(crate) fn collect_output() -> (Output, Arc<std::sync::Mutex<Vec<String>>>) {
+    let lines = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let sink = Arc::clone(&lines);
+    let output: Output = Arc::new(move |line: &str| sink.lock().unwrap().push(line.to_string()));
+    (output, lines)
+}
+
+/// Returns a table set up at the start position.
+pub(crate) fn start_table() -> Table {
+    parse_fen(START_FEN).expect("the start position is valid")
+}
+
+/// Plays a move given in coordinate notation, such as `e2e4` or `e7e8q`, if it is legal.
+///
+/// # Arguments
+///
+/// * `table` - A mutable reference to the `Table` to play the move on.
+/// * `text` - The move in coordinate notation, as used by both the UCI and XBoard protocols.
+///
+/// # Errors
+///
+/// Returns an error, leaving the table untouched, if the move cannot be parsed or is not
+/// legal.
+///
+pub(crate) fn play_move(table: &mut Table, text: &str) -> Result<Move, String> {
+    let mv = parse_uci(text)?;
+    if !legal_moves(table).contains(&mv) {
+        return Err(format!("ERROR: Illegal move: {}", text));
+    }
+    make_move(table, &mv)?;
+    Ok(mv)
+}
+
+/// Parses a move typed by a player into a legal move of the table, read in Standard
+/// Algebraic Notation with Spanish or English letters, or in the notation of the UCI
+/// protocol.
+///
+/// # Errors
+///
+/// Returns the error of reading the move with Spanish letters if it cannot be read in any
+/// way, or if it is not legal.
+///
+pub(crate) fn parse_typed_move(table: &Table, text: &str) -> Result<Move, String> {
+    parse_san(table, text, Language::Spanish).or_else(|e| {
+        parse_san(table, text, Language::English)
+            .or_else(|_| {
+                parse_uci(text)
+                    .ok()
+                    .filter(|mv| legal_moves(table).contains(mv))
+                    .ok_or(())
+            })
+            .map_err(|_| e)
+    })
+}
+
+/// Returns why the game on a table is over, if it is: a win by the rules of its variant, a
+/// checkmate, a stalemate or a draw rule.
+pub(crate) fn game_over(table: &Table) -> Option<String> {
+    if let Some(winner) = variant_winner(table) {
+        return Some(format!("{} wins", color_name(winner)));
+    }
+    if is_checkmate(table) {
+        let winner = table.state().side_to_move.opposite();
+        return Some(format!("{} wins by checkmate", color_name(winner)));
+    }
+    if is_stalemate(table) {
+        return Some("draw by stalemate".to_string());
+    }
+    draw_reason(table).map(|reason| match reason {
+        DrawReason::InsufficientMaterial => "draw by insufficient material".to_string(),
+        DrawReason::FiftyMoveRule => "draw by the fifty-move rule".to_string(),
+        DrawReason::ThreefoldRepetition => "draw by repetition".to_string(),
+    })
+}
+
+/// Returns the name of a color, as shown to the players.
+pub(crate) fn color_name(color: Color) -> &'static str {
+    match color {
+        Color::White => "White",
+        _ => "Black",
+    }
+}
+
+/// Decides how long to think about a move, given the state of the clock.
+///
+/// # Arguments
+///
+/// * `remaining` - The time left on the clock of the player to move.
+/// * `increment` - The time added to the clock after each move.
+/// * `moves_to_go` - The number of moves to play before the next time control, if any.
+///
+/// # Returns
+///
+/// An even share of the remaining time among the moves left, plus most of the increment,
+/// without ever using up the clock.
+///
+pub(crate) fn time_for_move(
+    remaining: Duration,
+    increment: Duration,
+    moves_to_go: Option<u32>,
+) -> Duration {
+    let moves = moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+    let share = remaining / moves + increment * 3 / 4;
+    share.min(remaining.saturating_sub(MOVE_OVERHEAD))
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_time_for_move() {
+        use super::*;
+        let second = Duration::from_secs(1);
+        assert_eq!(time_for_move(30 * second, Duration::ZERO, None), second);
+        assert_eq!(time_for_move(10 * second, 4 * second, Some(5)), 5 * second);
+        assert_eq!(
+            time_for_move(second, 10 * second, None),
+            second - MOVE_OVERHEAD
+        );
+        assert_eq!(
+            time_for_move(Duration::ZERO, Duration::ZERO, Some(0)),
+            Duration::ZERO
+        );
+    }
+}
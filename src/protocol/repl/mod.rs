@@ -0,0 +1,295 @@
+use std::fs;
+
+use crate::game::table::piece::position::Position;
+use crate::game::table::piece::{Color, Piece, PieceType};
+use crate::game::table::state::State;
+use crate::game::table::{check_moves, format_table, Table};
+use crate::game::{game_result, load_position};
+use crate::protocol::Output;
+
+/// The commands of the shell, with their arguments, as listed by `help`.
+const HELP: [&str; 10] = [
+    "place <square> <piece>  puts a piece, such as `d` or `T`, on a square",
+    "remove <square>         removes the piece on a square",
+    "move <from> <to>        moves a piece, capturing the piece on the other square",
+    "print                   prints the board in the table file format",
+    "check                   shows which colors can capture and the result of the game",
+    "undo                    takes back the last change to the board",
+    "load <file.txt|fen>     replaces the board with a table file or FEN",
+    "save <file.txt>         writes the board to a table file",
+    "help                    lists the commands",
+    "quit                    leaves the shell",
+];
+
+/// A struct representing an interactive shell to edit a board and query it, one command per
+/// line.
+///
+/// Pieces are written with the letters of the table files, lowercase for white and
+/// uppercase for black. Pieces are placed and moved freely, without following the rules of
+/// the game, so that any position can be set up.
+pub struct Repl {
+    /// The board being edited.
+    table: Table,
+    /// The boards before each change, the last one on top.
+    history: Vec<Table>,
+    /// Where the answers of the shell are sent.
+    output: Output,
+}
+
+impl Repl {
+    /// Creates a new shell with an empty board, which sends its answers to `output`.
+    pub fn new(output: Output) -> Repl {
+        Repl::with_table(Table::new(Vec::new(), State::default()), output)
+    }
+
+    /// Creates a new shell editing `table`, which sends its answers to `output`.
+    pub fn with_table(table: Table, output: Output) -> Repl {
+        Repl {
+            table,
+            history: Vec::new(),
+            output,
+        }
+    }
+
+    /// Returns the board being edited.
+    pub fn table(&self) -> &Table {
+        &self.table
+    }
+
+    /// Handles a command of the shell, as listed by `help`.
+    ///
+    /// Commands that cannot be run answer with an error and leave the board untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - The line of the command.
+    ///
+    /// # Returns
+    ///
+    /// `false` if the command was `quit` or `exit`, in which case no more commands should be
+    /// sent.
+    ///
+    pub fn handle_command(&mut self, line: &str) -> bool {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let result = match words.as_slice() {
+            [] => Ok(()),
+            ["place", square, piece] => self.place(square, piece),
+            ["remove", square] => self.remove(square),
+            ["move", from, to] => self.move_piece(from, to),
+            ["print"] => {
+                self.print();
+                Ok(())
+            }
+            ["check"] => {
+                self.check();
+                Ok(())
+            }
+            ["undo"] => self.undo(),
+            ["load", ..] if words.len() > 1 => self.load(&words[1..].join(" ")),
+            ["save", file] => self.save(file),
+            ["help"] => {
+                HELP.iter().for_each(|line| self.send(line));
+                Ok(())
+            }
+            ["quit"] | ["exit"] => return false,
+            [command, ..] if is_command(command) => {
+                Err(format!("ERROR: Invalid arguments: {}", line.trim()))
+            }
+            [command, ..] => Err(format!("ERROR: Unknown command: {}", command)),
+        };
+        if let Err(e) = result {
+            self.send(&e);
+        }
+        true
+    }
+
+    /// Puts a piece on a square, replacing the piece standing there.
+    fn place(&mut self, square: &str, symbol: &str) -> Result<(), String> {
+        let position = square.parse::<Position>()?;
+        let (piece_type, color) = parse_symbol(&self.table, symbol)?;
+        self.history.push(self.table.clone());
+        self.table.place(Piece {
+            piece_type,
+            position,
+            color,
+        });
+        Ok(())
+    }
+
+    /// Removes the piece on a square.
+    fn remove(&mut self, square: &str) -> Result<(), String> {
+        let position = square.parse::<Position>()?;
+        if self.table.piece_at(&position).is_none() {
+            return Err(format!("ERROR: No piece on {}", position));
+        }
+        self.history.push(self.table.clone());
+        self.table.remove(&position);
+        Ok(())
+    }
+
+    /// Moves the piece on a square to another one, replacing the piece standing there.
+    fn move_piece(&mut self, from: &str, to: &str) -> Result<(), String> {
+        let from = from.parse::<Position>()?;
+        let to = to.parse::<Position>()?;
+        let mut piece = match self.table.piece_at(&from) {
+            Some(piece) => piece.clone(),
+            None => return Err(format!("ERROR: No piece on {}", from)),
+        };
+        self.history.push(self.table.clone());
+        self.table.remove(&from);
+        piece.position = to;
+        self.table.place(piece);
+        Ok(())
+    }
+
+    /// Sends the board in the table file format.
+    fn print(&self) {
+        for line in format_table(&self.table) {
+            self.send(&line);
+        }
+    }
+
+    /// Sends whether each color can capture a piece of the other, as given by `check_moves`,
+    /// and the result of the game that `play_game` would give.
+    fn check(&self) {
+        let answer = |can_capture: bool| if can_capture { "yes" } else { "no" };
+        let (white, black) = check_moves(&self.table);
+        self.send(&format!("White can capture: {}", answer(white)));
+        self.send(&format!("Black can capture: {}", answer(black)));
+        self.send(&format!("Result: {}", game_result(&self.table)));
+    }
+
+    /// Takes back the last change to the board.
+    fn undo(&mut self) -> Result<(), String> {
+        match self.history.pop() {
+            Some(table) => {
+                self.table = table;
+                Ok(())
+            }
+            None => Err("ERROR: Nothing to undo".to_string()),
+        }
+    }
+
+    /// Replaces the board with the position of a table file or a FEN.
+    fn load(&mut self, source: &str) -> Result<(), String> {
+        let table = load_position(source)?;
+        self.history.push(std::mem::replace(&mut self.table, table));
+        Ok(())
+    }
+
+    /// Writes the board to a table file, which can be read back by `parse_position`.
+    fn save(&self, file: &str) -> Result<(), String> {
+        let mut contents = format_table(&self.table).join("\n");
+        contents.push('\n');
+        fs::write(file, contents).map_err(|e| format!("ERROR: {}", e))?;
+        self.send(&format!("Saved {}", file));
+        Ok(())
+    }
+
+    /// Sends a line to the user of the shell.
+    fn send(&self, line: &str) {
+        (self.output)(line)
+    }
+}
+
+/// Checks if a word is the name of a command of the shell.
+fn is_command(word: &str) -> bool {
+    HELP.iter()
+        .any(|line| line.split_whitespace().next() == Some(word))
+        || word == "exit"
+}
+
+/// Parses the symbol of a piece as written in table files, such as `d` for a white queen or
+/// `T` for a black rook, with the fairy pieces of the table.
+fn parse_symbol(table: &Table, symbol: &str) -> Result<(PieceType, Color), String> {
+    let invalid = || format!("ERROR: Invalid piece: {}", symbol);
+    let mut chars = symbol.chars();
+    let c = match (chars.next(), chars.next()) {
+        (Some(c), None) => c,
+        _ => return Err(invalid()),
+    };
+    let color = if c.is_ascii_lowercase() {
+        Color::White
+    } else if c.is_ascii_uppercase() {
+        Color::Black
+    } else {
+        return Err(invalid());
+    };
+    let piece_type = table
+        .piece_type_from_symbol(c.to_ascii_lowercase())
+        .ok_or_else(invalid)?;
+    Ok((piece_type, color))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::collect_output;
+
+    #[test]
+    fn test_repl() {
+        use crate::game::table::parse_position;
+        use crate::utils::read_file;
+
+        let (output, lines) = collect_output();
+        let mut repl = Repl::new(output);
+        let last = || lines.lock().unwrap().last().cloned().unwrap_or_default();
+        let answers = |count: usize| {
+            let lines = lines.lock().unwrap();
+            lines[lines.len() - count..].to_vec()
+        };
+
+        // A white queen on d1 and a black rook on d8 can capture each other
+        repl.handle_command("place d1 d");
+        repl.handle_command("place d8 T");
+        repl.handle_command("check");
+        assert_eq!(
+            answers(3),
+            [
+                "White can capture: yes",
+                "Black can capture: yes",
+                "Result: E"
+            ]
+        );
+
+        // Once the queen moves away only a knight can capture, jumping onto it
+        repl.handle_command("move d1 b3");
+        repl.handle_command("place c5 C");
+        repl.handle_command("check");
+        assert_eq!(last(), "Result: N");
+        repl.handle_command("undo");
+        repl.handle_command("remove d8");
+        repl.handle_command("print");
+        assert_eq!(answers(8)[5], "_ d _ _ _ _ _ _");
+        assert_eq!(repl.table().pieces().len(), 1);
+
+        repl.handle_command("remove d8");
+        assert_eq!(last(), "ERROR: No piece on d8");
+        repl.handle_command("place d9 d");
+        assert_eq!(last(), "ERROR: Invalid square: d9");
+        repl.handle_command("place d2 x");
+        assert_eq!(last(), "ERROR: Invalid piece: x");
+        repl.handle_command("move e2");
+        assert_eq!(last(), "ERROR: Invalid arguments: move e2");
+        repl.handle_command("fly");
+        assert_eq!(last(), "ERROR: Unknown command: fly");
+        for _ in 0..5 {
+            repl.handle_command("undo");
+        }
+        assert_eq!(last(), "ERROR: Nothing to undo");
+        assert!(repl.table().pieces().is_empty());
+
+        // A position is saved back to the table file format
+        repl.handle_command("load tables/start.txt");
+        repl.handle_command("move e2 e4");
+        repl.handle_command("remove h1");
+        assert!(!repl.table().state().castling.white_king_side);
+        assert!(repl.table().state().castling.white_queen_side);
+        let file = std::env::temp_dir().join("tp1_rust_repl.txt");
+        repl.handle_command(&format!("save {}", file.display()));
+        assert_eq!(last(), format!("Saved {}", file.display()));
+        let saved = parse_position(&read_file(&file).unwrap()).unwrap();
+        assert_eq!(&saved, repl.table());
+        assert!(!repl.handle_command("quit"));
+    }
+}
@@ -0,0 +1,396 @@
+use crate::game::game_result;
+use crate::game::table::generator::{is_attacked, is_in_check, legal_moves};
+use crate::game::table::moves::{make_move, unmake_move, Move};
+use crate::game::table::notation::{format_san, Language};
+use crate::game::table::piece::position::Position;
+use crate::game::table::piece::{Color, Piece, PieceType};
+use crate::game::table::{check_moves, Table, BOARD_SIZE};
+use crate::protocol::{color_name, game_over, parse_typed_move};
+
+/// The escape sequence that moves the cursor of the terminal home and clears the screen.
+const CLEAR: &str = "\x1b[H\x1b[2J";
+
+/// The escape sequence that resets the colors of the terminal.
+const RESET: &str = "\x1b[0m";
+
+/// The background of the square under the cursor.
+const CURSOR: u8 = 33;
+/// The background of the square of the selected piece.
+const SELECTED: u8 = 220;
+/// The background of the squares the selected piece can move to.
+const TARGET: u8 = 71;
+/// The background of the squares attacked by the opponent of the player to move.
+const ATTACKED: u8 = 167;
+/// The background of the light squares.
+const LIGHT: u8 = 180;
+/// The background of the dark squares.
+const DARK: u8 = 137;
+
+/// The keys of the help line.
+const HELP: &str = "Arrows: cursor  Enter: select or play  Type a move + Enter  Esc: cancel  Ctrl-Z: undo  Ctrl-C: quit";
+
+/// An enum representing the keys a terminal sends that the interface reacts to.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Key {
+    /// The up arrow, which moves the cursor one row up.
+    Up,
+    /// The down arrow.
+    Down,
+    /// The left arrow.
+    Left,
+    /// The right arrow.
+    Right,
+    /// The enter key, which selects the piece under the cursor or plays a move.
+    Enter,
+    /// The backspace key, which erases the last character of a typed move.
+    Backspace,
+    /// The escape key, which cancels the selection and the typed move.
+    Escape,
+    /// Ctrl-Z, which takes back the last move.
+    Undo,
+    /// Ctrl-C or Ctrl-D, which leave the interface.
+    Quit,
+    /// A printable character of a typed move.
+    Char(char),
+}
+
+/// Decodes the bytes read from a terminal without line buffering into keys.
+///
+/// Arrows are read from their escape sequences, such as `ESC [ A` for up. Other escape
+/// sequences and bytes that are not printable are ignored.
+pub fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let key = match bytes[i] {
+            0x1b if bytes.get(i + 1) == Some(&b'[') => {
+                i += 2;
+                match bytes.get(i) {
+                    Some(b'A') => Some(Key::Up),
+                    Some(b'B') => Some(Key::Down),
+                    Some(b'C') => Some(Key::Right),
+                    Some(b'D') => Some(Key::Left),
+                    _ => None,
+                }
+            }
+            0x1b => Some(Key::Escape),
+            b'\r' | b'\n' => Some(Key::Enter),
+            0x7f | 0x08 => Some(Key::Backspace),
+            0x1a => Some(Key::Undo),
+            0x03 | 0x04 => Some(Key::Quit),
+            c if c.is_ascii_graphic() => Some(Key::Char(c as char)),
+            _ => None,
+        };
+        keys.extend(key);
+        i += 1;
+    }
+    keys
+}
+
+/// A struct representing a terminal interface where two players take turns on the same
+/// table, moving a cursor over the board or typing their moves.
+///
+/// Keys are given with `handle_key`, and the screen is drawn with `render`. Typed moves are
+/// read in Standard Algebraic Notation with Spanish or English letters, or in the notation of
+/// the UCI protocol.
+pub struct Tui {
+    /// The table of the game.
+    table: Table,
+    /// The square under the cursor.
+    cursor: Position,
+    /// The square of the piece selected to move, if any.
+    selected: Option<Position>,
+    /// The move being typed.
+    input: String,
+    /// The line telling the last move played or why a move was refused.
+    message: String,
+}
+
+impl Tui {
+    /// Creates a new interface to play on `table`, with the cursor on the e2 square.
+    pub fn new(table: Table) -> Tui {
+        Tui {
+            table,
+            cursor: Position { x: 4, y: 6 },
+            selected: None,
+            input: String::new(),
+            message: String::new(),
+        }
+    }
+
+    /// Returns the table of the game.
+    pub fn table(&self) -> &Table {
+        &self.table
+    }
+
+    /// Handles a key pressed by a player.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key pressed.
+    ///
+    /// # Returns
+    ///
+    /// `false` if the key was `Key::Quit`, in which case the interface should be closed.
+    ///
+    pub fn handle_key(&mut self, key: Key) -> bool {
+        match key {
+            Key::Up => self.move_cursor(0, -1),
+            Key::Down => self.move_cursor(0, 1),
+            Key::Left => self.move_cursor(-1, 0),
+            Key::Right => self.move_cursor(1, 0),
+            Key::Enter if !self.input.is_empty() => {
+                let text = std::mem::take(&mut self.input);
+                self.play_typed(&text);
+            }
+            Key::Enter => self.select_or_play(),
+            Key::Backspace => {
+                self.input.pop();
+            }
+            Key::Escape => {
+                self.input.clear();
+                self.selected = None;
+            }
+            Key::Undo => {
+                self.selected = None;
+                self.message = match unmake_move(&mut self.table) {
+                    Some(_) => "Move taken back".to_string(),
+                    None => "No move to take back".to_string(),
+                };
+            }
+            Key::Quit => return false,
+            Key::Char(c) => self.input.push(c),
+        }
+        true
+    }
+
+    /// Moves the cursor by `(dx, dy)` squares, staying on the board.
+    fn move_cursor(&mut self, dx: isize, dy: isize) {
+        let x = self.cursor.x.saturating_add_signed(dx).min(BOARD_SIZE - 1);
+        let y = self.cursor.y.saturating_add_signed(dy).min(BOARD_SIZE - 1);
+        self.cursor = Position { x, y };
+    }
+
+    /// Selects the piece under the cursor if it belongs to the player to move, or plays the
+    /// move of the selected piece to the square under the cursor. Pawns reaching the last row
+    /// are promoted to a queen.
+    fn select_or_play(&mut self) {
+        let side = self.table.state().side_to_move;
+        if self
+            .table
+            .piece_at(&self.cursor)
+            .is_some_and(|piece| piece.color == side)
+            && self.target_move(&self.cursor).is_none()
+        {
+            self.selected = Some(self.cursor);
+            return;
+        }
+        match self.target_move(&self.cursor) {
+            Some(mv) => self.play(&mv),
+            None => self.selected = None,
+        }
+    }
+
+    /// Returns the legal move of the selected piece to `to`, preferring the promotion to a
+    /// queen when there are several.
+    fn target_move(&self, to: &Position) -> Option<Move> {
+        let from = self.selected?;
+        let moves: Vec<Move> = legal_moves(&self.table)
+            .into_iter()
+            .filter(|mv| mv.drop.is_none() && mv.from == from && mv.to == *to)
+            .collect();
+        moves
+            .iter()
+            .find(|mv| mv.promotion == Some(PieceType::D))
+            .or(moves.first())
+            .copied()
+    }
+
+    /// Plays a typed move, read in Spanish or English Standard Algebraic Notation or in the
+    /// notation of the UCI protocol.
+    fn play_typed(&mut self, text: &str) {
+        match parse_typed_move(&self.table, text) {
+            Ok(mv) => self.play(&mv),
+            Err(e) => self.message = e,
+        }
+    }
+
+    /// Plays a legal move, unless the game is over.
+    fn play(&mut self, mv: &Move) {
+        self.selected = None;
+        if let Some(result) = game_over(&self.table) {
+            self.message = format!("The game is over: {}", result);
+            return;
+        }
+        let san = format_san(&self.table, mv, Language::Spanish);
+        match san.and_then(|san| make_move(&mut self.table, mv).map(|_| san)) {
+            Ok(san) => {
+                let mover = self.table.state().side_to_move.opposite();
+                self.message = format!("{} played {}", color_name(mover), san);
+            }
+            Err(e) => self.message = e,
+        }
+    }
+
+    /// Draws the screen: the board with the highlighted squares, the side to move, the
+    /// capture verdict of `check_moves`, the typed move and the last message.
+    ///
+    /// # Returns
+    ///
+    /// The text of the screen, with the escape sequences of the colors, starting with the one
+    /// clearing the terminal.
+    ///
+    pub fn render(&self) -> String {
+        let side = self.table.state().side_to_move;
+        let targets: Vec<Position> = match self.selected {
+            Some(from) => legal_moves(&self.table)
+                .into_iter()
+                .filter(|mv| mv.drop.is_none() && mv.from == from)
+                .map(|mv| mv.to)
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let mut screen = String::from(CLEAR);
+        screen.push_str("   a  b  c  d  e  f  g  h\r\n");
+        for y in 0..BOARD_SIZE {
+            screen.push_str(&format!("{} ", BOARD_SIZE - y));
+            for x in 0..BOARD_SIZE {
+                let position = Position { x, y };
+                let background = if position == self.cursor {
+                    CURSOR
+                } else if self.selected == Some(position) {
+                    SELECTED
+                } else if targets.contains(&position) {
+                    TARGET
+                } else if is_attacked(&self.table, &position, side.opposite()) {
+                    ATTACKED
+                } else if (x + y) % 2 == 0 {
+                    LIGHT
+                } else {
+                    DARK
+                };
+                let glyph = match self.table.piece_at(&position) {
+                    Some(piece) => format!(
+                        "\x1b[1;{}m{}",
+                        if piece.color == Color::White { 97 } else { 30 },
+                        piece_glyph(piece)
+                    ),
+                    None => " ".to_string(),
+                };
+                screen.push_str(&format!("\x1b[48;5;{}m {} {}", background, glyph, RESET));
+            }
+            screen.push_str(&format!(" {}\r\n", BOARD_SIZE - y));
+        }
+        screen.push_str("   a  b  c  d  e  f  g  h\r\n\r\n");
+
+        let status = match game_over(&self.table) {
+            Some(result) => format!("Game over: {}", result),
+            None if is_in_check(&self.table, side) => {
+                format!("{} to move, in check", color_name(side))
+            }
+            None => format!("{} to move", color_name(side)),
+        };
+        let (white, black) = check_moves(&self.table);
+        let answer = |can_capture: bool| if can_capture { "yes" } else { "no" };
+        screen.push_str(&format!("{}\r\n", status));
+        screen.push_str(&format!(
+            "White can capture: {}  Black can capture: {}  Verdict: {}\r\n",
+            answer(white),
+            answer(black),
+            game_result(&self.table)
+        ));
+        screen.push_str(&format!("Move: {}\r\n", self.input));
+        screen.push_str(&format!("{}\r\n", self.message));
+        screen.push_str(HELP);
+        screen.push_str("\r\n");
+        screen
+    }
+}
+
+/// Returns the character drawn for a piece: the chess symbol of its type, filled for both
+/// colors so that the color of the text tells them apart, or the uppercase symbol of a
+/// registered kind.
+fn piece_glyph(piece: &Piece) -> char {
+    match piece.piece_type {
+        PieceType::R => '♚',
+        PieceType::D => '♛',
+        PieceType::T => '♜',
+        PieceType::A => '♝',
+        PieceType::C => '♞',
+        PieceType::P => '♟',
+        _ => piece.symbol().to_ascii_uppercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::table::fen::{parse_fen, START_FEN};
+
+    fn setup_tui() -> Tui {
+        Tui::new(parse_fen(START_FEN).unwrap())
+    }
+
+    fn type_move(tui: &mut Tui, text: &str) {
+        for c in text.chars() {
+            tui.handle_key(Key::Char(c));
+        }
+        tui.handle_key(Key::Enter);
+    }
+
+    #[test]
+    fn test_parse_keys() {
+        assert_eq!(
+            parse_keys(b"\x1b[A\x1b[D\re4\x7f\x1b\x1a\x03"),
+            vec![
+                Key::Up,
+                Key::Left,
+                Key::Enter,
+                Key::Char('e'),
+                Key::Char('4'),
+                Key::Backspace,
+                Key::Escape,
+                Key::Undo,
+                Key::Quit,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tui() {
+        // The cursor starts on e2: selecting the pawn shows its two moves, and the second
+        // Enter plays e2-e4
+        let mut tui = setup_tui();
+        tui.handle_key(Key::Enter);
+        assert_eq!(tui.selected, Some(Position { x: 4, y: 6 }));
+        let screen = tui.render();
+        assert_eq!(screen.matches(&format!("48;5;{}m", TARGET)).count(), 2);
+        assert!(screen.contains("White to move"));
+        tui.handle_key(Key::Up);
+        tui.handle_key(Key::Up);
+        tui.handle_key(Key::Enter);
+        assert_eq!(tui.message, "White played e4");
+        assert!(tui.render().contains("Black to move"));
+
+        // Moves can be typed with either language, and illegal ones are refused
+        type_move(&mut tui, "Nf6");
+        type_move(&mut tui, "e6");
+        assert_eq!(tui.message, "ERROR: Illegal move: e6");
+        type_move(&mut tui, "d2d4");
+        assert_eq!(tui.message, "White played d4");
+        tui.handle_key(Key::Undo);
+        assert_eq!(tui.table().state().side_to_move, Color::White);
+
+        // Fool's mate ends the game, and no more moves are played
+        let mut tui = setup_tui();
+        for mv in ["f3", "e5", "g4", "Dh4"] {
+            type_move(&mut tui, mv);
+        }
+        assert!(tui.render().contains("Game over: Black wins by checkmate"));
+        type_move(&mut tui, "a3");
+        assert!(tui.message.starts_with("ERROR: Illegal move"));
+        assert!(tui.handle_key(Key::Escape));
+        assert!(!tui.handle_key(Key::Quit));
+    }
+}
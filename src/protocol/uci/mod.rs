@@ -0,0 +1,455 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::game::table::fen::parse_fen;
+use crate::game::table::generator::legal_moves;
+use crate::game::table::notation::{format_uci, parse_uci};
+use crate::game::table::piece::Color;
+use crate::game::table::search::{mate_in, search_with, SearchLimits, SearchResult};
+use crate::game::table::state::{CastlingFiles, State};
+use crate::game::table::Table;
+use crate::protocol::{play_move, start_table, time_for_move, Output};
+
+/// The name the engine introduces itself with.
+pub const ENGINE_NAME: &str = "tp1_rust";
+
+/// A struct representing a search running in the background.
+struct RunningSearch {
+    /// The flag that stops the search.
+    stop: Arc<AtomicBool>,
+    /// The thread running the search, which sends the best move once it is done.
+    handle: JoinHandle<()>,
+    /// The time given to the move when the search is pondering, which only starts running
+    /// on `ponderhit`.
+    ponder_time: Option<Duration>,
+}
+
+/// A struct representing an engine driven by the Universal Chess Interface protocol.
+///
+/// Commands are given one line at a time with `handle_command`. Searches run in a background
+/// thread, so that commands such as `stop` and `isready` are answered while the engine is
+/// thinking.
+pub struct Uci {
+    /// The position set by the last `position` command.
+    table: Table,
+    /// Where the answers of the engine are sent.
+    output: Output,
+    /// The search started by the last `go` command, if it was not waited for yet.
+    search: Option<RunningSearch>,
+    /// Whether positions are played with the Chess960 castling rules, set with the
+    /// `UCI_Chess960` option.
+    chess960: bool,
+}
+
+impl Uci {
+    /// Creates a new engine set up at the start position, which sends its answers to
+    /// `output`.
+    pub fn new(output: Output) -> Uci {
+        Uci {
+            table: start_table(),
+            output,
+            search: None,
+            chess960: false,
+        }
+    }
+
+    /// Handles a command sent to the engine.
+    ///
+    /// Unknown commands are ignored, as the protocol requires, and invalid commands are
+    /// answered with an `info string` describing the error.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - The line of the command, such as `position startpos moves e2e4` or
+    ///   `go movetime 1000`.
+    ///
+    /// # Returns
+    ///
+    /// `false` if the command was `quit`, in which case no more commands should be sent.
+    ///
+    pub fn handle_command(&mut self, line: &str) -> bool {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let result = match words.first() {
+            Some(&"uci") => {
+                (self.output)(&format!("id name {}", ENGINE_NAME));
+                (self.output)("id author the tp1_rust authors");
+                (self.output)("option name UCI_Chess960 type check default false");
+                (self.output)("uciok");
+                Ok(())
+            }
+            Some(&"isready") => {
+                (self.output)("readyok");
+                Ok(())
+            }
+            Some(&"setoption") => self.set_option(&words[1..]),
+            Some(&"ucinewgame") => {
+                self.stop();
+                self.table = start_table();
+                Ok(())
+            }
+            Some(&"position") => {
+                self.stop();
+                parse_position_command(&words[1..], self.chess960).map(|table| self.table = table)
+            }
+            Some(&"go") => {
+                self.stop();
+                parse_go_command(&self.table, &words[1..])
+                    .map(|(limits, ponder)| self.go(limits, ponder))
+            }
+            Some(&"ponderhit") => {
+                self.ponderhit();
+                Ok(())
+            }
+            Some(&"stop") => {
+                self.stop();
+                Ok(())
+            }
+            Some(&"quit") => {
+                self.stop();
+                return false;
+            }
+            _ => Ok(()),
+        };
+        if let Err(e) = result {
+            (self.output)(&format!("info string {}", e));
+        }
+        true
+    }
+
+    /// Sets an option from the arguments of a `setoption` command, such as
+    /// `name UCI_Chess960 value true`. Unknown options are ignored.
+    fn set_option(&mut self, words: &[&str]) -> Result<(), String> {
+        let value_at = words
+            .iter()
+            .position(|word| *word == "value")
+            .unwrap_or(words.len());
+        let name = words[..value_at]
+            .iter()
+            .skip(1)
+            .copied()
+            .collect::<Vec<&str>>();
+        let value = words.get(value_at + 1).copied().unwrap_or("");
+        if name.join(" ") == "UCI_Chess960" {
+            self.chess960 = match value {
+                "true" => true,
+                "false" => false,
+                _ => return Err(format!("ERROR: Invalid value for UCI_Chess960: {}", value)),
+            };
+        }
+        Ok(())
+    }
+
+    /// Waits for the running search, if any, to send its best move.
+    pub fn wait(&mut self) {
+        if let Some(search) = self.search.take() {
+            let _ = search.handle.join();
+        }
+    }
+
+    /// Stops the running search, if any, and waits for it to send its best move.
+    fn stop(&mut self) {
+        if let Some(search) = &self.search {
+            search.stop.store(true, Ordering::Relaxed);
+        }
+        self.wait();
+    }
+
+    /// Lets a pondering search go on as the search of the move to play, stopping it once
+    /// the time given to the move has run out since the `ponderhit`.
+    fn ponderhit(&mut self) {
+        let Some(search) = &mut self.search else {
+            return;
+        };
+        if let Some(time) = search.ponder_time.take() {
+            let stop = Arc::clone(&search.stop);
+            thread::spawn(move || {
+                thread::sleep(time);
+                stop.store(true, Ordering::Relaxed);
+            });
+        }
+    }
+
+    /// Starts searching the current position in a background thread.
+    ///
+    /// An `info` line is sent after each depth searched, and a `bestmove` line once the
+    /// search is over. `bestmove 0000` is sent when there are no legal moves. With `ponder`,
+    /// the time limit only applies from the `ponderhit`.
+    fn go(&mut self, mut limits: SearchLimits, ponder: bool) {
+        let ponder_time = if ponder { limits.time.take() } else { None };
+        let table = self.table.clone();
+        let output = Arc::clone(&self.output);
+        let stop = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&stop);
+        let handle = thread::spawn(move || {
+            let start = Instant::now();
+            let result = search_with(&table, &limits, &flag, |result| {
+                output(&format_info(result, start.elapsed()))
+            });
+            let best = result
+                .best_move
+                .map_or("0000".to_string(), |mv| format_uci(&mv));
+            output(&format!("bestmove {}", best));
+        });
+        self.search = Some(RunningSearch {
+            stop,
+            handle,
+            ponder_time,
+        });
+    }
+}
+
+/// Parses the arguments of a `position` command, such as `startpos moves e2e4 e7e5` or
+/// `fen <fen> moves e7e8q`, into the table they describe.
+///
+/// With `chess960`, castlings are written as the king moving onto its rook, even from the
+/// standard squares.
+///
+/// # Errors
+///
+/// Returns an error if the position is invalid or one of the moves is illegal.
+///
+fn parse_position_command(words: &[&str], chess960: bool) -> Result<Table, String> {
+    let moves_at = words
+        .iter()
+        .position(|word| *word == "moves")
+        .unwrap_or(words.len());
+    let mut table = match words.first() {
+        Some(&"startpos") => start_table(),
+        Some(&"fen") => parse_fen(&words[1..moves_at].join(" "))?,
+        _ => return Err("ERROR: Position should be startpos or fen".to_string()),
+    };
+    if chess960 && table.state().chess960.is_none() {
+        let state = State {
+            chess960: Some(CastlingFiles::STANDARD),
+            ..*table.state()
+        };
+        table = Table::new(table.pieces().to_vec(), state);
+    }
+    for word in words.iter().skip(moves_at + 1) {
+        play_move(&mut table, word)?;
+    }
+    Ok(table)
+}
+
+/// Parses the arguments of a `go` command into the limits of the search.
+///
+/// A fixed time is taken from `movetime`, or else a share of the clock of the player to move
+/// is taken from `wtime`/`btime`, `winc`/`binc` and `movestogo`. Any missing limit leaves
+/// the search unlimited, and so does the `infinite` flag, which searches until `stop`. The
+/// search is restricted to the moves listed after `searchmoves`.
+///
+/// # Returns
+///
+/// The limits of the search, and whether it ponders, given by the `ponder` flag.
+///
+/// # Errors
+///
+/// Returns an error if the value of a limit is not a number, or a move of `searchmoves` is
+/// not legal.
+///
+fn parse_go_command(table: &Table, words: &[&str]) -> Result<(SearchLimits, bool), String> {
+    let mut limits = SearchLimits::default();
+    let (mut remaining, mut increment, mut moves_to_go) = (None, Duration::ZERO, None);
+    let (time_key, increment_key) = match table.state().side_to_move {
+        Color::Black => ("btime", "binc"),
+        _ => ("wtime", "winc"),
+    };
+    let (mut unlimited, mut ponder) = (false, false);
+    let mut i = 0;
+    while i < words.len() {
+        let key = words[i];
+        match key {
+            "infinite" => {
+                unlimited = true;
+                i += 1;
+                continue;
+            }
+            "ponder" => {
+                ponder = true;
+                i += 1;
+                continue;
+            }
+            "searchmoves" => {
+                let legal = legal_moves(table);
+                let mut root_moves = Vec::new();
+                i += 1;
+                while let Some(Ok(mv)) = words.get(i).map(|word| parse_uci(word)) {
+                    if !legal.contains(&mv) {
+                        return Err(format!("ERROR: Illegal move: {}", words[i]));
+                    }
+                    root_moves.push(mv);
+                    i += 1;
+                }
+                if !root_moves.is_empty() {
+                    limits.root_moves = Some(root_moves);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        let value = match words.get(i + 1).map(|value| value.parse::<u64>()) {
+            Some(Ok(value)) => value,
+            _ => return Err(format!("ERROR: Invalid value for {}", key)),
+        };
+        match key {
+            "depth" => limits.depth = Some(value as usize),
+            "movetime" => limits.time = Some(Duration::from_millis(value)),
+            "movestogo" => moves_to_go = Some(value as u32),
+            key if key == time_key => remaining = Some(Duration::from_millis(value)),
+            key if key == increment_key => increment = Duration::from_millis(value),
+            _ => {}
+        }
+        i += 2;
+    }
+    if unlimited {
+        limits.time = None;
+    } else if let (None, Some(remaining)) = (limits.time, remaining) {
+        limits.time = Some(time_for_move(remaining, increment, moves_to_go));
+    }
+    Ok((limits, ponder))
+}
+
+/// Formats the result of a depth as an `info` line.
+fn format_info(result: &SearchResult, elapsed: Duration) -> String {
+    let score = match mate_in(result.score) {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", result.score),
+    };
+    let pv: Vec<String> = result.principal_variation.iter().map(format_uci).collect();
+    format!(
+        "info depth {} score {} nodes {} time {} pv {}",
+        result.depth,
+        score,
+        result.nodes,
+        elapsed.as_millis(),
+        pv.join(" ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::table::fen::format_fen;
+    use crate::protocol::collect_output;
+
+    #[test]
+    fn test_uci() {
+        let (output, lines) = collect_output();
+        let mut uci = Uci::new(output);
+        assert!(uci.handle_command("uci"));
+        assert!(uci.handle_command("isready"));
+        assert_eq!(lines.lock().unwrap().last().unwrap(), "readyok");
+        assert!(lines.lock().unwrap().contains(&"uciok".to_string()));
+
+        // Mate in one on the back row
+        uci.handle_command("position fen 6k1/5ppp/8/8/8/8/8/2K1R3 w - - 0 1");
+        uci.handle_command("go depth 3");
+        uci.wait();
+        let answers = lines.lock().unwrap().clone();
+        assert_eq!(answers.last().unwrap(), "bestmove e1e8");
+        assert!(answers
+            .iter()
+            .any(|line| line.starts_with("info depth 2 score mate 1 ")));
+
+        // Moves are played from the start position
+        uci.handle_command("position startpos moves f2f3 e7e5 g2g4");
+        uci.handle_command("go depth 2");
+        uci.wait();
+        assert_eq!(lines.lock().unwrap().last().unwrap(), "bestmove d8h4");
+
+        uci.handle_command("position startpos moves e2e5");
+        assert_eq!(
+            lines.lock().unwrap().last().unwrap(),
+            "info string ERROR: Illegal move: e2e5"
+        );
+
+        // With Chess960 castling, the king castles by moving onto its rook
+        let answered = lines.lock().unwrap().len();
+        uci.handle_command("setoption name UCI_Chess960 value true");
+        uci.handle_command("position fen 4k3/8/8/8/8/8/8/4K2R w K - 0 1 moves e1h1");
+        assert_eq!(lines.lock().unwrap().len(), answered);
+        assert_eq!(format_fen(&uci.table), "4k3/8/8/8/8/8/8/5RK1 b - - 1 1");
+        uci.handle_command("position fen 4k3/8/8/8/8/8/8/4K2R w K - 0 1 moves e1g1");
+        assert_eq!(
+            lines.lock().unwrap().last().unwrap(),
+            "info string ERROR: Illegal move: e1g1"
+        );
+        uci.handle_command("go depth x");
+        assert_eq!(
+            lines.lock().unwrap().last().unwrap(),
+            "info string ERROR: Invalid value for depth"
+        );
+        assert!(!uci.handle_command("quit"));
+    }
+
+    #[test]
+    fn test_uci_stop() {
+        let (output, lines) = collect_output();
+        let mut uci = Uci::new(output);
+        uci.handle_command("position startpos");
+        uci.handle_command("go infinite");
+        thread::sleep(Duration::from_millis(100));
+        uci.handle_command("stop");
+        assert!(lines
+            .lock()
+            .unwrap()
+            .last()
+            .unwrap()
+            .starts_with("bestmove "));
+
+        let table = start_table();
+        let (limits, _) = parse_go_command(&table, &["wtime", "30000", "btime", "10"]).unwrap();
+        assert_eq!(limits.time, Some(Duration::from_secs(1)));
+        let (limits, _) = parse_go_command(&table, &["movetime", "200", "wtime", "30000"]).unwrap();
+        assert_eq!(limits.time, Some(Duration::from_millis(200)));
+
+        // Flags take no value, and the moves to search are read up to the next limit
+        let (limits, ponder) =
+            parse_go_command(&table, &["ponder", "wtime", "30000", "btime", "10"]).unwrap();
+        assert!(ponder);
+        assert_eq!(limits.time, Some(Duration::from_secs(1)));
+        let (limits, ponder) = parse_go_command(&table, &["infinite", "depth", "3"]).unwrap();
+        assert_eq!((limits.depth, limits.time, ponder), (Some(3), None, false));
+        let (limits, _) =
+            parse_go_command(&table, &["searchmoves", "e2e4", "g1f3", "depth", "2"]).unwrap();
+        assert_eq!(limits.depth, Some(2));
+        assert_eq!(
+            limits.root_moves,
+            Some(vec![parse_uci("e2e4").unwrap(), parse_uci("g1f3").unwrap()])
+        );
+        assert_eq!(
+            parse_go_command(&table, &["searchmoves", "e2e5"]),
+            Err("ERROR: Illegal move: e2e5".to_string())
+        );
+        assert_eq!(
+            parse_go_command(&table, &["searchmoves", "depth"]),
+            Err("ERROR: Invalid value for depth".to_string())
+        );
+
+        // Only the moves listed are searched, even if another one is better
+        uci.handle_command("go searchmoves d2d4 depth 2");
+        uci.wait();
+        assert_eq!(lines.lock().unwrap().last().unwrap(), "bestmove d2d4");
+
+        // Pondering goes on until the expected move is played, and then for the time of the
+        // move
+        let best_moves = || {
+            let lines = lines.lock().unwrap();
+            lines
+                .iter()
+                .filter(|line| line.starts_with("bestmove "))
+                .count()
+        };
+        uci.handle_command("go ponder wtime 3000 btime 3000");
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(best_moves(), 2);
+        let hit = Instant::now();
+        uci.handle_command("ponderhit");
+        assert_eq!(best_moves(), 2);
+        uci.wait();
+        assert_eq!(best_moves(), 3);
+        assert!(hit.elapsed() >= Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,384 @@
+use std::fs;
+
+use crate::game::table::moves::{make_move, played_moves, unmake_move};
+use crate::game::table::notation::{format_san, Language};
+use crate::game::table::pgn::{format_pgn, pgn_result};
+use crate::game::table::piece::Color;
+use crate::game::table::search::{search, SearchLimits};
+use crate::game::table::{format_table, Table};
+use crate::protocol::uci::ENGINE_NAME;
+use crate::protocol::{color_name, game_over, parse_typed_move, Output};
+
+/// The commands besides moves, as listed by `help`.
+const HELP: [&str; 8] = [
+    "<move>        plays a move, such as `Cf3`, `Nf3` or `g1f3`",
+    "takeback      takes back your last move and the answer of the engine",
+    "depth <n>     makes the engine search <n> moves ahead",
+    "time <ms>     makes the engine think for <ms> milliseconds",
+    "board         prints the board",
+    "resign        gives up the game",
+    "help          lists the commands",
+    "quit          leaves the game",
+];
+
+/// A struct representing a game between a user and the engine, driven by commands typed one
+/// line at a time.
+///
+/// The engine answers each move of the user, searching with the depth or time set with the
+/// `depth` and `time` commands. When the game ends, by the rules or by resigning, it is
+/// saved in Portable Game Notation to the file given when it was created, if any, as it is
+/// when the user quits before its end.
+pub struct Versus {
+    /// The table of the game.
+    table: Table,
+    /// The color the user plays.
+    user: Color,
+    /// The limits of the searches of the engine.
+    limits: SearchLimits,
+    /// The file the game is saved to when it ends.
+    pgn_file: Option<String>,
+    /// The result of the game once it is over.
+    result: Option<&'static str>,
+    /// Where the answers are sent.
+    output: Output,
+}
+
+impl Versus {
+    /// Creates a new game on `table` where the user plays `user`, which sends its answers to
+    /// `output`.
+    ///
+    /// # Arguments
+    ///
+    /// * `table` - The table the game starts from.
+    /// * `user` - The color the user plays, while the engine plays the other one.
+    /// * `limits` - The depth and time limits of the searches of the engine.
+    /// * `pgn_file` - The file the game is saved to when it ends, if any.
+    /// * `output` - Where the answers are sent.
+    ///
+    pub fn new(
+        table: Table,
+        user: Color,
+        limits: SearchLimits,
+        pgn_file: Option<String>,
+        output: Output,
+    ) -> Versus {
+        Versus {
+            table,
+            user,
+            limits,
+            pgn_file,
+            result: None,
+            output,
+        }
+    }
+
+    /// Returns the table of the game.
+    pub fn table(&self) -> &Table {
+        &self.table
+    }
+
+    /// Returns the result of the game, such as `1-0`, once it is over.
+    pub fn result(&self) -> Option<&'static str> {
+        self.result
+    }
+
+    /// Starts the game: prints the board, and lets the engine move first if it is its turn.
+    pub fn start(&mut self) {
+        self.print_board();
+        if !self.check_end() && self.table.state().side_to_move != self.user {
+            self.engine_move();
+        }
+    }
+
+    /// Handles a command of the user, as listed by `help`, or a move in Standard Algebraic
+    /// Notation with Spanish or English letters, or in the notation of the UCI protocol.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - The line of the command.
+    ///
+    /// # Returns
+    ///
+    /// `false` if the command was `quit`, in which case no more commands should be sent.
+    ///
+    pub fn handle_command(&mut self, line: &str) -> bool {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] => {}
+            ["takeback"] | ["undo"] => self.takeback(),
+            ["depth", n] => match n.parse::<usize>() {
+                Ok(depth) if depth > 0 => {
+                    self.limits = SearchLimits {
+                        depth: Some(depth),
+                        time: None,
+                        root_moves: None,
+                    };
+                }
+                _ => self.send(&format!("ERROR: Invalid number: {}", n)),
+            },
+            ["time", ms] => match ms.parse::<u64>() {
+                Ok(ms) if ms > 0 => {
+                    self.limits = SearchLimits {
+                        depth: None,
+                        time: Some(std::time::Duration::from_millis(ms)),
+                        root_moves: None,
+                    };
+                }
+                _ => self.send(&format!("ERROR: Invalid number: {}", ms)),
+            },
+            ["board"] => self.print_board(),
+            ["resign"] => {
+                if self.result.is_none() {
+                    let winner = self.user.opposite();
+                    self.send(&format!("{} resigns", color_name(self.user)));
+                    self.finish(if winner == Color::White { "1-0" } else { "0-1" });
+                }
+            }
+            ["help"] => HELP.iter().for_each(|line| self.send(line)),
+            ["quit"] => {
+                // A game left before its end is saved as unfinished
+                if self.result.is_none() && !played_moves(&self.table).is_empty() {
+                    self.save("*");
+                }
+                return false;
+            }
+            [text] => self.user_move(text),
+            _ => self.send(&format!("ERROR: Unknown command: {}", line.trim())),
+        }
+        true
+    }
+
+    /// Plays a move of the user and the answer of the engine.
+    fn user_move(&mut self, text: &str) {
+        if self.result.is_some() {
+            self.send("ERROR: The game is over");
+            return;
+        }
+        if self.table.state().side_to_move != self.user {
+            self.send("ERROR: It is not your turn");
+            return;
+        }
+        match parse_typed_move(&self.table, text).and_then(|mv| make_move(&mut self.table, &mv)) {
+            Ok(()) => {
+                if !self.check_end() {
+                    self.engine_move();
+                }
+            }
+            Err(e) => self.send(&e),
+        }
+    }
+
+    /// Searches the move of the engine, plays it and prints the board.
+    ///
+    /// If the engine cannot play, the error is sent and the game ends unfinished, since the
+    /// user could not move either.
+    fn engine_move(&mut self) {
+        let result = search(&self.table, &self.limits);
+        let played = result
+            .best_move
+            .ok_or_else(|| "ERROR: The engine found no move to play".to_string())
+            .and_then(|mv| {
+                let san = format_san(&self.table, &mv, Language::Spanish)?;
+                make_move(&mut self.table, &mv)?;
+                Ok(san)
+            });
+        match played {
+            Ok(san) => {
+                self.send(&format!("{} plays {}", ENGINE_NAME, san));
+                self.print_board();
+                self.check_end();
+            }
+            Err(e) => {
+                self.send(&e);
+                self.finish("*");
+            }
+        }
+    }
+
+    /// Takes back the last move of the user and the answer of the engine that followed it.
+    fn takeback(&mut self) {
+        let mut table = self.table.clone();
+        loop {
+            if unmake_move(&mut table).is_none() {
+                self.send("ERROR: No move to take back");
+                return;
+            }
+            if table.state().side_to_move == self.user {
+                break;
+            }
+        }
+        self.table = table;
+        self.result = None;
+        self.print_board();
+    }
+
+    /// Announces and saves the result of the game if it is over by the rules.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the game is over.
+    ///
+    fn check_end(&mut self) -> bool {
+        match (game_over(&self.table), pgn_result(&self.table)) {
+            (Some(reason), Some(result)) => {
+                self.send(&format!("Game over: {}", reason));
+                self.finish(result);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Records the result of the game and saves it in Portable Game Notation.
+    fn finish(&mut self, result: &'static str) {
+        self.result = Some(result);
+        self.send(&format!("Result: {}", result));
+        self.save(result);
+    }
+
+    /// Saves the game in Portable Game Notation with the given result, if it has a file.
+    fn save(&self, result: &str) {
+        let file = match &self.pgn_file {
+            Some(file) => file.clone(),
+            None => return,
+        };
+        let (white, black) = match self.user {
+            Color::White => ("User", ENGINE_NAME),
+            _ => (ENGINE_NAME, "User"),
+        };
+        let tags = [
+            ("Event", "Game against the engine"),
+            ("White", white),
+            ("Black", black),
+        ];
+        match format_pgn(&self.table, &tags, result)
+            .and_then(|pgn| fs::write(&file, pgn).map_err(|e| format!("ERROR: {}", e)))
+        {
+            Ok(()) => self.send(&format!("Game saved to {}", file)),
+            Err(e) => self.send(&e),
+        }
+    }
+
+    /// Prints the board in the table file format, followed by the player to move.
+    fn print_board(&self) {
+        for line in format_table(&self.table) {
+            self.send(&line);
+        }
+        let side = self.table.state().side_to_move;
+        let player = if side == self.user {
+            "you"
+        } else {
+            ENGINE_NAME
+        };
+        self.send(&format!("{} to move ({})", color_name(side), player));
+    }
+
+    /// Sends a line to the user.
+    fn send(&self, line: &str) {
+        (self.output)(line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::game::table::fen::{parse_fen, START_FEN};
+    use crate::protocol::collect_output;
+
+    /// Creates a game from `fen` whose answers are collected in the returned vector.
+    fn setup_versus(
+        fen: &str,
+        user: Color,
+        pgn_file: Option<String>,
+    ) -> (Versus, Arc<Mutex<Vec<String>>>) {
+        let (output, lines) = collect_output();
+        let limits = SearchLimits {
+            depth: Some(2),
+            time: None,
+            root_moves: None,
+        };
+        let versus = Versus::new(parse_fen(fen).unwrap(), user, limits, pgn_file, output);
+        (versus, lines)
+    }
+
+    #[test]
+    fn test_versus() {
+        // The engine opens as white, and answers each move of the user
+        let (mut versus, lines) = setup_versus(START_FEN, Color::Black, None);
+        let last = || lines.lock().unwrap().last().cloned().unwrap_or_default();
+        versus.start();
+        assert_eq!(played_moves(versus.table()).len(), 1);
+        assert!(last().ends_with("(you)"));
+        versus.handle_command("Cf6");
+        assert_eq!(played_moves(versus.table()).len(), 3);
+        versus.handle_command("Cf6");
+        assert!(last().starts_with("ERROR: Illegal move"));
+
+        // A takeback undoes the move of the user and the answer, but not the opening move
+        versus.handle_command("takeback");
+        assert_eq!(played_moves(versus.table()).len(), 1);
+        versus.handle_command("takeback");
+        assert_eq!(last(), "ERROR: No move to take back");
+        assert_eq!(played_moves(versus.table()).len(), 1);
+        versus.handle_command("depth x");
+        assert_eq!(last(), "ERROR: Invalid number: x");
+    }
+
+    #[test]
+    fn test_versus_pgn() {
+        // The user mates at once, and the game is saved
+        let file = std::env::temp_dir().join("tp1_rust_versus.pgn");
+        let (mut versus, lines) = setup_versus(
+            "6k1/5ppp/8/8/8/8/8/K3R3 w - - 0 1",
+            Color::White,
+            Some(file.display().to_string()),
+        );
+        versus.start();
+        versus.handle_command("Te8");
+        assert_eq!(versus.result(), Some("1-0"));
+        assert!(lines
+            .lock()
+            .unwrap()
+            .contains(&"Game over: White wins by checkmate".to_string()));
+        let pgn = fs::read_to_string(&file).unwrap();
+        assert!(pgn.contains("[White \"User\"]"));
+        assert!(pgn.ends_with("1. Re8# 1-0\n"));
+        versus.handle_command("a3");
+        assert_eq!(
+            lines.lock().unwrap().last().unwrap(),
+            "ERROR: The game is over"
+        );
+
+        // Resigning ends the game as well
+        let (mut versus, _) = setup_versus(START_FEN, Color::White, None);
+        versus.handle_command("resign");
+        assert_eq!(versus.result(), Some("0-1"));
+    }
+
+    #[test]
+    fn test_versus_engine_error() {
+        // An engine allowed no move cannot answer, which ends the game instead of waiting
+        let (output, lines) = collect_output();
+        let limits = SearchLimits {
+            depth: Some(2),
+            time: None,
+            root_moves: Some(Vec::new()),
+        };
+        let table = parse_fen(START_FEN).unwrap();
+        let mut versus = Versus::new(table, Color::White, limits, None, output);
+        versus.handle_command("e4");
+        assert_eq!(versus.result(), Some("*"));
+        assert_eq!(played_moves(versus.table()).len(), 1);
+        assert!(lines
+            .lock()
+            .unwrap()
+            .contains(&"ERROR: The engine found no move to play".to_string()));
+        versus.handle_command("d4");
+        assert_eq!(
+            lines.lock().unwrap().last().unwrap(),
+            "ERROR: The game is over"
+        );
+    }
+}
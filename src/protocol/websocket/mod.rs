@@ -0,0 +1,986 @@
+use std::collections::HashMap;
+use std::io::{self, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::atomic::AtomicUsize;
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+
+use crate::game::table::moves::{make_move, played_moves};
+use crate::game::table::notation::format_uci;
+use crate::game::table::pgn::pgn_result;
+use crate::game::table::piece::Color;
+use crate::game::table::Table;
+use crate::protocol::http::{
+    analysis, board_from_json, color_json, read_head, respond, spawn_connection, Response,
+};
+use crate::protocol::json::{parse_json, Json};
+use crate::protocol::{color_name, game_over, parse_typed_move, start_table, Output};
+
+/// The largest message accepted from a client, in bytes.
+pub const MAX_MESSAGE: usize = 64 * 1024;
+
+/// The most frames waiting to be sent to a client. A client that lets more pile up, by not
+/// reading its connection, is disconnected rather than holding up the other ones.
+pub const MAX_QUEUED: usize = 256;
+
+/// The string appended to the key of a handshake before hashing it, fixed by RFC 6455.
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// The opcodes of the frames.
+const CONTINUATION: u8 = 0x0;
+const TEXT: u8 = 0x1;
+const BINARY: u8 = 0x2;
+const CLOSE: u8 = 0x8;
+const PING: u8 = 0x9;
+const PONG: u8 = 0xa;
+
+/// The status codes sent when closing a connection.
+const CLOSE_PROTOCOL_ERROR: u16 = 1002;
+const CLOSE_TOO_BIG: u16 = 1009;
+
+/// An enum representing the part a client takes in a game.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Role {
+    /// The client plays a color.
+    Player(Color),
+    /// The client watches the game.
+    Spectator,
+}
+
+/// A struct representing a client connected to the hub.
+struct Client {
+    /// Where the messages to the client are sent.
+    output: Output,
+    /// The game the client joined and its part in it, if any.
+    game: Option<(String, Role)>,
+}
+
+/// A struct representing a live game hosted by the hub.
+struct Game {
+    /// The table of the game.
+    table: Table,
+    /// The client playing white, if any.
+    white: Option<usize>,
+    /// The client playing black, if any.
+    black: Option<usize>,
+    /// The clients watching the game.
+    spectators: Vec<usize>,
+    /// The color that offered a draw, until the offer is answered or a move is played.
+    draw_offer: Option<Color>,
+    /// The result of the game once it is over, and the reason, such as `White resigns`.
+    result: Option<(&'static str, String)>,
+}
+
+impl Game {
+    /// Returns the seat of a color.
+    fn seat(&mut self, color: Color) -> &mut Option<usize> {
+        match color {
+            Color::White => &mut self.white,
+            _ => &mut self.black,
+        }
+    }
+
+    /// Returns every client of the game, players and spectators.
+    fn clients(&self) -> Vec<usize> {
+        self.white
+            .iter()
+            .chain(self.black.iter())
+            .chain(self.spectators.iter())
+            .copied()
+            .collect()
+    }
+}
+
+/// A struct hosting live games between connected clients, which exchange JSON messages.
+///
+/// The messages of the clients have a `"type"`:
+///
+/// * `join` - Joins the game named by `"game"`, creating it if it does not exist, from the
+///   board given as `"table"` or `"fen"` as in `/analyze`, or from the start position. The
+///   `"role"` may be `white`, `black` or `spectator`; without it the client takes the first
+///   free seat, or watches.
+/// * `move` - Plays the `"move"`, in Standard Algebraic Notation with Spanish or English
+///   letters, or in the notation of the UCI protocol.
+/// * `resign` - Gives up the game.
+/// * `draw` - Offers a draw, or accepts the one offered by the opponent.
+/// * `decline` - Declines the draw offered by the opponent.
+///
+/// After each change, every client of the game gets its `state`: the analysis of the board,
+/// with the results of `check_moves` and the verdict, the moves played in UCI notation, the
+/// seats taken, the draw offer and the result. Errors are sent only to the client that caused
+/// them, as `{"type": "error", "message": "ERROR: ..."}`.
+#[derive(Default)]
+pub struct Hub {
+    /// The connected clients, by their identifiers.
+    clients: HashMap<usize, Client>,
+    /// The games being played, by their names.
+    games: HashMap<String, Game>,
+    /// The identifier of the next client to connect.
+    next_client: usize,
+}
+
+impl Hub {
+    /// Creates a hub without clients or games.
+    pub fn new() -> Hub {
+        Hub::default()
+    }
+
+    /// Connects a new client, whose messages are sent to `output`.
+    ///
+    /// # Returns
+    ///
+    /// The identifier of the client, to pass to `handle_message` and `disconnect`.
+    ///
+    pub fn connect(&mut self, output: Output) -> usize {
+        let id = self.next_client;
+        self.next_client += 1;
+        self.clients.insert(id, Client { output, game: None });
+        id
+    }
+
+    /// Disconnects a client, freeing its seat for another one. A game left without clients
+    /// is removed.
+    pub fn disconnect(&mut self, client: usize) {
+        self.leave(client);
+        self.clients.remove(&client);
+    }
+
+    /// Handles a message of a client, as described in `Hub`.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - The identifier of the client, as returned by `connect`.
+    /// * `text` - The JSON text of the message.
+    ///
+    pub fn handle_message(&mut self, client: usize, text: &str) {
+        if let Err(e) = self.try_message(client, text) {
+            let error = Json::object(vec![("type", "error".into()), ("message", e.into())]);
+            self.send(client, &error);
+        }
+    }
+
+    /// Handles a message, returning the error to send back to the client.
+    fn try_message(&mut self, client: usize, text: &str) -> Result<(), String> {
+        let message = parse_json(text)?;
+        match message.get("type").and_then(Json::as_str) {
+            Some("join") => self.join(client, &message),
+            Some("move") => {
+                let text = message
+                    .get("move")
+                    .and_then(Json::as_str)
+                    .ok_or("ERROR: The message must have a \"move\" string")?;
+                self.play(client, text)
+            }
+            Some("resign") => {
+                let (name, color) = self.player(client)?;
+                let game = self.open_game(&name)?;
+                let winner = if color == Color::White { "0-1" } else { "1-0" };
+                game.result = Some((winner, format!("{} resigns", color_name(color))));
+                self.broadcast(&name);
+                Ok(())
+            }
+            Some("draw") => {
+                let (name, color) = self.player(client)?;
+                let game = self.open_game(&name)?;
+                match game.draw_offer {
+                    Some(offer) if offer == color => {
+                        return Err("ERROR: You already offered a draw".to_string())
+                    }
+                    Some(_) => {
+                        game.draw_offer = None;
+                        game.result = Some(("1/2-1/2", "draw by agreement".to_string()));
+                    }
+                    None => game.draw_offer = Some(color),
+                }
+                self.broadcast(&name);
+                Ok(())
+            }
+            Some("decline") => {
+                let (name, color) = self.player(client)?;
+                let game = self.open_game(&name)?;
+                if game.draw_offer != Some(color.opposite()) {
+                    return Err("ERROR: There is no draw offer to decline".to_string());
+                }
+                game.draw_offer = None;
+                self.broadcast(&name);
+                Ok(())
+            }
+            Some(other) => Err(format!("ERROR: Unknown message type: {}", other)),
+            None => Err("ERROR: The message must have a \"type\" string".to_string()),
+        }
+    }
+
+    /// Makes a client join a game, creating it if it does not exist.
+    fn join(&mut self, client: usize, message: &Json) -> Result<(), String> {
+        let name = message
+            .get("game")
+            .and_then(Json::as_str)
+            .ok_or("ERROR: The message must have a \"game\" string")?
+            .to_string();
+        let role = match message.get("role").and_then(Json::as_str) {
+            Some("white") => Some(Role::Player(Color::White)),
+            Some("black") => Some(Role::Player(Color::Black)),
+            Some("spectator") => Some(Role::Spectator),
+            Some(other) => return Err(format!("ERROR: Unknown role: {}", other)),
+            None => None,
+        };
+        if let Some((current, _)) = self.clients.get(&client).and_then(|c| c.game.as_ref()) {
+            if *current == name {
+                return Err("ERROR: You already joined this game".to_string());
+            }
+        }
+        if let (Some(game), Some(Role::Player(color))) = (self.games.get_mut(&name), role) {
+            if game.seat(color).is_some() {
+                let color = color_name(color).to_lowercase();
+                return Err(format!("ERROR: The {} seat is taken", color));
+            }
+        }
+        let has_board = message.get("table").is_some() || message.get("fen").is_some();
+        let table = match self.games.contains_key(&name) {
+            false if has_board => board_from_json(message)?,
+            _ => start_table(),
+        };
+
+        self.leave(client);
+        let game = self.games.entry(name.clone()).or_insert_with(|| Game {
+            table,
+            white: None,
+            black: None,
+            spectators: Vec::new(),
+            draw_offer: None,
+            result: None,
+        });
+        let role = match role {
+            Some(role) => role,
+            None if game.white.is_none() => Role::Player(Color::White),
+            None if game.black.is_none() => Role::Player(Color::Black),
+            None => Role::Spectator,
+        };
+        match role {
+            Role::Player(color) => *game.seat(color) = Some(client),
+            Role::Spectator => game.spectators.push(client),
+        }
+        if let Some(c) = self.clients.get_mut(&client) {
+            c.game = Some((name.clone(), role));
+        }
+
+        let role_name = match role {
+            Role::Player(color) => color_json(color),
+            Role::Spectator => "spectator".into(),
+        };
+        let joined = Json::object(vec![
+            ("type", "joined".into()),
+            ("game", name.as_str().into()),
+            ("role", role_name),
+        ]);
+        self.send(client, &joined);
+        self.broadcast(&name);
+        Ok(())
+    }
+
+    /// Plays a move of a client.
+    fn play(&mut self, client: usize, text: &str) -> Result<(), String> {
+        let (name, color) = self.player(client)?;
+        let game = self.open_game(&name)?;
+        if game.table.state().side_to_move != color {
+            return Err("ERROR: It is not your turn".to_string());
+        }
+        let mv = parse_typed_move(&game.table, text)?;
+        make_move(&mut game.table, &mv)?;
+        game.draw_offer = None;
+        if let (Some(reason), Some(result)) = (game_over(&game.table), pgn_result(&game.table)) {
+            game.result = Some((result, reason));
+        }
+        self.broadcast(&name);
+        Ok(())
+    }
+
+    /// Removes a client from its game, removing the game if it is left without clients.
+    fn leave(&mut self, client: usize) {
+        let name = match self.clients.get_mut(&client).and_then(|c| c.game.take()) {
+            Some((name, _)) => name,
+            None => return,
+        };
+        let game = match self.games.get_mut(&name) {
+            Some(game) => game,
+            None => return,
+        };
+        for color in [Color::White, Color::Black] {
+            if *game.seat(color) == Some(client) {
+                *game.seat(color) = None;
+            }
+        }
+        game.spectators.retain(|&c| c != client);
+        if game.clients().is_empty() {
+            self.games.remove(&name);
+        } else {
+            self.broadcast(&name);
+        }
+    }
+
+    /// Returns the game and the color of a client that plays a game.
+    fn player(&self, client: usize) -> Result<(String, Color), String> {
+        match self.clients.get(&client).and_then(|c| c.game.clone()) {
+            Some((name, Role::Player(color))) => Ok((name, color)),
+            Some((_, Role::Spectator)) => Err("ERROR: Spectators cannot play".to_string()),
+            None => Err("ERROR: Join a game first".to_string()),
+        }
+    }
+
+    /// Returns a game that is not over.
+    fn open_game(&mut self, name: &str) -> Result<&mut Game, String> {
+        match self.games.get_mut(name) {
+            Some(game) if game.result.is_none() => Ok(game),
+            Some(_) => Err("ERROR: The game is over".to_string()),
+            None => Err("ERROR: Join a game first".to_string()),
+        }
+    }
+
+    /// Sends the state of a game to all its clients.
+    fn broadcast(&self, name: &str) {
+        let game = match self.games.get(name) {
+            Some(game) => game,
+            None => return,
+        };
+        let moves = played_moves(&game.table)
+            .iter()
+            .map(|mv| format_uci(mv).into())
+            .collect();
+        let (result, reason) = match &game.result {
+            Some((result, reason)) => ((*result).into(), reason.as_str().into()),
+            None => (Json::Null, Json::Null),
+        };
+        let state = Json::object(vec![
+            ("type", "state".into()),
+            ("game", name.into()),
+            ("board", analysis(&game.table)),
+            ("moves", Json::Array(moves)),
+            (
+                "players",
+                Json::object(vec![
+                    ("white", game.white.is_some().into()),
+                    ("black", game.black.is_some().into()),
+                ]),
+            ),
+            ("draw_offer", game.draw_offer.map_or(Json::Null, color_json)),
+            ("result", result),
+            ("reason", reason),
+        ]);
+        for client in game.clients() {
+            self.send(client, &state);
+        }
+    }
+
+    /// Sends a message to a client.
+    fn send(&self, client: usize, message: &Json) {
+        if let Some(c) = self.clients.get(&client) {
+            (c.output)(&message.to_string())
+        }
+    }
+}
+
+/// Serves live games on a listener, speaking the WebSocket protocol with each client in its
+/// own thread.
+///
+/// The frames sent to each client are written by a thread of its own, so that a client that
+/// does not read its connection never holds up the hub, and is dropped once `MAX_QUEUED`
+/// frames wait for it. At most `MAX_CONNECTIONS` clients are served at the same time.
+pub fn serve(listener: TcpListener) {
+    let hub = Arc::new(Mutex::new(Hub::new()));
+    let connections = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming().flatten() {
+        let hub = Arc::clone(&hub);
+        spawn_connection(stream, &connections, move |stream| {
+            handle_connection(stream, hub)
+        });
+    }
+}
+
+/// Performs the opening handshake of a connection, then hands its messages to the hub until
+/// it is closed.
+fn handle_connection(mut stream: TcpStream, hub: Arc<Mutex<Hub>>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let key = read_head(&mut reader).and_then(|head| {
+        let upgrade = head.header("upgrade").unwrap_or("");
+        match head.header("sec-websocket-key") {
+            Some(key) if head.method == "GET" && upgrade.eq_ignore_ascii_case("websocket") => {
+                Ok(key.to_string())
+            }
+            _ => Err(Response::error(
+                400,
+                "ERROR: Expected a WebSocket handshake",
+            )),
+        }
+    });
+    let key = match key {
+        Ok(key) => key,
+        Err(response) => return respond(&mut stream, &response),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(&key)
+    )?;
+    // Clients may stay silent for as long as they like once connected, and each update is
+    // sent at once instead of waiting for the previous one to be acknowledged
+    stream.set_read_timeout(None)?;
+    stream.set_nodelay(true)?;
+
+    let outbox = spawn_writer(stream.try_clone()?);
+    let queue = outbox.clone();
+    let output: Output = Arc::new(move |line: &str| queue.send(TEXT, line.as_bytes()));
+    // A panic while another connection held the hub must not take this one down as well
+    let client = hub
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .connect(output);
+    read_messages(&mut reader, &outbox, &hub, client);
+    hub.lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .disconnect(client);
+    Ok(())
+}
+
+/// A struct queueing the frames sent to a client, which are written by its own thread.
+#[derive(Clone)]
+struct Outbox {
+    /// The queue of the encoded frames.
+    frames: SyncSender<Vec<u8>>,
+    /// The connection, to shut down when the queue is full.
+    stream: Arc<TcpStream>,
+}
+
+impl Outbox {
+    /// Queues a frame, shutting down the connection if the client lets too many pile up.
+    fn send(&self, opcode: u8, payload: &[u8]) {
+        if self
+            .frames
+            .try_send(encode_frame(opcode, payload, None))
+            .is_err()
+        {
+            let _ = self.stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+/// Starts the thread writing the frames queued for a client to its connection.
+fn spawn_writer(stream: TcpStream) -> Outbox {
+    let (frames, queue) = mpsc::sync_channel::<Vec<u8>>(MAX_QUEUED);
+    let stream = Arc::new(stream);
+    let writer = Arc::clone(&stream);
+    thread::spawn(move || {
+        for frame in queue {
+            if (&*writer).write_all(&frame).is_err() {
+                let _ = writer.shutdown(Shutdown::Both);
+                break;
+            }
+        }
+    });
+    Outbox { frames, stream }
+}
+
+/// Reads the frames of a client, handing each complete message to the hub and answering the
+/// control frames, until the connection is closed.
+fn read_messages(reader: &mut impl Read, outbox: &Outbox, hub: &Mutex<Hub>, client: usize) {
+    let close = |code: u16| outbox.send(CLOSE, &code.to_be_bytes());
+    let mut message = Vec::new();
+    loop {
+        let frame = match read_frame(reader) {
+            Ok(frame) => frame,
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => return close(CLOSE_TOO_BIG),
+            Err(_) => return,
+        };
+        // The frames of clients must be masked
+        if !frame.masked {
+            return close(CLOSE_PROTOCOL_ERROR);
+        }
+        match frame.opcode {
+            TEXT | BINARY | CONTINUATION => {
+                message.extend_from_slice(&frame.payload);
+                if message.len() > MAX_MESSAGE {
+                    return close(CLOSE_TOO_BIG);
+                }
+                if frame.fin {
+                    let text = String::from_utf8_lossy(&std::mem::take(&mut message)).into_owned();
+                    hub.lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .handle_message(client, &text);
+                }
+            }
+            PING => outbox.send(PONG, &frame.payload),
+            PONG => {}
+            CLOSE => return outbox.send(CLOSE, frame.payload.get(..2).unwrap_or(&[])),
+            _ => return close(CLOSE_PROTOCOL_ERROR),
+        }
+    }
+}
+
+/// A struct representing a frame of the WebSocket protocol.
+struct Frame {
+    /// Whether the frame is the last one of its message.
+    fin: bool,
+    /// The kind of the frame, such as `TEXT`.
+    opcode: u8,
+    /// Whether the payload was masked, as it must be when sent by a client.
+    masked: bool,
+    /// The payload, unmasked.
+    payload: Vec<u8>,
+}
+
+/// Reads a frame, unmasking its payload.
+///
+/// # Errors
+///
+/// Returns an error if the connection fails, or one of kind `InvalidData` if the payload is
+/// larger than `MAX_MESSAGE`.
+///
+fn read_frame(reader: &mut impl Read) -> io::Result<Frame> {
+    let mut head = [0; 2];
+    reader.read_exact(&mut head)?;
+    let length = match head[1] & 0x7f {
+        126 => {
+            let mut bytes = [0; 2];
+            reader.read_exact(&mut bytes)?;
+            u16::from_be_bytes(bytes) as u64
+        }
+        127 => {
+            let mut bytes = [0; 8];
+            reader.read_exact(&mut bytes)?;
+            u64::from_be_bytes(bytes)
+        }
+        length => length as u64,
+    };
+    if length > MAX_MESSAGE as u64 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "ERROR: Message too large",
+        ));
+    }
+    let mask = if head[1] & 0x80 != 0 {
+        let mut mask = [0; 4];
+        reader.read_exact(&mut mask)?;
+        Some(mask)
+    } else {
+        None
+    };
+    let mut payload = vec![0; length as usize];
+    reader.read_exact(&mut payload)?;
+    if let Some(mask) = mask {
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+    }
+    Ok(Frame {
+        fin: head[0] & 0x80 != 0,
+        opcode: head[0] & 0x0f,
+        masked: mask.is_some(),
+        payload,
+    })
+}
+
+/// Writes a whole message in a single frame, masked with `mask` if it is given, as clients
+/// must do.
+#[cfg(test)]
+fn write_frame(
+    writer: &mut impl Write,
+    opcode: u8,
+    payload: &[u8],
+    mask: Option<[u8; 4]>,
+) -> io::Result<()> {
+    writer.write_all(&encode_frame(opcode, payload, mask))?;
+    writer.flush()
+}
+
+/// Encodes a whole message as a single frame, masked with `mask` if it is given.
+fn encode_frame(opcode: u8, payload: &[u8], mask: Option<[u8; 4]>) -> Vec<u8> {
+    let mut frame = vec![0x80 | opcode];
+    let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+    match payload.len() {
+        length if length < 126 => frame.push(mask_bit | length as u8),
+        length if length <= u16::MAX as usize => {
+            frame.push(mask_bit | 126);
+            frame.extend_from_slice(&(length as u16).to_be_bytes());
+        }
+        length => {
+            frame.push(mask_bit | 127);
+            frame.extend_from_slice(&(length as u64).to_be_bytes());
+        }
+    }
+    match mask {
+        Some(mask) => {
+            frame.extend_from_slice(&mask);
+            frame.extend(
+                payload
+                    .iter()
+                    .enumerate()
+                    .map(|(i, byte)| byte ^ mask[i % 4]),
+            );
+        }
+        None => frame.extend_from_slice(payload),
+    }
+    frame
+}
+
+/// Returns the `Sec-WebSocket-Accept` answer to the key of a handshake: the Base64 of the
+/// SHA-1 of the key followed by `HANDSHAKE_GUID`.
+fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{}{}", key, HANDSHAKE_GUID).as_bytes()))
+}
+
+/// Returns the SHA-1 digest of some data.
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5a827999),
+                20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+                _ => (b ^ c ^ d, 0xca62c1d6),
+            };
+            let t = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            (e, d, c, b, a) = (d, c, b.rotate_left(30), a, t);
+        }
+        for (x, y) in h.iter_mut().zip([a, b, c, d, e]) {
+            *x = x.wrapping_add(y);
+        }
+    }
+
+    let mut digest = [0; 20];
+    for (bytes, x) in digest.chunks_mut(4).zip(h) {
+        bytes.copy_from_slice(&x.to_be_bytes());
+    }
+    digest
+}
+
+/// Returns the Base64 encoding of some data, with padding.
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut text = String::new();
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufRead;
+
+    use super::*;
+
+    /// Connects a client to the hub, whose messages are collected in the returned vector.
+    fn connect(hub: &mut Hub) -> (usize, Arc<Mutex<Vec<Json>>>) {
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&messages);
+        let id = hub.connect(Arc::new(move |line: &str| {
+            sink.lock().unwrap().push(parse_json(line).unwrap())
+        }));
+        (id, messages)
+    }
+
+    /// Returns a member of the last message of a client, written as JSON.
+    fn last(messages: &Mutex<Vec<Json>>, key: &str) -> String {
+        let messages = messages.lock().unwrap();
+        let message = messages.last().unwrap();
+        message.get(key).map(Json::to_string).unwrap_or_default()
+    }
+
+    #[test]
+    fn test_accept_key() {
+        // The example of RFC 6455
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+        assert_eq!(base64(b"ab"), "YWI=");
+    }
+
+    #[test]
+    fn test_hub() {
+        let mut hub = Hub::new();
+        let (white, white_messages) = connect(&mut hub);
+        let (black, black_messages) = connect(&mut hub);
+        let (spectator, spectator_messages) = connect(&mut hub);
+        hub.handle_message(white, r#"{"type": "join", "game": "g"}"#);
+        assert_eq!(
+            last(&white_messages, "players"),
+            r#"{"white":true,"black":false}"#
+        );
+        hub.handle_message(black, r#"{"type": "join", "game": "g"}"#);
+        hub.handle_message(spectator, r#"{"type": "join", "game": "g"}"#);
+        assert_eq!(
+            spectator_messages.lock().unwrap()[0].get("role"),
+            Some(&Json::from("spectator"))
+        );
+
+        // The moves are checked, and pushed to the players and spectators
+        hub.handle_message(black, r#"{"type": "move", "move": "e5"}"#);
+        assert_eq!(
+            last(&black_messages, "message"),
+            "\"ERROR: It is not your turn\""
+        );
+        hub.handle_message(spectator, r#"{"type": "move", "move": "e4"}"#);
+        assert_eq!(
+            last(&spectator_messages, "message"),
+            "\"ERROR: Spectators cannot play\""
+        );
+        hub.handle_message(white, r#"{"type": "move", "move": "e4"}"#);
+        hub.handle_message(black, r#"{"type": "move", "move": "e7e5"}"#);
+        assert_eq!(last(&spectator_messages, "moves"), r#"["e2e4","e7e5"]"#);
+        hub.handle_message(white, r#"{"type": "move", "move": "Re3"}"#);
+        assert!(last(&white_messages, "message").starts_with("\"ERROR: Illegal move"));
+
+        // A draw offer can be declined, and is withdrawn by a move
+        hub.handle_message(white, r#"{"type": "draw"}"#);
+        assert_eq!(last(&black_messages, "draw_offer"), "\"white\"");
+        hub.handle_message(black, r#"{"type": "decline"}"#);
+        assert_eq!(last(&black_messages, "draw_offer"), "null");
+        hub.handle_message(white, r#"{"type": "decline"}"#);
+        assert_eq!(
+            last(&white_messages, "message"),
+            "\"ERROR: There is no draw offer to decline\""
+        );
+        hub.handle_message(black, r#"{"type": "draw"}"#);
+        hub.handle_message(white, r#"{"type": "draw"}"#);
+        assert_eq!(last(&spectator_messages, "result"), "\"1/2-1/2\"");
+        hub.handle_message(white, r#"{"type": "resign"}"#);
+        assert_eq!(
+            last(&white_messages, "message"),
+            "\"ERROR: The game is over\""
+        );
+
+        // The seat of a player who leaves is freed, and a game without clients is removed
+        hub.disconnect(white);
+        assert_eq!(
+            last(&black_messages, "players"),
+            r#"{"white":false,"black":true}"#
+        );
+        hub.disconnect(black);
+        hub.disconnect(spectator);
+        assert!(hub.games.is_empty());
+    }
+
+    #[test]
+    fn test_hub_resign() {
+        let mut hub = Hub::new();
+        let (white, white_messages) = connect(&mut hub);
+        let (black, black_messages) = connect(&mut hub);
+        let join =
+            r#"{"type": "join", "game": "mate", "fen": "6k1/5ppp/8/8/8/8/8/K3R3 w - - 0 1"}"#;
+        hub.handle_message(white, join);
+        hub.handle_message(
+            black,
+            r#"{"type": "join", "game": "mate", "role": "white"}"#,
+        );
+        assert_eq!(
+            last(&black_messages, "message"),
+            "\"ERROR: The white seat is taken\""
+        );
+        hub.handle_message(
+            black,
+            r#"{"type": "join", "game": "mate", "role": "black"}"#,
+        );
+
+        // The board of the state carries the capture results and the verdict
+        let messages = white_messages.lock().unwrap();
+        let board = messages.last().unwrap().get("board").unwrap();
+        assert_eq!(
+            board.get("check_moves").unwrap().to_string(),
+            r#"{"white":false,"black":false}"#
+        );
+        assert_eq!(board.get("verdict"), Some(&Json::from("P")));
+        drop(messages);
+
+        hub.handle_message(white, r#"{"type": "move", "move": "Te8"}"#);
+        assert_eq!(last(&black_messages, "result"), "\"1-0\"");
+        assert_eq!(
+            last(&black_messages, "reason"),
+            "\"White wins by checkmate\""
+        );
+
+        let (other, other_messages) = connect(&mut hub);
+        hub.handle_message(other, r#"{"type": "join", "game": "new", "role": "black"}"#);
+        hub.handle_message(other, r#"{"type": "resign"}"#);
+        assert_eq!(last(&other_messages, "reason"), "\"Black resigns\"");
+        hub.handle_message(other, &"[".repeat(60_000));
+        assert!(last(&other_messages, "message").ends_with("nested more than 64 levels deep\""));
+        hub.handle_message(other, "{\"type\": \"castle\"}");
+        assert_eq!(
+            last(&other_messages, "message"),
+            "\"ERROR: Unknown message type: castle\""
+        );
+    }
+
+    /// A client speaking the WebSocket protocol to a server on localhost.
+    struct TestClient {
+        reader: BufReader<TcpStream>,
+        writer: TcpStream,
+    }
+
+    impl TestClient {
+        fn connect(address: std::net::SocketAddr) -> TestClient {
+            let writer = TcpStream::connect(address).unwrap();
+            let mut reader = BufReader::new(writer.try_clone().unwrap());
+            let mut client_writer = writer.try_clone().unwrap();
+            write!(
+                client_writer,
+                "GET /live HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\n\
+                 Connection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+                 Sec-WebSocket-Version: 13\r\n\r\n"
+            )
+            .unwrap();
+            let mut lines = Vec::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim_end().is_empty() {
+                    break;
+                }
+                lines.push(line.trim_end().to_string());
+            }
+            assert_eq!(lines[0], "HTTP/1.1 101 Switching Protocols");
+            assert!(
+                lines.contains(&"Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=".to_string())
+            );
+            TestClient { reader, writer }
+        }
+
+        fn send(&mut self, text: &str) {
+            write_frame(&mut self.writer, TEXT, text.as_bytes(), Some([1, 2, 3, 4])).unwrap();
+        }
+
+        fn receive(&mut self) -> Json {
+            let frame = read_frame(&mut self.reader).unwrap();
+            assert_eq!(frame.opcode, TEXT);
+            parse_json(&String::from_utf8(frame.payload).unwrap()).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_serve() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || serve(listener));
+
+        let mut white = TestClient::connect(address);
+        white.send(r#"{"type": "join", "game": "live"}"#);
+        assert_eq!(white.receive().get("role"), Some(&Json::from("white")));
+        white.receive();
+        let mut black = TestClient::connect(address);
+        black.send(r#"{"type": "join", "game": "live"}"#);
+        assert_eq!(black.receive().get("role"), Some(&Json::from("black")));
+        black.receive();
+        white.receive();
+
+        white.send(r#"{"type": "move", "move": "Cf3"}"#);
+        for client in [&mut white, &mut black] {
+            let state = client.receive();
+            assert_eq!(state.get("moves").unwrap().to_string(), r#"["g1f3"]"#);
+        }
+
+        // Pings are answered, and a close is echoed
+        write_frame(&mut black.writer, PING, b"hi", Some([9, 9, 9, 9])).unwrap();
+        let pong = read_frame(&mut black.reader).unwrap();
+        assert_eq!((pong.opcode, pong.payload), (PONG, b"hi".to_vec()));
+        write_frame(
+            &mut black.writer,
+            CLOSE,
+            &1000u16.to_be_bytes(),
+            Some([0; 4]),
+        )
+        .unwrap();
+        assert_eq!(read_frame(&mut black.reader).unwrap().opcode, CLOSE);
+        drop(black);
+        let state = white.receive();
+        assert_eq!(
+            state.get("players").unwrap().to_string(),
+            r#"{"white":true,"black":false}"#
+        );
+
+        // A plain HTTP request is refused
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request"));
+    }
+
+    #[test]
+    fn test_serve_client_not_reading() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || serve(listener));
+
+        // A spectator joins but never reads what it is sent
+        let mut spectator = TestClient::connect(address);
+        spectator.send(r#"{"type": "join", "game": "slow", "role": "spectator"}"#);
+        let (done, finished) = mpsc::channel();
+        thread::spawn(move || {
+            let mut white = TestClient::connect(address);
+            white.send(r#"{"type": "join", "game": "slow", "role": "white"}"#);
+            white.receive();
+            white.receive();
+            let mut black = TestClient::connect(address);
+            black.send(r#"{"type": "join", "game": "slow", "role": "black"}"#);
+            black.receive();
+            black.receive();
+            white.receive();
+
+            // Each offer and decline sends the state to every client, the spectator included
+            for _ in 0..2000 {
+                white.send(r#"{"type": "draw"}"#);
+                white.receive();
+                black.receive();
+                black.send(r#"{"type": "decline"}"#);
+                white.receive();
+                black.receive();
+            }
+            done.send(()).unwrap();
+        });
+        assert!(finished
+            .recv_timeout(std::time::Duration::from_secs(60))
+            .is_ok());
+
+        // The spectator was dropped once its frames piled up, without getting all the states
+        let mut states = 0;
+        while read_frame(&mut spectator.reader).is_ok() {
+            states += 1;
+        }
+        assert!(states < 2 * 2000);
+    }
+}
@@ -0,0 +1,327 @@
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+
+use crate::game::table::draw::{draw_reason, DrawReason};
+use crate::game::table::fen::parse_fen;
+use crate::game::table::generator::{is_checkmate, is_stalemate};
+use crate::game::table::moves::make_move;
+use crate::game::table::notation::format_uci;
+use crate::game::table::piece::Color;
+use crate::game::table::search::{mate_in, search_with, SearchLimits, MATE_SCORE};
+use crate::game::table::variant::variant_winner;
+use crate::game::table::Table;
+use crate::protocol::uci::ENGINE_NAME;
+use crate::protocol::{play_move, start_table, time_for_move, Output};
+
+/// The time taken to think about a move when no time control was given.
+const DEFAULT_MOVE_TIME: Duration = Duration::from_secs(1);
+
+/// The features announced in answer to `protover`.
+const FEATURES: [&str; 7] = [
+    "ping=1",
+    "setboard=1",
+    "usermove=1",
+    "san=0",
+    "sigint=0",
+    "sigterm=0",
+    "colors=0",
+];
+
+/// A struct representing an engine driven by the Chess Engine Communication Protocol used by
+/// XBoard and WinBoard.
+///
+/// Commands are given one line at a time with `handle_command`. Unlike `Uci`, the engine
+/// thinks in the thread handling the commands: commands sent while it is thinking are read
+/// once its move has been sent.
+pub struct XBoard {
+    /// The position of the game being played.
+    table: Table,
+    /// Where the answers of the engine are sent.
+    output: Output,
+    /// The color the engine plays, or `None` in force mode, when it only records the moves.
+    engine: Option<Color>,
+    /// Whether the thinking output is sent after each depth searched.
+    post: bool,
+    /// The depth limit set with `sd`.
+    depth: Option<usize>,
+    /// The fixed time per move set with `st`.
+    move_time: Option<Duration>,
+    /// The time left on the clock of the engine, last sent with `time`.
+    clock: Option<Duration>,
+    /// The time added to the clock after each move, set with `level`.
+    increment: Duration,
+    /// The number of moves of each time control set with `level`, or `None` when the whole
+    /// game is played with the same clock.
+    moves_per_session: Option<u32>,
+}
+
+impl XBoard {
+    /// Creates a new engine set up at the start position in force mode, which sends its
+    /// answers to `output`.
+    pub fn new(output: Output) -> XBoard {
+        XBoard {
+            table: start_table(),
+            output,
+            engine: None,
+            post: false,
+            depth: None,
+            move_time: None,
+            clock: None,
+            increment: Duration::ZERO,
+            moves_per_session: None,
+        }
+    }
+
+    /// Handles a command sent to the engine.
+    ///
+    /// Moves are given with `usermove` in coordinate notation, such as `usermove e2e4`. The
+    /// engine answers with its own moves as `move e7e5` whenever it is its turn and it is
+    /// not in force mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - The line of the command.
+    ///
+    /// # Returns
+    ///
+    /// `false` if the command was `quit`, in which case no more commands should be sent.
+    ///
+    pub fn handle_command(&mut self, line: &str) -> bool {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let argument = |i: usize| words.get(i).copied().unwrap_or("");
+        match words.first().copied().unwrap_or("") {
+            "" | "xboard" | "accepted" | "rejected" | "random" | "hard" | "easy" | "computer"
+            | "otim" | "?" => {}
+            "protover" => {
+                let features: Vec<String> = FEATURES.iter().map(|f| f.to_string()).collect();
+                self.send(&format!(
+                    "feature myname=\"{}\" {} done=1",
+                    ENGINE_NAME,
+                    features.join(" ")
+                ));
+            }
+            "ping" => self.send(&format!("pong {}", argument(1))),
+            "new" => {
+                self.table = start_table();
+                self.engine = Some(Color::Black);
+                self.depth = None;
+            }
+            "force" | "result" => self.engine = None,
+            "go" => {
+                self.engine = Some(self.table.state().side_to_move);
+                self.think();
+            }
+            "playother" => self.engine = Some(self.table.state().side_to_move.opposite()),
+            "setboard" => match parse_fen(&words[1..].join(" ")) {
+                Ok(table) => self.table = table,
+                Err(e) => self.send(&format!("tellusererror {}", e)),
+            },
+            "usermove" => match play_move(&mut self.table, argument(1)) {
+                Ok(_) => {
+                    if !self.send_result() && self.engine == Some(self.table.state().side_to_move) {
+                        self.think();
+                    }
+                }
+                Err(_) => self.send(&format!("Illegal move: {}", argument(1))),
+            },
+            "post" => self.post = true,
+            "nopost" => self.post = false,
+            "sd" => match argument(1).parse() {
+                Ok(depth) => self.depth = Some(depth),
+                Err(_) => self.send_error(line, "invalid depth"),
+            },
+            "st" => match argument(1).parse::<u64>() {
+                Ok(seconds) => self.move_time = Some(Duration::from_secs(seconds)),
+                Err(_) => self.send_error(line, "invalid time"),
+            },
+            "time" => match argument(1).parse::<u64>() {
+                Ok(centiseconds) => self.clock = Some(Duration::from_millis(10 * centiseconds)),
+                Err(_) => self.send_error(line, "invalid time"),
+            },
+            "level" => match (argument(1).parse(), argument(3).parse::<f64>()) {
+                (Ok(moves), Ok(increment)) => {
+                    self.moves_per_session = if moves > 0 { Some(moves) } else { None };
+                    self.increment = Duration::from_secs_f64(increment.max(0.0));
+                    self.move_time = None;
+                }
+                _ => self.send_error(line, "invalid level"),
+            },
+            "quit" => return false,
+            _ => self.send_error(line, "unknown command"),
+        }
+        true
+    }
+
+    /// Sends a line to the program driving the engine.
+    fn send(&self, line: &str) {
+        (self.output)(line)
+    }
+
+    /// Sends an error about a command that cannot be handled.
+    fn send_error(&self, command: &str, reason: &str) {
+        self.send(&format!("Error ({}): {}", reason, command));
+    }
+
+    /// Sends the result of the game if it is won by the rules of its variant, if the player to
+    /// move is checkmated or stalemated, or if the game is drawn by a draw rule.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the game is over.
+    ///
+    fn send_result(&self) -> bool {
+        let result = if let Some(winner) = variant_winner(&self.table) {
+            match winner {
+                Color::White => "1-0 {White wins}",
+                _ => "0-1 {Black wins}",
+            }
+        } else if is_checkmate(&self.table) {
+            match self.table.state().side_to_move {
+                Color::Black => "1-0 {White mates}",
+                _ => "0-1 {Black mates}",
+            }
+        } else if is_stalemate(&self.table) {
+            "1/2-1/2 {Stalemate}"
+        } else {
+            match draw_reason(&self.table) {
+                Some(DrawReason::ThreefoldRepetition) => "1/2-1/2 {Draw by repetition}",
+                Some(DrawReason::FiftyMoveRule) => "1/2-1/2 {Draw by fifty-move rule}",
+                Some(DrawReason::InsufficientMaterial) => "1/2-1/2 {Insufficient material}",
+                None => return false,
+            }
+        };
+        self.send(result);
+        true
+    }
+
+    /// Searches the best move of the engine, plays it and sends it.
+    ///
+    /// With `post`, a line with the depth, score, time in centiseconds, nodes and principal
+    /// variation is sent after each depth searched. Checkmates are scored `100000 + n`, as
+    /// is usual in XBoard, where `n` is the number of moves until mate.
+    fn think(&mut self) {
+        let limits = self.limits();
+        let output = self.output.clone();
+        let post = self.post;
+        let start = Instant::now();
+        let stop = AtomicBool::new(false);
+        let result = search_with(&self.table, &limits, &stop, |result| {
+            if !post {
+                return;
+            }
+            let score = match mate_in(result.score) {
+                Some(moves) => moves.signum() * MATE_SCORE + moves,
+                None => result.score,
+            };
+            let pv: Vec<String> = result.principal_variation.iter().map(format_uci).collect();
+            output(&format!(
+                "{} {} {} {} {}",
+                result.depth,
+                score,
+                start.elapsed().as_millis() / 10,
+                result.nodes,
+                pv.join(" ")
+            ));
+        });
+        if let Some(mv) = result.best_move {
+            if make_move(&mut self.table, &mv).is_ok() {
+                self.send(&format!("move {}", format_uci(&mv)));
+                self.send_result();
+            }
+        }
+    }
+
+    /// Returns the limits of the next search, from the time control and depth limit set.
+    fn limits(&self) -> SearchLimits {
+        let time = match (self.move_time, self.clock) {
+            (Some(time), _) => Some(time),
+            (None, Some(clock)) => {
+                let moves_to_go = self.moves_per_session.map(|moves| {
+                    let played = self.table.state().fullmove_number.saturating_sub(1);
+                    moves - played % moves
+                });
+                Some(time_for_move(clock, self.increment, moves_to_go))
+            }
+            (None, None) if self.depth.is_none() => Some(DEFAULT_MOVE_TIME),
+            (None, None) => None,
+        };
+        SearchLimits {
+            depth: self.depth,
+            time,
+            root_moves: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::collect_output;
+
+    #[test]
+    fn test_xboard() {
+        let (output, lines) = collect_output();
+        let mut xboard = XBoard::new(output);
+        let last = || lines.lock().unwrap().last().cloned().unwrap_or_default();
+        assert!(xboard.handle_command("xboard"));
+        xboard.handle_command("protover 2");
+        assert!(last().starts_with("feature myname=\"tp1_rust\""));
+        assert!(last().ends_with("done=1"));
+        xboard.handle_command("ping 7");
+        assert_eq!(last(), "pong 7");
+
+        // The engine plays black after `new`, answering each move
+        xboard.handle_command("new");
+        xboard.handle_command("sd 2");
+        xboard.handle_command("usermove e2e4");
+        assert!(last().starts_with("move "));
+        xboard.handle_command("usermove e2e5");
+        assert_eq!(last(), "Illegal move: e2e5");
+
+        // In force mode moves are only recorded, until `go`
+        let answered = lines.lock().unwrap().len();
+        xboard.handle_command("force");
+        xboard.handle_command("setboard 6k1/5ppp/8/8/8/8/8/2K1R3 w - - 0 1");
+        xboard.handle_command("usermove e1e2");
+        xboard.handle_command("usermove g8h8");
+        assert_eq!(lines.lock().unwrap().len(), answered);
+        xboard.handle_command("post");
+        xboard.handle_command("go");
+        let answers = lines.lock().unwrap().clone();
+        assert_eq!(answers[answers.len() - 2], "move e2e8");
+        assert_eq!(answers[answers.len() - 1], "1-0 {White mates}");
+        assert!(answers.iter().any(|line| line.starts_with("2 100001 ")));
+
+        // Taking the last knight leaves two bare kings
+        xboard.handle_command("force");
+        xboard.handle_command("setboard 4k3/8/8/8/8/8/3n4/4K3 w - - 0 1");
+        xboard.handle_command("usermove e1d2");
+        assert_eq!(last(), "1/2-1/2 {Insufficient material}");
+
+        xboard.handle_command("frobnicate");
+        assert_eq!(last(), "Error (unknown command): frobnicate");
+        assert!(!xboard.handle_command("quit"));
+    }
+
+    #[test]
+    fn test_xboard_limits() {
+        let mut xboard = XBoard::new(collect_output().0);
+        assert_eq!(xboard.limits().time, Some(DEFAULT_MOVE_TIME));
+        xboard.handle_command("level 40 5 2");
+        xboard.handle_command("time 30000");
+        assert_eq!(
+            xboard.limits().time,
+            Some(Duration::from_millis(300_000 / 40 + 1500))
+        );
+        xboard.handle_command("st 3");
+        xboard.handle_command("sd 4");
+        assert_eq!(
+            xboard.limits(),
+            SearchLimits {
+                depth: Some(4),
+                time: Some(Duration::from_secs(3)),
+                root_moves: None,
+            }
+        );
+    }
+}
@@ -0,0 +1,178 @@
+use crate::game::play_game;
+use crate::game::table::{check_moves, parse_table, Table};
+use crate::protocol::http::piece_json;
+use crate::protocol::json::Json;
+
+/// Parses a board given as the text of a table file, as `parse_table` does.
+///
+/// # Arguments
+///
+/// * `board` - The lines of the table file, separated by newlines.
+///
+/// # Returns
+///
+/// The JSON text of `{"pieces": [...], "variant": "standard"}`, where each piece is written
+/// as `{"type": "D", "color": "white", "square": "d1"}`, or `{"error": "ERROR: ..."}`.
+///
+pub fn parse_table_json(board: &str) -> String {
+    answer(read_board(board).map(|table| {
+        Json::object(vec![
+            (
+                "pieces",
+                Json::Array(table.pieces().iter().map(piece_json).collect()),
+            ),
+            ("variant", table.state().variant.to_string().into()),
+        ])
+    }))
+}
+
+/// Tells which colors can capture a piece of the other one on a board, as `check_moves` does.
+///
+/// # Returns
+///
+/// The JSON text of `{"white": true, "black": false}`, or `{"error": "ERROR: ..."}`.
+///
+pub fn check_moves_json(board: &str) -> String {
+    answer(read_board(board).map(|table| {
+        let (white, black) = check_moves(&table);
+        Json::object(vec![("white", white.into()), ("black", black.into())])
+    }))
+}
+
+/// Plays the game on a board, as `play_game` does for the command line.
+///
+/// # Returns
+///
+/// The JSON text of `{"result": "B"}`, with the results described in `play_game`, or
+/// `{"error": "ERROR: ..."}`.
+///
+pub fn play_game_json(board: &str) -> String {
+    answer(play_game(lines(board)).map(|result| Json::object(vec![("result", result.into())])))
+}
+
+/// Parses the text of a table file.
+fn read_board(board: &str) -> Result<Table, String> {
+    parse_table(&lines(board))
+}
+
+/// Splits the text of a table file into its lines.
+fn lines(board: &str) -> Vec<String> {
+    board.lines().map(|line| line.to_string()).collect()
+}
+
+/// Returns the JSON text of a result, or of `{"error": ...}` for an error.
+fn answer(result: Result<Json, String>) -> String {
+    match result {
+        Ok(value) => value,
+        Err(e) => Json::object(vec![("error", e.into())]),
+    }
+    .to_string()
+}
+
+/// The functions exported by the WebAssembly module, wrapped by `wasm/tp1_rust.js`.
+///
+/// Strings are passed as UTF-8 in the memory of the module: the caller writes its argument
+/// into a buffer taken with `alloc`, and reads the answer of the call, whose length is
+/// returned, at `result_ptr`.
+#[cfg(target_arch = "wasm32")]
+mod exports {
+    use std::cell::RefCell;
+
+    thread_local! {
+        /// The answer of the last call, kept until the next one.
+        static RESULT: RefCell<String> = const { RefCell::new(String::new()) };
+    }
+
+    /// Reserves a buffer of `len` bytes for an argument.
+    #[no_mangle]
+    pub extern "C" fn alloc(len: usize) -> *mut u8 {
+        let mut buffer = Vec::<u8>::with_capacity(len);
+        let ptr = buffer.as_mut_ptr();
+        std::mem::forget(buffer);
+        ptr
+    }
+
+    /// Frees a buffer taken with `alloc`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` and `len` must be the ones of a buffer taken with `alloc` and not freed yet.
+    #[no_mangle]
+    pub unsafe extern "C" fn dealloc(ptr: *mut u8, len: usize) {
+        drop(Vec::from_raw_parts(ptr, 0, len));
+    }
+
+    /// Returns the address of the answer of the last call.
+    #[no_mangle]
+    pub extern "C" fn result_ptr() -> *const u8 {
+        RESULT.with(|result| result.borrow().as_ptr())
+    }
+
+    /// Calls `f` on the string at `ptr`, keeping its answer.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to `len` initialized bytes.
+    unsafe fn call(ptr: *const u8, len: usize, f: fn(&str) -> String) -> usize {
+        let bytes = std::slice::from_raw_parts(ptr, len);
+        let answer = match std::str::from_utf8(bytes) {
+            Ok(board) => f(board),
+            Err(_) => super::answer(Err("ERROR: The board is not valid UTF-8".to_string())),
+        };
+        RESULT.with(|result| {
+            *result.borrow_mut() = answer;
+            result.borrow().len()
+        })
+    }
+
+    /// Exports `parse_table_json`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to `len` initialized bytes.
+    #[no_mangle]
+    pub unsafe extern "C" fn parse_table(ptr: *const u8, len: usize) -> usize {
+        call(ptr, len, super::parse_table_json)
+    }
+
+    /// Exports `check_moves_json`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to `len` initialized bytes.
+    #[no_mangle]
+    pub unsafe extern "C" fn check_moves(ptr: *const u8, len: usize) -> usize {
+        call(ptr, len, super::check_moves_json)
+    }
+
+    /// Exports `play_game_json`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to `len` initialized bytes.
+    #[no_mangle]
+    pub unsafe extern "C" fn play_game(ptr: *const u8, len: usize) -> usize {
+        call(ptr, len, super::play_game_json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_wasm_api() {
+        use super::*;
+        let board = "_ _ _ T _ _ _ _\n_ _ _ _ _ _ _ _\n_ _ _ _ _ _ _ _\n_ _ _ _ _ _ _ _\n\
+                     _ _ _ _ _ _ _ _\n_ _ _ _ _ _ _ _\n_ _ _ _ _ _ _ _\n_ _ _ a _ _ _ _";
+        assert_eq!(
+            parse_table_json(board),
+            "{\"pieces\":[{\"type\":\"T\",\"color\":\"black\",\"square\":\"d8\"},\
+             {\"type\":\"A\",\"color\":\"white\",\"square\":\"d1\"}],\"variant\":\"standard\"}"
+        );
+        assert_eq!(check_moves_json(board), "{\"white\":false,\"black\":true}");
+        assert_eq!(play_game_json(board), "{\"result\":\"N\"}");
+        assert_eq!(
+            play_game_json("_ _"),
+            "{\"error\":\"ERROR: Table formated incorrectly. Table has 1 rows, expected 8\"}"
+        );
+    }
+}
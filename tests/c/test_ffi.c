@@ -0,0 +1,63 @@
+/* Exercises the C API of tp1_rust. Built and run by `cargo test`, or by hand with
+ *
+ *     cargo build
+ *     cc tests/c/test_ffi.c -Iinclude -Ltarget/debug -ltp1_rust -o test_ffi
+ *     LD_LIBRARY_PATH=target/debug ./test_ffi
+ */
+#include <stdio.h>
+#include <string.h>
+
+#include "tp1_rust.h"
+
+static int failures = 0;
+
+#define CHECK(condition)                                                  \
+    do {                                                                  \
+        if (!(condition)) {                                               \
+            fprintf(stderr, "%s:%d: %s\n", __FILE__, __LINE__, #condition); \
+            failures++;                                                   \
+        }                                                                 \
+    } while (0)
+
+int main(void) {
+    /* A white queen and a black rook on the same file can capture each other */
+    const char *table =
+        "_ _ _ T _ _ _ _\n"
+        "_ _ _ _ _ _ _ _\n"
+        "_ _ _ _ _ _ _ _\n"
+        "_ _ _ _ _ _ _ _\n"
+        "_ _ _ _ _ _ _ _\n"
+        "_ _ _ _ _ _ _ _\n"
+        "_ _ _ _ _ _ _ _\n"
+        "_ _ _ d _ _ _ _\n";
+    Tp1Board *board = tp1_board_from_table(table);
+    CHECK(board != NULL);
+    CHECK(tp1_board_white_can_capture(board) == 1);
+    CHECK(tp1_board_black_can_capture(board) == 1);
+    CHECK(strcmp(tp1_board_verdict(board), "E") == 0);
+    tp1_board_free(board);
+
+    /* A bishop cannot reach the rook, which captures it */
+    board = tp1_board_from_fen("3r4/8/8/8/8/8/8/3B4 w - - 0 1");
+    CHECK(board != NULL);
+    CHECK(tp1_board_white_can_capture(board) == 0);
+    CHECK(strcmp(tp1_board_verdict(board), "N") == 0);
+    tp1_board_free(board);
+
+    /* Errors leave a message */
+    CHECK(tp1_last_error() == NULL);
+    CHECK(tp1_board_from_table("_ _ x") == NULL);
+    CHECK(tp1_last_error() != NULL && strncmp(tp1_last_error(), "ERROR: ", 7) == 0);
+    CHECK(tp1_board_from_fen(NULL) == NULL);
+    CHECK(strcmp(tp1_last_error(), "ERROR: The string is NULL") == 0);
+    CHECK(tp1_board_black_can_capture(NULL) == -1);
+    CHECK(tp1_board_verdict(NULL) == NULL);
+    tp1_board_free(NULL);
+
+    if (failures > 0) {
+        fprintf(stderr, "%d checks failed\n", failures);
+        return 1;
+    }
+    printf("All checks passed\n");
+    return 0;
+}
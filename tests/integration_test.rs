@@ -1,6 +1,8 @@
 extern crate tp1_rust;
 use crate::tp1_rust::game::play_game;
 use crate::tp1_rust::utils::read_file;
+use std::io::Write;
+use std::process::{Command, Stdio};
 #[test]
 fn test_game() {
     let res = read_file(&"tables/game_B.txt");
@@ -11,3 +13,92 @@ fn test_game() {
     let game_result = res.unwrap();
     assert_eq!(game_result, "B");
 }
+
+#[test]
+fn test_uci_binary() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_uci"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let script = "uci\nisready\nposition startpos moves e2e4 e7e5\ngo depth 2\n";
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(script.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let output = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert!(lines.contains(&"uciok"));
+    assert!(lines.contains(&"readyok"));
+    assert!(lines.iter().any(|line| line.starts_with("info depth 2 ")));
+    assert!(lines.last().unwrap().starts_with("bestmove "));
+}
+
+#[test]
+fn test_xboard_binary() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_xboard"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let script = "xboard\nprotover 2\nnew\nsd 2\nusermove e2e4\nquit\n";
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(script.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let output = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert!(lines[0].starts_with("feature "));
+    assert!(lines.last().unwrap().starts_with("move "));
+}
+
+#[test]
+fn test_c_api() {
+    // The C test program needs a C compiler, and the skip is reported rather than passed
+    // silently
+    if Command::new("cc").arg("--version").output().is_err() {
+        eprintln!("test_c_api skipped: no C compiler found as `cc`");
+        return;
+    }
+
+    // The C test program is linked against the library built for the tests, next to them
+    let exe = std::env::current_exe().unwrap();
+    let lib_dir = exe.parent().unwrap().parent().unwrap();
+    let program = lib_dir.join("test_ffi");
+    let status = Command::new("cc")
+        .args(["tests/c/test_ffi.c", "-Iinclude", "-ltp1_rust", "-o"])
+        .arg(&program)
+        .arg("-L")
+        .arg(lib_dir)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let output = Command::new(&program)
+        .env("LD_LIBRARY_PATH", lib_dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+#[ignore = "needs the wasm32-unknown-unknown target and Node, run with --ignored"]
+fn test_wasm_module() {
+    let output = Command::new("wasm/check.sh").output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("wasm: all tests passed"));
+}
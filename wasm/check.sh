@@ -0,0 +1,10 @@
+#!/bin/sh
+# Builds the WebAssembly module and runs the tests of its JavaScript API with Node.
+#
+# Needs the wasm32-unknown-unknown target, added with
+#
+#     rustup target add wasm32-unknown-unknown
+set -e
+cd "$(dirname "$0")/.."
+cargo build --release --target wasm32-unknown-unknown --lib
+node wasm/test.mjs target/wasm32-unknown-unknown/release/tp1_rust.wasm
@@ -0,0 +1,40 @@
+// Tests the JavaScript API against the WebAssembly module, run by `wasm/check.sh` as
+//
+//     node wasm/test.mjs target/wasm32-unknown-unknown/release/tp1_rust.wasm
+
+import assert from "node:assert/strict";
+import { readFile } from "node:fs/promises";
+import { load } from "./tp1_rust.js";
+
+const tp1 = await load(await readFile(process.argv[2]));
+
+// A black rook on d8 captures the white bishop on d1, which cannot reach it
+const lines = [
+  "_ _ _ T _ _ _ _",
+  ...Array(6).fill("_ _ _ _ _ _ _ _"),
+  "_ _ _ a _ _ _ _",
+];
+assert.deepEqual(tp1.parseTable(lines), {
+  pieces: [
+    { type: "T", color: "black", square: "d8" },
+    { type: "A", color: "white", square: "d1" },
+  ],
+  variant: "standard",
+});
+assert.deepEqual(tp1.checkMoves(lines), { white: false, black: true });
+assert.deepEqual(tp1.playGame(lines), { result: "N" });
+assert.deepEqual(tp1.playGame(lines.join("\n")), { result: "N" });
+
+// Two bare kings are drawn by a rule
+const kings = ["_ _ _ _ R _ _ _", ...Array(6).fill("_ _ _ _ _ _ _ _"), "_ _ _ _ r _ _ _"];
+assert.deepEqual(tp1.playGame(kings), { result: "T" });
+
+// Errors of the parser are thrown with their message
+assert.throws(() => tp1.playGame(["_ _"]), {
+  message: "ERROR: Table formated incorrectly. Table has 1 rows, expected 8",
+});
+assert.throws(() => tp1.checkMoves(lines.with(0, "_ _ _ x _ _ _ _")), {
+  message: "ERROR: Invalid piece: x",
+});
+
+console.log("wasm: all tests passed");
@@ -0,0 +1,72 @@
+// JavaScript API of the WebAssembly module of tp1_rust, built with
+//
+//     cargo build --release --target wasm32-unknown-unknown
+//
+// as target/wasm32-unknown-unknown/release/tp1_rust.wasm. `wasm/check.sh` builds it and
+// runs the tests of this API in `wasm/test.mjs` with Node.
+//
+// Boards are passed as the text of a table file or as an array of its lines, and the
+// answers are plain objects. Errors of the parser are thrown as `Error`s whose message is
+// the one printed by the command line, such as "ERROR: Invalid piece: x".
+//
+//     const tp1 = await load("tp1_rust.wasm");
+//     tp1.checkMoves(lines);  // { white: true, black: false }
+//     tp1.playGame(lines);    // { result: "B" }
+
+const encoder = new TextEncoder();
+const decoder = new TextDecoder();
+
+/**
+ * Loads the module from a URL, or from its bytes.
+ *
+ * @param {string | URL | BufferSource} source - Where the `.wasm` file is.
+ * @returns {Promise<{parseTable: Function, checkMoves: Function, playGame: Function}>}
+ */
+export async function load(source) {
+  const bytes =
+    typeof source === "string" || source instanceof URL
+      ? await (await fetch(source)).arrayBuffer()
+      : source;
+  const { instance } = await WebAssembly.instantiate(bytes, {});
+  const wasm = instance.exports;
+
+  // Calls an export on a board, and returns the object it answers
+  function call(name, board) {
+    const text = Array.isArray(board) ? board.join("\n") : String(board);
+    const input = encoder.encode(text);
+    const ptr = wasm.alloc(input.length);
+    new Uint8Array(wasm.memory.buffer, ptr, input.length).set(input);
+    const len = wasm[name](ptr, input.length);
+    wasm.dealloc(ptr, input.length);
+    const output = new Uint8Array(wasm.memory.buffer, wasm.result_ptr(), len);
+    const answer = JSON.parse(decoder.decode(output));
+    if (answer.error !== undefined) {
+      throw new Error(answer.error);
+    }
+    return answer;
+  }
+
+  return {
+    /**
+     * Parses a board as `parse_table` does.
+     *
+     * @returns {{pieces: {type: string, color: string, square: string}[], variant: string}}
+     */
+    parseTable: (board) => call("parse_table", board),
+
+    /**
+     * Tells which colors can capture a piece of the other one, as `check_moves` does.
+     *
+     * @returns {{white: boolean, black: boolean}}
+     */
+    checkMoves: (board) => call("check_moves", board),
+
+    /**
+     * Plays the game on a board as `play_game` does.
+     *
+     * @returns {{result: string}} "B" if white wins, "N" if black wins, "E" for a draw,
+     * "T" for a draw by a rule and "P" if both lose.
+     */
+    playGame: (board) => call("play_game", board),
+  };
+}